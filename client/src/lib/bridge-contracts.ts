@@ -2,19 +2,57 @@ import type { Chain, ChainConfig, BridgeEvent, CrossChainTransaction } from "@/t
 
 // Mock contract interfaces for demonstration
 export interface LockEvent {
+  // Canonical id, equal to the destination's release event for this transfer
+  transferId?: string;
   token: string;
+  // The signer, who any refund goes back to
   sender: string;
+  // Beneficiary to credit when the sender locked for someone else
+  onBehalfOf?: string;
   targetChain: string;
   targetAddr: string;
+  // Same as netAmount; older events carry only this field
   amount: string;
+  grossAmount: string;
+  fee: string;
+  // What the destination chain releases, grossAmount - fee
+  netAmount: string;
   nonce: string;
+  // Position in this token and target chain's own lock or burn stream,
+  // contiguous unlike the shared nonce
+  sequence?: string;
+  // Nonce of the first transfer when locked through `batch_lock`
+  batchId?: string;
+  // Sender reference written through the SPL Memo program at lock time
+  memo?: string;
+  // Delivery tier the sender paid for; relayers submit "priority" first
+  tier?: "standard" | "priority";
+  // Slot and cluster time the event was emitted at; absent on older events
+  slot?: string;
+  unixTimestamp?: number;
+  // Vault balance right after a lock, or the wrapped supply right after a
+  // burn, for solvency monitoring
+  vaultBalance?: string;
+  wrappedSupply?: string;
 }
 
 export interface MintEvent {
+  transferId?: string;
   sourceChain: string;
   sourceTx: string;
   to: string;
   amount: string;
+  slot?: string;
+  unixTimestamp?: number;
+  // Wrapped mint supply right after the mint
+  wrappedSupply?: string;
+}
+
+export interface WrappedOrigin {
+  // Hex of the 32-byte chain id and token address on the home chain
+  chainId: string;
+  tokenAddress: string;
+  decimals: number;
 }
 
 export class BridgeContract {
@@ -103,6 +141,83 @@ export class BridgeContract {
     // contract.on("Locked", (token, sender, targetChain, targetAddr, amount, nonce) => {
     //   callback({ token, sender, targetChain, targetAddr, amount, nonce });
     // });
+    //
+    // Solana `Locked` / `WrappedBurned` events are decoded with the program's
+    // coder, falling back to `amount` for events without the fee fields:
+    // program.addEventListener("Locked", (event) => {
+    //   const netAmount = (event.netAmount ?? event.amount).toString();
+    //   callback({
+    //     transferId: event.transferId && Buffer.from(event.transferId).toString("hex"),
+    //     token: event.token.toBase58(),
+    //     sender: event.source.toBase58(),
+    //     onBehalfOf: event.onBehalfOf?.toBase58(),
+    //     targetChain: Buffer.from(event.targetChain).toString("hex"),
+    //     targetAddr: Buffer.from(event.targetAddr).toString("hex"),
+    //     amount: netAmount,
+    //     grossAmount: (event.grossAmount ?? event.amount).toString(),
+    //     fee: (event.fee ?? 0).toString(),
+    //     netAmount,
+    //     nonce: event.nonce.toString(),
+    //     sequence: event.sequence?.toString(),
+    //     batchId: event.batchId?.toString(),
+    //     memo: event.memo ?? undefined,
+    //     tier: event.tier?.priority ? "priority" : "standard",
+    //     slot: event.slot?.toString(),
+    //     unixTimestamp: event.unixTimestamp?.toNumber(),
+    //     vaultBalance: event.vaultBalance?.toString(),
+    //     wrappedSupply: event.wrappedSupply?.toString(),
+    //   });
+    // });
+  }
+
+  async isProcessed(sourceChain: string, sourceTx: string): Promise<boolean> {
+    // Delivered transfers leave a record at a deterministic address, so this
+    // is a plain account lookup and never costs the relayer a transaction.
+    // In real implementation:
+    // const [record] = PublicKey.findProgramAddressSync(
+    //   [Buffer.from("processed"), sourceChainBytes, sourceTxBytes],
+    //   programId
+    // );
+    // return (await connection.getAccountInfo(record)) !== null;
+    //
+    // Or simulate the program's `check_processed` instruction and decode the
+    // returned bool from the simulation's return data.
+    return false;
+  }
+
+  async resolveWrappedOrigin(mint: string): Promise<WrappedOrigin | null> {
+    // Every wrapped mint has an origin record at a PDA derived from the mint
+    // alone, so no source chain or token needs to be known up front.
+    // In real implementation:
+    // const [originPda] = PublicKey.findProgramAddressSync(
+    //   [Buffer.from("origin"), new PublicKey(mint).toBuffer()],
+    //   programId
+    // );
+    // const origin = await program.account.wrappedOrigin.fetchNullable(originPda);
+    // if (!origin) return null;
+    // return {
+    //   chainId: Buffer.from(origin.sourceChain).toString("hex"),
+    //   tokenAddress: Buffer.from(origin.sourceToken).toString("hex"),
+    //   decimals: origin.sourceDecimals,
+    // };
+    return null;
+  }
+
+  async quoteFee(
+    token: string,
+    amount: string,
+    targetChain: Chain
+  ): Promise<{ gross: string; fee: string; net: string; feeBpsEffective: number }> {
+    // The program computes the quote with the same code path `lock` charges
+    // through, so the client never re-implements the fee math.
+    // In real implementation:
+    // const { returnData } = (await program.methods.quoteFee(
+    //   mint,
+    //   new anchor.BN(amount),
+    //   targetChainBytes
+    // ).accounts({...}).simulate()).raw;
+    // return decodeFeeQuote(returnData);
+    return { gross: amount, fee: "0", net: amount, feeBpsEffective: 0 };
   }
 
   async verifyTransaction(txHash: string): Promise<boolean> {
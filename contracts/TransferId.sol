@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title TransferId
+ * @dev Canonical id of a cross-chain transfer, identical to the Solana
+ * program's `transfer_id` and shared/transfer-id.ts:
+ * keccak256(sourceChain || destChain || sourceAddr || destAddr || token || amount || nonce)
+ */
+library TransferId {
+    /**
+     * @dev An address as a word: left-padded with zeroes when shorter than
+     * 32 bytes, hashed when longer
+     */
+    function word(bytes memory addr) internal pure returns (bytes32) {
+        if (addr.length > 32) {
+            return keccak256(addr);
+        }
+        return bytes32(uint256(bytes32(addr)) >> (8 * (32 - addr.length)));
+    }
+
+    /**
+     * @dev `token` is the asset's address on its home chain; `amount` is in
+     * the units the source event carries and `nonce` is the source nonce
+     */
+    function compute(
+        bytes32 sourceChain,
+        bytes32 destChain,
+        bytes memory sourceAddr,
+        bytes memory destAddr,
+        bytes memory token,
+        uint64 amount,
+        uint64 nonce
+    ) public pure returns (bytes32) {
+        return keccak256(
+            abi.encodePacked(
+                sourceChain,
+                destChain,
+                word(sourceAddr),
+                word(destAddr),
+                word(token),
+                amount,
+                nonce
+            )
+        );
+    }
+}
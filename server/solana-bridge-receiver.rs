@@ -0,0 +1,114 @@
+//! Minimal recipient for `release_with_call` / `mint_wrapped_with_call`.
+//!
+//! The bridge moves the tokens into an escrow owned by this program's
+//! `[b"bridge_escrow"]` PDA and then calls `on_bridge_receive`. Anyone can
+//! call that entrypoint directly too, so the receiver checks that the
+//! top-level instruction belongs to the bridge before trusting the payload.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, get_instruction_relative,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+declare_id!("BRRcvr11111111111111111111111111111111111111");
+
+/// Program id of the bridge, the only caller whose deliveries are accepted
+pub const BRIDGE_PROGRAM_ID: Pubkey = pubkey!("BR1dg3Prog1111111111111111111111111111111111");
+
+#[program]
+pub mod bridge_receiver {
+    use super::*;
+
+    pub fn on_bridge_receive(
+        ctx: Context<OnBridgeReceive>,
+        payload: Vec<u8>,
+        amount: u64,
+        source_chain: [u8; 32],
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        let outer = get_instruction_relative(0, &ctx.accounts.instructions)?;
+        require_keys_eq!(outer.program_id, BRIDGE_PROGRAM_ID, ReceiverError::NotFromBridge);
+        require!(ctx.accounts.escrow.amount >= amount, ReceiverError::EscrowShort);
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.deliveries += 1;
+        receipt.last_amount = amount;
+        receipt.last_source_chain = source_chain;
+        receipt.last_source_addr = source_addr;
+        receipt.bump = ctx.bumps.receipt;
+
+        emit!(BridgeReceived {
+            mint: ctx.accounts.mint.key(),
+            amount,
+            source_chain,
+            source_addr,
+            payload,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct OnBridgeReceive<'info> {
+    /// Passed first by the bridge, already credited with `amount`
+    #[account(
+        constraint = escrow.owner == escrow_authority.key(),
+        constraint = escrow.mint == mint.key()
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: This program's escrow authority
+    #[account(seeds = [b"bridge_escrow"], bump)]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// Forwarded by the bridge from the delivering transaction's remaining accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Receipt::INIT_SPACE,
+        seeds = [b"receipt", mint.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    /// CHECK: Instructions sysvar, read to check the caller
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Running tally of deliveries per mint
+#[account]
+#[derive(InitSpace)]
+pub struct Receipt {
+    pub deliveries: u64,
+    pub last_amount: u64,
+    pub last_source_chain: [u8; 32],
+    pub last_source_addr: [u8; 32],
+    pub bump: u8,
+}
+
+#[event]
+pub struct BridgeReceived {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub source_chain: [u8; 32],
+    pub source_addr: [u8; 32],
+    pub payload: Vec<u8>,
+}
+
+#[error_code]
+pub enum ReceiverError {
+    #[msg("Only deliveries made by the bridge are accepted")]
+    NotFromBridge,
+    #[msg("Escrow holds less than the delivered amount")]
+    EscrowShort,
+}
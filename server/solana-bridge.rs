@@ -1,317 +1,15295 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint, MintTo, Burn};
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke, set_return_data};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::{ed25519_program, keccak, secp256k1_program};
+use std::collections::BTreeSet;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::system_program::{self, CreateAccount};
+use anchor_spl::associated_token::{
+    self, get_associated_token_address_with_program_id, AssociatedToken, Create,
+};
+use anchor_spl::metadata::mpl_token_metadata::{
+    self,
+    types::{Creator, DataV2, TokenStandard},
+};
+use anchor_spl::metadata::{
+    create_metadata_accounts_v3, update_metadata_accounts_v2, CreateMetadataAccountsV3, Metadata,
+    MetadataAccount, UpdateMetadataAccountsV2,
+};
+// Guardian staking stays on the classic token program; bridged tokens may be
+// either program
+use anchor_spl::token::{self, spl_token::native_mint, Token};
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    default_account_state::DefaultAccountState, permanent_delegate::PermanentDelegate,
+    transfer_fee::TransferFeeConfig, transfer_hook, BaseStateWithExtensions, ExtensionType,
+    StateWithExtensions,
+};
+use anchor_spl::token_interface::{
+    self, spl_token_2022, Burn, GetAccountDataSize, InitializeAccount3, InitializeMint2, Mint,
+    CloseAccount, MintTo, SetAuthority, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
+use anchor_spl::token_interface::spl_token_2022::instruction::AuthorityType;
 
 declare_id!("BR1dg3Prog1111111111111111111111111111111111");
 
+/// Layout version of `BridgeState` this build reads and writes.
+pub const CURRENT_STATE_VERSION: u8 = 3;
+
+/// Capacity of the replay-protection list in the legacy `BridgeState` layouts.
+pub const MAX_PROCESSED: usize = 256;
+
+/// Bytes of nonce bits held by one `ReplayBitmap` page.
+pub const REPLAY_BITMAP_BYTES: usize = 128;
+
+/// Source nonces covered by one `ReplayBitmap` page.
+pub const NONCES_PER_BITMAP_PAGE: u64 = REPLAY_BITMAP_BYTES as u64 * 8;
+
+/// Largest decimals gap between a foreign token and its wrapped mint; keeps
+/// the scale factor within `u64`.
+pub const MAX_DECIMALS_DIFF: u8 = 18;
+
+/// Upper bound on the number of guardians in a `GuardianSet`.
+pub const MAX_GUARDIANS: usize = 19;
+
+/// Longest window, in slots, for which a rotated-out guardian set keeps attesting.
+pub const MAX_GUARDIAN_SET_GRACE_SLOTS: u64 = 432_000;
+
+/// Upper bound on the number of watchers in the `WatcherSet`.
+pub const MAX_WATCHERS: usize = 16;
+
+/// Upper bound on the number of relayers in the `RelayerRegistry`.
+pub const MAX_RELAYERS: usize = 16;
+
+/// Denominator for all basis-point rates.
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Hard ceiling for `BridgeConfig::max_target_addr_len`.
+pub const MAX_TARGET_ADDR_LEN: u8 = 128;
+
+/// Target address bound applied until the admin changes it.
+pub const DEFAULT_MAX_TARGET_ADDR_LEN: u8 = 64;
+
+/// Chain id this program's transfers carry as their source: Solana's
+/// Wormhole chain id, 1, big-endian in the last two bytes.
+pub const LOCAL_CHAIN_ID: [u8; 32] = {
+    let mut id = [0u8; 32];
+    id[31] = 1;
+    id
+};
+
+/// Length of a raw EVM account address.
+pub const EVM_ADDRESS_LEN: u8 = 20;
+
+/// Length of a Solana-style ed25519 account address.
+pub const SOLANA_ADDRESS_LEN: u8 = 32;
+
+/// Longest human-readable prefix a bech32 route may register.
+pub const MAX_BECH32_HRP_LEN: usize = 16;
+
+/// Characters of a bech32 data part taken by the checksum.
+pub const BECH32_CHECKSUM_LEN: usize = 6;
+
+/// Longest bech32 string, prefix and separator included, that BIP-173 allows.
+pub const MAX_BECH32_LEN: usize = 90;
+
+/// Number of slot buckets making up a token's circuit-breaker window.
+pub const CIRCUIT_BREAKER_BUCKETS: usize = 6;
+
+/// Hourly buckets making up the rolling window of `TokenRegistry::daily_limit`
+pub const DAILY_LIMIT_BUCKETS: usize = 24;
+
+/// Slots per daily-limit bucket, about an hour at 400ms slots
+pub const DAILY_LIMIT_BUCKET_SLOTS: u64 = 9_000;
+
+/// How long a `ProcessedRecord` is kept before it may be closed, until the admin changes it.
+pub const DEFAULT_PROCESSED_RETENTION_SLOTS: u64 = 216_000;
+
+/// Cranker's share of the rent reclaimed by `prune_processed`, until the admin changes it.
+pub const DEFAULT_PRUNE_INCENTIVE_BPS: u16 = 500;
+
+/// Most `ProcessedRecord`s one `prune_processed` call will look at.
+pub const MAX_PRUNE_BATCH: u8 = 8;
+
+/// Most tokens one `batch_lock` will send; each entry runs a full `lock`.
+pub const MAX_LOCK_BATCH: usize = 4;
+
+/// Most attested transfers one `batch_release` will deliver; eight entries
+/// of four accounts each still fit one transaction through a lookup table.
+pub const MAX_RELEASE_BATCH: usize = 8;
+
+/// Floor for `BridgeConfig::emergency_withdraw_delay_slots`, about 7 days of slots.
+pub const MIN_EMERGENCY_WITHDRAW_DELAY_SLOTS: u64 = 1_512_000;
+
+/// Floor for a non-zero `BridgeConfig::refund_after_slots`, about 2 days of
+/// slots, well past any normal attestation time.
+pub const MIN_REFUND_AFTER_SLOTS: u64 = 432_000;
+
+/// Seconds past a lock's deadline before `refund_expired_lock` accepts it
+/// early, covering clock drift and finality on the destination.
+pub const DEADLINE_REFUND_GRACE_SECS: i64 = 3_600;
+
+/// Slots between `cancel_lock` and `refund_cancelled_lock`, giving guardians
+/// time to observe the cancellation.
+pub const LOCK_CANCEL_DELAY_SLOTS: u64 = 150;
+
+/// Longest memo a transfer may carry, in bytes.
+pub const MAX_MEMO_LEN: usize = 64;
+
+/// SPL Memo program, which delivered memos are written through.
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Floor for a non-zero `BridgeConfig::optimistic_window_slots`, about 20
+/// seconds, enough for guardians to see the claim and answer it.
+pub const MIN_OPTIMISTIC_WINDOW_SLOTS: u64 = 50;
+
+/// Deepest inclusion proof `release_with_proof` accepts, enough for a root
+/// over 65,536 transfers.
+pub const MAX_MERKLE_PROOF_DEPTH: usize = 16;
+
+/// Wormhole core bridge, whose posted-VAA accounts `mint_wrapped_from_vaa`
+/// accepts in place of guardian signatures.
+pub const WORMHOLE_CORE_BRIDGE_ID: Pubkey = pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+
+/// Virtual shares and assets LP share prices are computed with, so an empty
+/// pool still has a price and no deposit can round a later one to nothing.
+pub const LP_VIRTUAL_SHARES: u64 = 1;
+pub const LP_VIRTUAL_ASSETS: u64 = 1;
+
+/// Hard ceiling for `BridgeConfig::max_memo_len`.
+pub const MAX_LOCK_MEMO_LEN: u16 = 256;
+
+/// Hard ceiling for `BridgeConfig::max_payload_len`; a payload has to fit in
+/// one transaction alongside the lock accounts.
+pub const MAX_PAYLOAD_LEN: u16 = 768;
+
+/// `register_token` flag accepting a Token-2022 mint with a transfer hook.
+pub const ALLOW_TRANSFER_HOOK: u8 = 1 << 0;
+
+/// `register_token` flag accepting a Token-2022 mint whose new accounts start frozen.
+pub const ALLOW_DEFAULT_FROZEN: u8 = 1 << 1;
+
+/// `register_token` flag accepting a Token-2022 mint whose permanent delegate
+/// can move vault funds.
+pub const ALLOW_PERMANENT_DELEGATE: u8 = 1 << 2;
+
+/// Every flag `register_token` understands.
+pub const ALLOWED_EXTENSION_FLAGS: u8 =
+    ALLOW_TRANSFER_HOOK | ALLOW_DEFAULT_FROZEN | ALLOW_PERMANENT_DELEGATE;
+
+/// Leads every digest this bridge has signed, so its messages cannot be
+/// passed off as another protocol's that shares the keys.
+pub const SIGNING_DOMAIN: &[u8] = b"YHGS-Bridge/solana/v1";
+
 #[program]
 pub mod bridge {
     use super::*;
 
-    pub fn lock(
-        ctx: Context<Lock>, 
-        amount: u64, 
-        target_chain: [u8; 32], 
-        target_addr: Vec<u8>
-    ) -> Result<()> {
-        require!(amount > 0, BridgeError::InvalidAmount);
-        require!(target_addr.len() <= 64, BridgeError::InvalidTargetAddress);
+    pub fn initialize(ctx: Context<Initialize>, admin: Pubkey) -> Result<()> {
+        let space = BridgeState::SPACE;
+        let bump = ctx.bumps.bridge_state;
+        let seeds: &[&[u8]] = &[b"bridge_state", &[bump]];
 
-        // Transfer tokens to bridge vault
-        token::transfer(ctx.accounts.into_transfer_context(), amount)?;
-        
-        let bridge_state = &mut ctx.accounts.bridge_state;
-        bridge_state.nonce += 1;
+        create_pda_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.system_program,
+            space,
+            &crate::ID,
+            seeds,
+        )?;
 
-        emit!(Locked {
-            source: *ctx.accounts.user.key,
-            token: ctx.accounts.token_mint.key(),
-            amount,
-            target_chain,
-            target_addr,
-            nonce: bridge_state.nonce,
-            slot: ctx.accounts.clock.slot,
+        BridgeState::new(admin, 0, 0).write(&ctx.accounts.bridge_state)?;
+
+        let config = &mut ctx.accounts.config;
+        config.fee_bps = 0;
+        config.min_amount = 0;
+        config.max_amount = 0;
+        config.release_delay = 0;
+        config.max_target_addr_len = DEFAULT_MAX_TARGET_ADDR_LEN;
+        config.large_release_threshold = 0;
+        config.large_release_delay_slots = 0;
+        config.emergency_withdraw_delay_slots = MIN_EMERGENCY_WITHDRAW_DELAY_SLOTS;
+        config.retention_slots = DEFAULT_PROCESSED_RETENTION_SLOTS;
+        config.rent_collector = admin;
+        config.prune_incentive_bps = DEFAULT_PRUNE_INCENTIVE_BPS;
+        config.fee_collector = admin;
+        config.max_native_drop = 0;
+        config.referral_share_bps = 0;
+        config.treasury_share_bps = BPS_DENOMINATOR;
+        config.insurance_share_bps = 0;
+        config.max_locks_per_epoch = 0;
+        config.max_volume_per_epoch = 0;
+        config.max_payload_len = 0;
+        config.refund_after_slots = 0;
+        config.max_memo_len = 0;
+        config.optimistic_window_slots = 0;
+        config.optimistic_bond_bps = BPS_DENOMINATOR;
+        config.priority_fee_bps = 0;
+        config.compliance_authority = admin;
+        config.bump = ctx.bumps.config;
+
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        guardian_set.index = 0;
+        guardian_set.guardians = Vec::new();
+        guardian_set.threshold = 0;
+        guardian_set.expires_at_slot = 0;
+        guardian_set.bump = ctx.bumps.guardian_set;
+
+        emit!(BridgeInitialized {
+            bridge_state: ctx.accounts.bridge_state.key(),
+            admin,
+            payer: ctx.accounts.payer.key(),
         });
-        
+
         Ok(())
     }
 
-    pub fn release(
-        ctx: Context<Release>, 
-        amount: u64, 
-        source_tx: [u8; 32]
+    pub fn migrate_processed(
+        ctx: Context<MigrateProcessed>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
     ) -> Result<()> {
-        let bridge_state = &mut ctx.accounts.bridge_state;
-        
-        require!(
-            !bridge_state.processed.contains(&source_tx), 
-            BridgeError::AlreadyProcessed
-        );
-        
-        bridge_state.processed.push(source_tx);
-        
-        // Transfer from vault to user
-        let seeds = &[b"vault", &[ctx.bumps.vault]];
-        let signer = &[&seeds[..]];
-        
-        token::transfer(
-            ctx.accounts.into_transfer_context().with_signer(signer), 
-            amount
+        let info = ctx.accounts.bridge_state.to_account_info();
+        let (version, mut legacy) = LegacyBridgeState::load(&info)?;
+
+        let position = legacy
+            .processed
+            .iter()
+            .position(|hash| *hash == source_tx)
+            .ok_or(BridgeError::LegacyRecordNotFound)?;
+        legacy.processed.swap_remove(position);
+        legacy.store(&info, version)?;
+
+        let slot = Clock::get()?.slot;
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            legacy.guardian_set_index,
+            slot,
         )?;
-        
-        emit!(Released {
-            recipient: *ctx.accounts.user.key,
-            amount,
+
+        emit!(ProcessedRecordMigrated {
+            source_chain,
             source_tx,
+            remaining: legacy.processed.len() as u32,
+            slot,
         });
-        
+
         Ok(())
     }
 
-    pub fn mint_wrapped(
-        ctx: Context<MintWrapped>,
-        amount: u64,
-        source_tx: [u8; 32],
-        source_chain: [u8; 32]
-    ) -> Result<()> {
-        let bridge_state = &mut ctx.accounts.bridge_state;
-        
-        require!(
-            !bridge_state.processed.contains(&source_tx),
-            BridgeError::AlreadyProcessed
-        );
-        
-        bridge_state.processed.push(source_tx);
-        
-        // Mint wrapped tokens
-        let seeds = &[b"wrapped_mint", source_chain.as_ref(), &[ctx.bumps.wrapped_mint]];
-        let signer = &[&seeds[..]];
-        
-        token::mint_to(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                MintTo {
-                    mint: ctx.accounts.wrapped_mint.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.wrapped_mint.to_account_info(),
-                },
-                signer
-            ),
-            amount
+    /// Rewrites an older `BridgeState` at `CURRENT_STATE_VERSION`. A legacy
+    /// processed list must first be drained into `ProcessedRecord`s with
+    /// `migrate_processed`, since that list no longer exists afterwards.
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let info = ctx.accounts.bridge_state.to_account_info();
+        let (version, prior) = if BridgeStateV2::matches(&info)? {
+            (2, BridgeStateV2::load(&info)?)
+        } else {
+            let (version, legacy) = LegacyBridgeState::load(&info)?;
+            require!(legacy.processed.is_empty(), BridgeError::LegacyProcessedPending);
+            (version, legacy.into_v2())
+        };
+
+        let mut state = BridgeState::new(prior.admin, prior.guardian_set_index, prior.nonce);
+        state.set_pending_admin(prior.pending_admin);
+        state.paused = prior.paused as u8;
+
+        resize_account(
+            &ctx.accounts.payer,
+            &info,
+            &ctx.accounts.system_program,
+            BridgeState::SPACE,
         )?;
-        
-        emit!(WrappedMinted {
-            recipient: *ctx.accounts.user.key,
-            wrapped_mint: ctx.accounts.wrapped_mint.key(),
-            amount,
-            source_tx,
-            source_chain,
+        state.write(&info)?;
+
+        emit!(StateMigrated {
+            from_version: version,
+            to_version: CURRENT_STATE_VERSION,
+            authority: ctx.accounts.upgrade_authority.key(),
+            slot: Clock::get()?.slot,
         });
-        
+
         Ok(())
     }
 
-    pub fn burn_wrapped(
-        ctx: Context<BurnWrapped>,
-        amount: u64,
-        target_chain: [u8; 32],
-        target_addr: Vec<u8>
-    ) -> Result<()> {
-        require!(amount > 0, BridgeError::InvalidAmount);
-        
-        // Burn wrapped tokens
-        token::burn(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Burn {
-                    mint: ctx.accounts.wrapped_mint.to_account_info(),
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                }
-            ),
-            amount
+    pub fn initialize_vault(ctx: Context<InitializeVault>) -> Result<()> {
+        let mint_key = ctx.accounts.token_mint.key();
+        let bump = ctx.bumps.vault;
+        let seeds: &[&[u8]] = &[b"vault", mint_key.as_ref(), &[bump]];
+
+        // The vault is its own authority so release can sign with the PDA seeds
+        create_self_owned_token_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            seeds,
         )?;
-        
-        let bridge_state = &mut ctx.accounts.bridge_state;
-        bridge_state.nonce += 1;
-        
-        emit!(WrappedBurned {
-            source: *ctx.accounts.user.key,
-            wrapped_mint: ctx.accounts.wrapped_mint.key(),
-            amount,
-            target_chain,
-            target_addr,
-            nonce: bridge_state.nonce,
+
+        emit!(VaultInitialized {
+            mint: mint_key,
+            vault: ctx.accounts.vault.key(),
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
         });
-        
+
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Lock<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = user_token_account.owner == user.key(),
-        constraint = user_token_account.mint == token_mint.key()
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault", token_mint.key().as_ref()],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    
-    pub token_mint: Account<'info, Mint>,
-    
-    #[account(mut, seeds = [b"bridge_state"], bump)]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub clock: Sysvar<'info, Clock>,
-    pub token_program: Program<'info, Token>,
-}
+    pub fn initialize_fee_vault(ctx: Context<InitializeFeeVault>) -> Result<()> {
+        let mint_key = ctx.accounts.token_mint.key();
+        let bump = ctx.bumps.fee_vault;
+        let seeds: &[&[u8]] = &[b"fee_vault", mint_key.as_ref(), &[bump]];
 
-#[derive(Accounts)]
-pub struct Release<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault"],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    
-    #[account(mut, seeds = [b"bridge_state"], bump)]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        create_self_owned_token_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.fee_vault,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            seeds,
+        )?;
 
-#[derive(Accounts)]
-pub struct MintWrapped<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        seeds = [b"wrapped_mint", source_chain.as_ref()],
-        bump
-    )]
-    pub wrapped_mint: Account<'info, Mint>,
-    
-    /// CHECK: Used for seeding only
-    pub source_chain: AccountInfo<'info>,
-    
-    #[account(mut, seeds = [b"bridge_state"], bump)]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        emit!(FeeVaultInitialized {
+            mint: mint_key,
+            fee_vault: ctx.accounts.fee_vault.key(),
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
 
-#[derive(Accounts)]
-pub struct BurnWrapped<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub wrapped_mint: Account<'info, Mint>,
-    
-    #[account(mut, seeds = [b"bridge_state"], bump)]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub token_program: Program<'info, Token>,
-}
+        Ok(())
+    }
 
-#[account]
-pub struct BridgeState {
-    pub nonce: u64,
-    pub processed: Vec<[u8; 32]>,
-}
+    pub fn initialize_insurance_vault(ctx: Context<InitializeInsuranceVault>) -> Result<()> {
+        let mint_key = ctx.accounts.token_mint.key();
+        let bump = ctx.bumps.insurance_vault;
+        let seeds: &[&[u8]] = &[b"insurance_vault", mint_key.as_ref(), &[bump]];
 
-impl<'info> Lock<'info> {
-    fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        CpiContext::new(
-            self.token_program.to_account_info(),
-            Transfer {
-                from: self.user_token_account.to_account_info(),
-                to: self.vault.to_account_info(),
-                authority: self.user.to_account_info(),
-            }
-        )
-    }
-}
+        create_self_owned_token_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.insurance_vault,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            seeds,
+        )?;
 
-impl<'info> Release<'info> {
-    fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        CpiContext::new(
-            self.token_program.to_account_info(),
-            Transfer {
-                from: self.vault.to_account_info(),
-                to: self.user_token_account.to_account_info(),
-                authority: self.vault.to_account_info(),
-            }
-        )
+        emit!(InsuranceVaultInitialized {
+            mint: mint_key,
+            insurance_vault: ctx.accounts.insurance_vault.key(),
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
     }
-}
 
-#[event]
-pub struct Locked {
-    pub source: Pubkey,
-    pub token: Pubkey,
-    pub amount: u64,
-    pub target_chain: [u8; 32],
-    pub target_addr: Vec<u8>,
-    pub nonce: u64,
-    pub slot: u64,
-}
+    pub fn update_config(ctx: Context<UpdateConfig>, update: ConfigUpdate) -> Result<()> {
+        let admin = ctx.accounts.admin.key();
+        let slot = Clock::get()?.slot;
+        let config = &mut ctx.accounts.config;
+        let old = (**config).clone();
 
-#[event]
-pub struct Released {
-    pub recipient: Pubkey,
-    pub amount: u64,
-    pub source_tx: [u8; 32],
-}
+        if let Some(fee_bps) = update.fee_bps {
+            config.fee_bps = fee_bps;
+        }
+        if let Some(min_amount) = update.min_amount {
+            config.min_amount = min_amount;
+        }
+        if let Some(max_amount) = update.max_amount {
+            config.max_amount = max_amount;
+        }
+        if let Some(release_delay) = update.release_delay {
+            config.release_delay = release_delay;
+        }
+        if let Some(max_target_addr_len) = update.max_target_addr_len {
+            config.max_target_addr_len = max_target_addr_len;
+        }
+        if let Some(large_release_threshold) = update.large_release_threshold {
+            config.large_release_threshold = large_release_threshold;
+        }
+        if let Some(large_release_delay_slots) = update.large_release_delay_slots {
+            config.large_release_delay_slots = large_release_delay_slots;
+        }
+        if let Some(emergency_withdraw_delay_slots) = update.emergency_withdraw_delay_slots {
+            config.emergency_withdraw_delay_slots = emergency_withdraw_delay_slots;
+        }
+        if let Some(retention_slots) = update.retention_slots {
+            config.retention_slots = retention_slots;
+        }
+        if let Some(rent_collector) = update.rent_collector {
+            config.rent_collector = rent_collector;
+        }
+        if let Some(prune_incentive_bps) = update.prune_incentive_bps {
+            config.prune_incentive_bps = prune_incentive_bps;
+        }
+        if let Some(fee_collector) = update.fee_collector {
+            config.fee_collector = fee_collector;
+        }
+        if let Some(max_native_drop) = update.max_native_drop {
+            config.max_native_drop = max_native_drop;
+        }
+        if let Some(referral_share_bps) = update.referral_share_bps {
+            config.referral_share_bps = referral_share_bps;
+        }
+        if let Some(treasury_share_bps) = update.treasury_share_bps {
+            config.treasury_share_bps = treasury_share_bps;
+        }
+        if let Some(insurance_share_bps) = update.insurance_share_bps {
+            config.insurance_share_bps = insurance_share_bps;
+        }
+        if let Some(max_locks_per_epoch) = update.max_locks_per_epoch {
+            config.max_locks_per_epoch = max_locks_per_epoch;
+        }
+        if let Some(max_volume_per_epoch) = update.max_volume_per_epoch {
+            config.max_volume_per_epoch = max_volume_per_epoch;
+        }
+        if let Some(max_payload_len) = update.max_payload_len {
+            config.max_payload_len = max_payload_len;
+        }
+        if let Some(refund_after_slots) = update.refund_after_slots {
+            config.refund_after_slots = refund_after_slots;
+        }
+        if let Some(max_memo_len) = update.max_memo_len {
+            config.max_memo_len = max_memo_len;
+        }
+        if let Some(optimistic_window_slots) = update.optimistic_window_slots {
+            config.optimistic_window_slots = optimistic_window_slots;
+        }
+        if let Some(optimistic_bond_bps) = update.optimistic_bond_bps {
+            config.optimistic_bond_bps = optimistic_bond_bps;
+        }
+        if let Some(priority_fee_bps) = update.priority_fee_bps {
+            config.priority_fee_bps = priority_fee_bps;
+        }
+        if let Some(compliance_authority) = update.compliance_authority {
+            config.compliance_authority = compliance_authority;
+        }
+        config.validate()?;
 
-#[event]
-pub struct WrappedMinted {
-    pub recipient: Pubkey,
-    pub wrapped_mint: Pubkey,
-    pub amount: u64,
-    pub source_tx: [u8; 32],
-    pub source_chain: [u8; 32],
+        if update.fee_bps.is_some() {
+            emit!(FeeUpdated {
+                admin,
+                old_fee_bps: old.fee_bps,
+                new_fee_bps: config.fee_bps,
+                slot,
+            });
+        }
+        if update.min_amount.is_some() || update.max_amount.is_some() {
+            emit!(LimitUpdated {
+                admin,
+                old_min_amount: old.min_amount,
+                old_max_amount: old.max_amount,
+                new_min_amount: config.min_amount,
+                new_max_amount: config.max_amount,
+                slot,
+            });
+        }
+        if update.release_delay.is_some() {
+            emit!(ReleaseDelayUpdated {
+                admin,
+                old_release_delay: old.release_delay,
+                new_release_delay: config.release_delay,
+                slot,
+            });
+        }
+        if update.max_target_addr_len.is_some() {
+            emit!(MaxTargetAddrLenUpdated {
+                admin,
+                old_max_target_addr_len: old.max_target_addr_len,
+                new_max_target_addr_len: config.max_target_addr_len,
+                slot,
+            });
+        }
+        if update.large_release_threshold.is_some() || update.large_release_delay_slots.is_some() {
+            emit!(LargeReleaseUpdated {
+                admin,
+                old_threshold: old.large_release_threshold,
+                old_delay_slots: old.large_release_delay_slots,
+                new_threshold: config.large_release_threshold,
+                new_delay_slots: config.large_release_delay_slots,
+                slot,
+            });
+        }
+        if update.emergency_withdraw_delay_slots.is_some() {
+            emit!(EmergencyWithdrawDelayUpdated {
+                admin,
+                old_delay_slots: old.emergency_withdraw_delay_slots,
+                new_delay_slots: config.emergency_withdraw_delay_slots,
+                slot,
+            });
+        }
+        if update.retention_slots.is_some()
+            || update.rent_collector.is_some()
+            || update.prune_incentive_bps.is_some()
+        {
+            emit!(RetentionUpdated {
+                admin,
+                old_retention_slots: old.retention_slots,
+                old_rent_collector: old.rent_collector,
+                old_prune_incentive_bps: old.prune_incentive_bps,
+                new_retention_slots: config.retention_slots,
+                new_rent_collector: config.rent_collector,
+                new_prune_incentive_bps: config.prune_incentive_bps,
+                slot,
+            });
+        }
+        if update.fee_collector.is_some() {
+            emit!(FeeCollectorUpdated {
+                admin,
+                old_fee_collector: old.fee_collector,
+                new_fee_collector: config.fee_collector,
+                slot,
+            });
+        }
+        if update.max_native_drop.is_some() {
+            emit!(MaxNativeDropUpdated {
+                admin,
+                old_max_native_drop: old.max_native_drop,
+                new_max_native_drop: config.max_native_drop,
+                slot,
+            });
+        }
+        if update.referral_share_bps.is_some() {
+            emit!(ReferralShareUpdated {
+                admin,
+                old_referral_share_bps: old.referral_share_bps,
+                new_referral_share_bps: config.referral_share_bps,
+                slot,
+            });
+        }
+        if update.treasury_share_bps.is_some() || update.insurance_share_bps.is_some() {
+            emit!(FeeSplitUpdated {
+                admin,
+                old_treasury_share_bps: old.treasury_share_bps,
+                old_insurance_share_bps: old.insurance_share_bps,
+                new_treasury_share_bps: config.treasury_share_bps,
+                new_insurance_share_bps: config.insurance_share_bps,
+                slot,
+            });
+        }
+        if update.max_locks_per_epoch.is_some() || update.max_volume_per_epoch.is_some() {
+            emit!(RateLimitUpdated {
+                admin,
+                old_max_locks_per_epoch: old.max_locks_per_epoch,
+                old_max_volume_per_epoch: old.max_volume_per_epoch,
+                new_max_locks_per_epoch: config.max_locks_per_epoch,
+                new_max_volume_per_epoch: config.max_volume_per_epoch,
+                slot,
+            });
+        }
+        if update.max_payload_len.is_some() {
+            emit!(MaxPayloadLenUpdated {
+                admin,
+                old_max_payload_len: old.max_payload_len,
+                new_max_payload_len: config.max_payload_len,
+                slot,
+            });
+        }
+        if update.refund_after_slots.is_some() {
+            emit!(RefundAfterSlotsUpdated {
+                admin,
+                old_refund_after_slots: old.refund_after_slots,
+                new_refund_after_slots: config.refund_after_slots,
+                slot,
+            });
+        }
+        if update.max_memo_len.is_some() {
+            emit!(MaxMemoLenUpdated {
+                admin,
+                old_max_memo_len: old.max_memo_len,
+                new_max_memo_len: config.max_memo_len,
+                slot,
+            });
+        }
+        if update.optimistic_window_slots.is_some() || update.optimistic_bond_bps.is_some() {
+            emit!(OptimisticReleaseConfigUpdated {
+                admin,
+                old_window_slots: old.optimistic_window_slots,
+                new_window_slots: config.optimistic_window_slots,
+                old_bond_bps: old.optimistic_bond_bps,
+                new_bond_bps: config.optimistic_bond_bps,
+                slot,
+            });
+        }
+        if update.priority_fee_bps.is_some() {
+            emit!(PriorityFeeUpdated {
+                admin,
+                old_priority_fee_bps: old.priority_fee_bps,
+                new_priority_fee_bps: config.priority_fee_bps,
+                slot,
+            });
+        }
+        if update.compliance_authority.is_some() {
+            emit!(ComplianceAuthorityUpdated {
+                admin,
+                old_compliance_authority: old.compliance_authority,
+                new_compliance_authority: config.compliance_authority,
+                slot,
+            });
+        }
+
+        emit!(ConfigUpdated {
+            admin,
+            old,
+            new: (**config).clone(),
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_guardian(ctx: Context<AddGuardian>, guardian: GuardianKey) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+
+        require!(
+            !guardian_set.guardians.contains(&guardian),
+            BridgeError::DuplicateGuardian
+        );
+        require!(
+            guardian_set.guardians.len() < MAX_GUARDIANS,
+            BridgeError::GuardianSetFull
+        );
+        guardian_set.guardians.push(guardian.clone());
+
+        emit!(GuardianAdded {
+            admin: ctx.accounts.admin.key(),
+            guardian,
+            guardian_set_index: guardian_set.index,
+            guardian_count: guardian_set.guardians.len() as u8,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_guardian(ctx: Context<UpdateGuardianSet>, guardian: GuardianKey) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+
+        let position = guardian_set
+            .guardians
+            .iter()
+            .position(|g| *g == guardian)
+            .ok_or(BridgeError::GuardianNotFound)?;
+        require!(
+            guardian_set.guardians.len() > guardian_set.threshold as usize,
+            BridgeError::InvalidThreshold
+        );
+        guardian_set.guardians.remove(position);
+
+        emit!(GuardianRemoved {
+            admin: ctx.accounts.admin.key(),
+            guardian,
+            guardian_set_index: guardian_set.index,
+            guardian_count: guardian_set.guardians.len() as u8,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_threshold(ctx: Context<UpdateGuardianSet>, threshold: u8) -> Result<()> {
+        let guardian_set = &mut ctx.accounts.guardian_set;
+
+        require!(
+            threshold > 0 && threshold as usize <= guardian_set.guardians.len(),
+            BridgeError::InvalidThreshold
+        );
+        let old_threshold = guardian_set.threshold;
+        guardian_set.threshold = threshold;
+
+        emit!(GuardianThresholdUpdated {
+            admin: ctx.accounts.admin.key(),
+            guardian_set_index: guardian_set.index,
+            old_threshold,
+            new_threshold: threshold,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn rotate_guardian_set(
+        ctx: Context<RotateGuardianSet>,
+        guardians: Vec<GuardianKey>,
+        threshold: u8,
+        grace_slots: u64,
+    ) -> Result<()> {
+        require!(
+            grace_slots <= MAX_GUARDIAN_SET_GRACE_SLOTS,
+            BridgeError::InvalidConfig
+        );
+        require!(guardians.len() <= MAX_GUARDIANS, BridgeError::GuardianSetFull);
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                !guardians[..i].contains(guardian),
+                BridgeError::DuplicateGuardian
+            );
+        }
+        require!(
+            threshold > 0 && threshold as usize <= guardians.len(),
+            BridgeError::InvalidThreshold
+        );
+
+        // One stake account per incoming guardian, in the same order
+        require!(
+            ctx.remaining_accounts.len() == guardians.len(),
+            BridgeError::GuardianNotStaked
+        );
+        for (guardian, stake_info) in guardians.iter().zip(ctx.remaining_accounts.iter()) {
+            check_guardian_stake(stake_info, guardian, &ctx.accounts.staking_config)?;
+        }
+
+        let slot = Clock::get()?.slot;
+        let old_set = &mut ctx.accounts.current_guardian_set;
+        old_set.expires_at_slot = slot.checked_add(grace_slots).ok_or(BridgeError::CounterOverflow)?;
+
+        let new_set = &mut ctx.accounts.new_guardian_set;
+        new_set.index = old_set.index.checked_add(1).ok_or(BridgeError::CounterOverflow)?;
+        new_set.guardians = guardians;
+        new_set.threshold = threshold;
+        new_set.expires_at_slot = 0;
+        new_set.bump = ctx.bumps.new_guardian_set;
+
+        ctx.accounts.bridge_state.load_mut()?.guardian_set_index = new_set.index;
+
+        emit!(GuardianSetRotated {
+            admin: ctx.accounts.admin.key(),
+            old_index: old_set.index,
+            old_expires_at_slot: old_set.expires_at_slot,
+            new_index: new_set.index,
+            guardian_count: new_set.guardians.len() as u8,
+            threshold,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_guardian_set(ctx: Context<CloseGuardianSet>, index: u32) -> Result<()> {
+        emit!(GuardianSetClosed {
+            admin: ctx.accounts.admin.key(),
+            index,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_staking(
+        ctx: Context<InitializeStaking>,
+        min_stake: u64,
+        unstake_cooldown_slots: u64,
+    ) -> Result<()> {
+        let staking = &mut ctx.accounts.staking_config;
+        staking.stake_mint = ctx.accounts.stake_mint.key();
+        staking.treasury = ctx.accounts.treasury.key();
+        staking.min_stake = min_stake;
+        staking.unstake_cooldown_slots = unstake_cooldown_slots;
+        staking.vault_bump = ctx.bumps.stake_vault;
+        staking.bump = ctx.bumps.staking_config;
+
+        emit!(StakingInitialized {
+            admin: ctx.accounts.admin.key(),
+            stake_mint: staking.stake_mint,
+            treasury: staking.treasury,
+            min_stake,
+            unstake_cooldown_slots,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn stake_guardian(
+        ctx: Context<StakeGuardian>,
+        guardian: GuardianKey,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let stake = &mut ctx.accounts.guardian_stake;
+        if stake.staker == Pubkey::default() {
+            stake.staker = ctx.accounts.staker.key();
+            stake.guardian = guardian.clone();
+            stake.rent_payer = ctx.accounts.payer.key();
+            stake.bump = ctx.bumps.guardian_stake;
+        }
+        require!(stake.guardian == guardian, BridgeError::GuardianMismatch);
+        require!(!stake.slashed, BridgeError::GuardianSlashed);
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staker_token_account.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.staker.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        stake.amount = stake.amount.checked_add(amount).ok_or(BridgeError::InvalidAmount)?;
+        // Topping up cancels a pending exit
+        stake.unstake_requested_slot = 0;
+
+        emit!(GuardianStaked {
+            staker: stake.staker,
+            guardian,
+            amount,
+            total_stake: stake.amount,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn request_unstake(ctx: Context<RequestUnstake>) -> Result<()> {
+        let stake = &mut ctx.accounts.guardian_stake;
+        require!(!stake.slashed, BridgeError::GuardianSlashed);
+
+        stake.unstake_requested_slot = Clock::get()?.slot;
+        let unlock_slot = stake
+            .unstake_requested_slot
+            .checked_add(ctx.accounts.staking_config.unstake_cooldown_slots)
+            .ok_or(BridgeError::CounterOverflow)?;
+
+        emit!(GuardianUnstakeRequested {
+            staker: stake.staker,
+            guardian: stake.guardian.clone(),
+            amount: stake.amount,
+            unlock_slot,
+            slot: stake.unstake_requested_slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn unstake(ctx: Context<Unstake>) -> Result<()> {
+        let stake = &ctx.accounts.guardian_stake;
+        let staking = &ctx.accounts.staking_config;
+        let slot = Clock::get()?.slot;
+
+        require!(!stake.slashed, BridgeError::GuardianSlashed);
+        require!(stake.unstake_requested_slot != 0, BridgeError::UnstakeCooldown);
+        let unlock_slot = stake
+            .unstake_requested_slot
+            .checked_add(staking.unstake_cooldown_slots)
+            .ok_or(BridgeError::CounterOverflow)?;
+        require!(slot >= unlock_slot, BridgeError::UnstakeCooldown);
+        // A guardian must leave the set before its stake can leave the vault
+        require!(
+            !ctx.accounts.guardian_set.guardians.contains(&stake.guardian),
+            BridgeError::GuardianStillActive
+        );
+
+        let seeds: &[&[u8]] = &[b"stake_vault", &[staking.vault_bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    to: ctx.accounts.staker_token_account.to_account_info(),
+                    authority: ctx.accounts.stake_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            stake.amount,
+        )?;
+
+        emit!(GuardianUnstaked {
+            staker: stake.staker,
+            guardian: stake.guardian.clone(),
+            amount: stake.amount,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Slashes a guardian that signed two different messages for the same
+    /// `(source_chain, source_tx)`. Both signatures must be verified by native
+    /// signature-program instructions earlier in this transaction, over the
+    /// digests for `guardian_set_index`, the set the guardian signed them as.
+    pub fn slash_guardian(
+        ctx: Context<SlashGuardian>,
+        first: TransferMessage,
+        second: TransferMessage,
+        guardian_set_index: u32,
+    ) -> Result<()> {
+        require!(
+            first.source_chain == second.source_chain && first.source_tx == second.source_tx,
+            BridgeError::InvalidSlashEvidence
+        );
+        let first_digest = first.digest(guardian_set_index)?;
+        let second_digest = second.digest(guardian_set_index)?;
+        require!(first_digest != second_digest, BridgeError::InvalidSlashEvidence);
+
+        let guardian = ctx.accounts.guardian_stake.guardian.clone();
+        let mut signed_first = false;
+        let mut signed_second = false;
+        for_each_signature(&ctx.accounts.instructions, |key, signed| {
+            if key == guardian {
+                signed_first |= signed == first_digest.as_slice();
+                signed_second |= signed == second_digest.as_slice();
+            }
+        })?;
+        require!(signed_first && signed_second, BridgeError::InvalidSlashEvidence);
+
+        let amount = ctx.accounts.guardian_stake.amount;
+        let seeds: &[&[u8]] = &[b"stake_vault", &[ctx.accounts.staking_config.vault_bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                    authority: ctx.accounts.stake_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+        )?;
+
+        let stake = &mut ctx.accounts.guardian_stake;
+        stake.amount = 0;
+        stake.slashed = true;
+
+        // Removal may leave fewer guardians than the threshold; deliveries then
+        // halt until the admin restores a quorum, which is the safe failure mode
+        let guardian_set = &mut ctx.accounts.guardian_set;
+        guardian_set.guardians.retain(|g| *g != guardian);
+
+        emit!(GuardianSlashed {
+            admin: ctx.accounts.admin.key(),
+            guardian,
+            amount,
+            source_chain: first.source_chain,
+            source_tx: first.source_tx,
+            guardian_set_index: guardian_set.index,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Allowlists a mint for bridging. `decimals` must match the mint, as a
+    /// guard against registering the wrong one.
+    pub fn register_token(
+        ctx: Context<RegisterToken>,
+        decimals: u8,
+        min_amount: u64,
+        max_amount: u64,
+        daily_limit: u64,
+        allow_freezable: bool,
+        allowed_extensions: u8,
+    ) -> Result<()> {
+        require!(decimals == ctx.accounts.token_mint.decimals, BridgeError::DecimalsMismatch);
+        require!(
+            allow_freezable || ctx.accounts.token_mint.freeze_authority.is_none(),
+            BridgeError::FreezableMint
+        );
+        require!(
+            allowed_extensions & !ALLOWED_EXTENSION_FLAGS == 0,
+            BridgeError::InvalidConfig
+        );
+        check_mint_extensions(&ctx.accounts.token_mint, allowed_extensions)?;
+        require!(
+            max_amount == 0 || min_amount <= max_amount,
+            BridgeError::InvalidConfig
+        );
+
+        let registry = &mut ctx.accounts.token_registry;
+        registry.initialize(ctx.accounts.token_mint.key(), ctx.bumps.token_registry);
+        registry.min_amount = min_amount;
+        registry.max_amount = max_amount;
+        registry.daily_limit = daily_limit;
+        registry.allow_freezable = allow_freezable;
+        registry.allowed_extensions = allowed_extensions;
+
+        emit!(TokenRegistered {
+            mint: registry.mint,
+            registry: registry.key(),
+            decimals,
+            supply: ctx.accounts.token_mint.supply,
+            mint_authority: ctx.accounts.token_mint.mint_authority.into(),
+            min_amount,
+            max_amount,
+            daily_limit,
+            freeze_authority: ctx.accounts.token_mint.freeze_authority.into(),
+            allowed_extensions,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Zero-extends a registry created before its later fields existed, such
+    /// as `fee_bps_override` or `lp_drawn`, so it decodes with them unset.
+    pub fn migrate_token_registry(ctx: Context<MigrateTokenRegistry>, mint: Pubkey) -> Result<()> {
+        let info = ctx.accounts.token_registry.to_account_info();
+        let old_len = info.data_len();
+        let space = 8 + TokenRegistry::INIT_SPACE;
+        require!(old_len < space, BridgeError::AlreadyInitialized);
+
+        resize_account(&ctx.accounts.payer, &info, &ctx.accounts.system_program, space)?;
+
+        emit!(TokenRegistryMigrated {
+            mint,
+            old_len: old_len as u32,
+            new_len: space as u32,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Deploys the wrapped mint for a foreign token, with the mint PDA as its
+    /// own mint authority and no freeze authority, and registers it so
+    /// `mint_wrapped` works right away. A second call for the same pair fails
+    /// because the asset registry already exists.
+    pub fn create_wrapped(
+        ctx: Context<CreateWrapped>,
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        decimals: u8,
+        source_decimals: u8,
+    ) -> Result<()> {
+        require!(
+            decimals.abs_diff(source_decimals) <= MAX_DECIMALS_DIFF,
+            BridgeError::InvalidConfig
+        );
+
+        let mint_key = ctx.accounts.wrapped_mint.key();
+        let mint_bump = ctx.bumps.wrapped_mint;
+        let seeds: &[&[u8]] = &[b"wrapped_mint", &source_chain, &source_token, &[mint_bump]];
+
+        create_pda_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.wrapped_mint,
+            &ctx.accounts.system_program,
+            token_interface::find_mint_account_size(None)?,
+            ctx.accounts.token_program.key,
+            seeds,
+        )?;
+        token_interface::initialize_mint2(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                InitializeMint2 {
+                    mint: ctx.accounts.wrapped_mint.to_account_info(),
+                },
+            ),
+            decimals,
+            &mint_key,
+            None,
+        )?;
+        {
+            let data = ctx.accounts.wrapped_mint.try_borrow_data()?;
+            let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?.base;
+            require!(
+                mint.mint_authority == COption::Some(mint_key) && mint.freeze_authority.is_none(),
+                BridgeError::InvalidWrappedMintAuthority
+            );
+        }
+
+        let asset = &mut ctx.accounts.wrapped_asset;
+        asset.source_chain = source_chain;
+        asset.source_token = source_token;
+        asset.wrapped_mint = mint_key;
+        asset.mint_bump = mint_bump;
+        asset.legacy_seeds = false;
+        asset.supply_cap = 0;
+        asset.source_decimals = source_decimals;
+        asset.local_decimals = decimals;
+        asset.bump = ctx.bumps.wrapped_asset;
+        asset.multi_hop = false;
+
+        let origin = &mut ctx.accounts.origin;
+        origin.wrapped_mint = mint_key;
+        origin.source_chain = source_chain;
+        origin.source_token = source_token;
+        origin.source_decimals = source_decimals;
+        origin.bump = ctx.bumps.origin;
+
+        ctx.accounts
+            .token_registry
+            .initialize(mint_key, ctx.bumps.token_registry);
+
+        emit!(WrappedAssetCreated {
+            source_chain,
+            source_token,
+            wrapped_mint: mint_key,
+            registry: ctx.accounts.token_registry.key(),
+            decimals,
+            source_decimals,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the Token Metadata account of a wrapped mint so wallets show it
+    /// as `yh<chain>.<token>`. The wrapped-mint PDA is the update authority,
+    /// and the origin chain and token are appended to `uri` as hex query
+    /// parameters, checkable against the mint's seeds.
+    pub fn create_wrapped_metadata(
+        ctx: Context<CreateWrappedMetadata>,
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        chain_symbol: String,
+        token_symbol: String,
+        token_name: String,
+        uri: String,
+    ) -> Result<()> {
+        let data = wrapped_metadata(
+            &source_chain,
+            &source_token,
+            format!("{} ({})", token_name, chain_symbol),
+            format!("yh{}.{}", chain_symbol, token_symbol),
+            &uri,
+        )?;
+
+        let mint_info = ctx.accounts.wrapped_mint.to_account_info();
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            create_metadata_accounts_v3(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_metadata_program.to_account_info(),
+                    CreateMetadataAccountsV3 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        mint: mint_info.clone(),
+                        mint_authority: mint_info.clone(),
+                        payer: ctx.accounts.payer.to_account_info(),
+                        update_authority: mint_info.clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                data.clone(),
+                true,
+                true,
+                None,
+            )
+        })?;
+
+        emit!(WrappedMetadataCreated {
+            wrapped_mint: mint_info.key(),
+            metadata: ctx.accounts.metadata.key(),
+            name: data.name,
+            symbol: data.symbol,
+            uri: data.uri,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces a wrapped mint's name, symbol and URI, e.g. after the origin
+    /// asset rebrands. The origin query parameters are re-appended to `uri`.
+    pub fn update_wrapped_metadata(
+        ctx: Context<UpdateWrappedMetadata>,
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        let data = wrapped_metadata(&source_chain, &source_token, name, symbol, &uri)?;
+
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            update_metadata_accounts_v2(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_metadata_program.to_account_info(),
+                    UpdateMetadataAccountsV2 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        update_authority: ctx.accounts.wrapped_mint.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                None,
+                Some(data.clone()),
+                None,
+                None,
+            )
+        })?;
+
+        emit!(WrappedMetadataUpdated {
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            metadata: ctx.accounts.metadata.key(),
+            name: data.name,
+            symbol: data.symbol,
+            uri: data.uri,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Caps the wrapped supply of an asset; 0 removes the cap. Lowering it
+    /// below the current supply only blocks further mints.
+    pub fn set_wrapped_supply_cap(
+        ctx: Context<SetWrappedSupplyCap>,
+        _source_chain: [u8; 32],
+        _source_token: [u8; 32],
+        supply_cap: u64,
+    ) -> Result<()> {
+        let asset = &mut ctx.accounts.wrapped_asset;
+        let old_supply_cap = asset.supply_cap;
+        asset.supply_cap = supply_cap;
+
+        emit!(WrappedSupplyCapUpdated {
+            wrapped_mint: asset.wrapped_mint,
+            admin: ctx.accounts.admin.key(),
+            old_supply_cap,
+            new_supply_cap: supply_cap,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Lets an asset be burned toward chains other than its home chain, for
+    /// routes where the destination forwards it on.
+    pub fn set_wrapped_multi_hop(
+        ctx: Context<SetWrappedSupplyCap>,
+        _source_chain: [u8; 32],
+        _source_token: [u8; 32],
+        multi_hop: bool,
+    ) -> Result<()> {
+        let asset = &mut ctx.accounts.wrapped_asset;
+        asset.multi_hop = multi_hop;
+
+        emit!(WrappedMultiHopUpdated {
+            wrapped_mint: asset.wrapped_mint,
+            admin: ctx.accounts.admin.key(),
+            multi_hop,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Hands a wrapped mint's authorities back after they ended up elsewhere:
+    /// `current_authority` must hold whichever of them is wrong, and signs
+    /// the mint authority back to the mint PDA and the freeze authority away.
+    /// Only while the whole bridge is paused, since minting had been unsafe.
+    pub fn repair_wrapped_authority(
+        ctx: Context<RepairWrappedAuthority>,
+        _source_chain: [u8; 32],
+        _source_token: [u8; 32],
+    ) -> Result<()> {
+        require!(ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgeNotPaused);
+        let mint = &ctx.accounts.wrapped_mint;
+        require!(!has_wrapped_authorities(mint), BridgeError::WrappedAuthorityIntact);
+
+        let mint_key = mint.key();
+        let authority = ctx.accounts.current_authority.key();
+        let old_mint_authority: Option<Pubkey> = mint.mint_authority.into();
+        let old_freeze_authority: Option<Pubkey> = mint.freeze_authority.into();
+        let set_authority = |authority_type, new_authority| {
+            token_interface::set_authority(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    SetAuthority {
+                        current_authority: ctx.accounts.current_authority.to_account_info(),
+                        account_or_mint: ctx.accounts.wrapped_mint.to_account_info(),
+                    },
+                ),
+                authority_type,
+                new_authority,
+            )
+        };
+        if old_mint_authority != Some(mint_key) {
+            require!(old_mint_authority == Some(authority), BridgeError::InvalidWrappedMintAuthority);
+            set_authority(AuthorityType::MintTokens, Some(mint_key))?;
+        }
+        if old_freeze_authority.is_some() {
+            require!(old_freeze_authority == Some(authority), BridgeError::InvalidWrappedMintAuthority);
+            set_authority(AuthorityType::FreezeAccount, None)?;
+        }
+
+        emit!(WrappedAuthorityRepaired {
+            wrapped_mint: mint_key,
+            admin: ctx.accounts.admin.key(),
+            current_authority: authority,
+            old_mint_authority,
+            old_freeze_authority,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Records the source token behind a wrapped mint created under the old
+    /// `[b"wrapped_mint", source_chain]` seeds. That mint stays its own
+    /// authority under those seeds, so the asset keeps signing with them;
+    /// each legacy mint may be claimed only once, since its origin record
+    /// already exists afterwards.
+    pub fn register_legacy_wrapped_asset(
+        ctx: Context<RegisterLegacyWrappedAsset>,
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        source_decimals: u8,
+    ) -> Result<()> {
+        let local_decimals = ctx.accounts.wrapped_mint.decimals;
+        require!(
+            local_decimals.abs_diff(source_decimals) <= MAX_DECIMALS_DIFF,
+            BridgeError::InvalidConfig
+        );
+
+        let asset = &mut ctx.accounts.wrapped_asset;
+        asset.source_chain = source_chain;
+        asset.source_token = source_token;
+        asset.wrapped_mint = ctx.accounts.wrapped_mint.key();
+        asset.mint_bump = ctx.bumps.wrapped_mint;
+        asset.legacy_seeds = true;
+        asset.supply_cap = 0;
+        asset.source_decimals = source_decimals;
+        asset.local_decimals = local_decimals;
+        asset.bump = ctx.bumps.wrapped_asset;
+        asset.multi_hop = false;
+
+        let origin = &mut ctx.accounts.origin;
+        origin.wrapped_mint = asset.wrapped_mint;
+        origin.source_chain = source_chain;
+        origin.source_token = source_token;
+        origin.source_decimals = source_decimals;
+        origin.bump = ctx.bumps.origin;
+
+        emit!(WrappedAssetRegistered {
+            source_chain,
+            source_token,
+            wrapped_mint: asset.wrapped_mint,
+            legacy_seeds: true,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Sets or, with `None`, clears the token's fee override.
+    pub fn set_token_fee(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        fee_bps: Option<u16>,
+    ) -> Result<()> {
+        require!(fee_bps.unwrap_or(0) <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+
+        let registry = &mut ctx.accounts.token_registry;
+        let old_fee_bps = registry.fee_bps_override;
+        registry.fee_bps_override = fee_bps;
+
+        emit!(TokenFeeUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            old_fee_bps,
+            new_fee_bps: fee_bps,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Configures the release fee curve; all zero disables it.
+    pub fn set_outflow_fee(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        base_bps: u16,
+        kink_bps: u16,
+        surcharge_bps: u16,
+    ) -> Result<()> {
+        require!(kink_bps < BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        require!(
+            base_bps as u32 + surcharge_bps as u32 <= BPS_DENOMINATOR as u32,
+            BridgeError::InvalidConfig
+        );
+
+        let registry = &mut ctx.accounts.token_registry;
+        registry.outflow_fee_bps = base_bps;
+        registry.utilization_kink_bps = kink_bps;
+        registry.utilization_surcharge_bps = surcharge_bps;
+
+        emit!(OutflowFeeUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            base_bps,
+            kink_bps,
+            surcharge_bps,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the share of release fees kept in the vault for liquidity
+    /// providers of `mint`; 0 sends every fee to the fee vault.
+    pub fn set_lp_fee_share(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        lp_fee_share_bps: u16,
+    ) -> Result<()> {
+        require!(lp_fee_share_bps <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+
+        let registry = &mut ctx.accounts.token_registry;
+        let old_lp_fee_share_bps = registry.lp_fee_share_bps;
+        registry.lp_fee_share_bps = lp_fee_share_bps;
+
+        emit!(LpFeeShareUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            old_lp_fee_share_bps,
+            new_lp_fee_share_bps: lp_fee_share_bps,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_token_limits(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        min_amount: u64,
+        max_amount: u64,
+    ) -> Result<()> {
+        require!(
+            max_amount == 0 || min_amount <= max_amount,
+            BridgeError::InvalidConfig
+        );
+
+        let registry = &mut ctx.accounts.token_registry;
+        let old_min_amount = registry.min_amount;
+        let old_max_amount = registry.max_amount;
+        registry.min_amount = min_amount;
+        registry.max_amount = max_amount;
+
+        emit!(TokenLimitsUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            old_min_amount,
+            old_max_amount,
+            new_min_amount: min_amount,
+            new_max_amount: max_amount,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Changes take effect for the next release; recorded outflow is kept.
+    pub fn set_daily_limit(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        daily_limit: u64,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.token_registry;
+        let old_daily_limit = registry.daily_limit;
+        registry.daily_limit = daily_limit;
+
+        emit!(DailyLimitUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            old_daily_limit,
+            new_daily_limit: daily_limit,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause_token(ctx: Context<SetTokenPaused>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.token_registry.paused = true;
+
+        emit!(TokenPaused {
+            mint,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn unpause_token(ctx: Context<SetTokenPaused>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.token_registry.paused = false;
+
+        emit!(TokenUnpaused {
+            mint,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Stops new locks of the token; releases keep paying out what is already
+    /// in flight, and the vault is left as it is.
+    pub fn delist_token(ctx: Context<SetTokenPaused>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.token_registry.delisted = true;
+
+        emit!(TokenDelisted {
+            mint,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Winds down a delisted token once everything locked has been released:
+    /// sweeps the dust left in its vault and fee vault to `treasury`, closes
+    /// both and the registry, and returns their rent to `config.rent_collector`.
+    pub fn close_token_accounts(ctx: Context<CloseTokenAccounts>, mint: Pubkey) -> Result<()> {
+        let registry = &ctx.accounts.token_registry;
+        require!(registry.delisted, BridgeError::TokenNotDelisted);
+        require!(registry.total_locked == 0, BridgeError::TokenStillLocked);
+        // Liquidity providers' capital sits in the vault and would be swept
+        require!(registry.lp_assets == 0, BridgeError::TokenStillLocked);
+        // Unclaimed referral shares sit in the fee vault and would be swept
+        require!(registry.referral_liability == 0, BridgeError::ReferralFeesOutstanding);
+
+        let lamports = ctx.accounts.vault.to_account_info().lamports()
+            + ctx.accounts.fee_vault.to_account_info().lamports()
+            + registry.to_account_info().lamports();
+        let vault_swept = sweep_and_close_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.treasury,
+            &ctx.accounts.rent_collector,
+            &[b"vault", mint.as_ref(), &[ctx.bumps.vault]],
+        )?;
+        let fee_vault_swept = sweep_and_close_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.fee_vault,
+            &ctx.accounts.treasury,
+            &ctx.accounts.rent_collector,
+            &[b"fee_vault", mint.as_ref(), &[ctx.bumps.fee_vault]],
+        )?;
+
+        emit!(TokenDecommissioned {
+            mint,
+            vault_swept,
+            fee_vault_swept,
+            treasury: ctx.accounts.treasury.key(),
+            rent_collector: ctx.accounts.rent_collector.key(),
+            lamports,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn relist_token(ctx: Context<SetTokenPaused>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.token_registry.delisted = false;
+
+        emit!(TokenRelisted {
+            mint,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_circuit_breaker(
+        ctx: Context<SetTokenPaused>,
+        mint: Pubkey,
+        limit: u64,
+        bucket_slots: u64,
+    ) -> Result<()> {
+        require!(limit == 0 || bucket_slots > 0, BridgeError::InvalidConfig);
+
+        let registry = &mut ctx.accounts.token_registry;
+        let old_limit = registry.circuit_breaker_limit;
+        let old_bucket_slots = registry.circuit_breaker_bucket_slots;
+        registry.circuit_breaker_limit = limit;
+        registry.circuit_breaker_bucket_slots = bucket_slots;
+        if bucket_slots != old_bucket_slots {
+            registry.outflow_buckets = [OutflowBucket::default(); CIRCUIT_BREAKER_BUCKETS];
+        }
+
+        emit!(CircuitBreakerUpdated {
+            mint,
+            admin: ctx.accounts.admin.key(),
+            old_limit,
+            old_bucket_slots,
+            new_limit: limit,
+            new_bucket_slots: bucket_slots,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Records the trip of a release that failed with `CircuitBreakerTripped`,
+    /// which rolled back with it. A watcher passes the release's local
+    /// `amount`; the trip only sticks if that amount would still take the
+    /// window past the limit. Outflows stay shut until `reset_circuit_breaker`.
+    pub fn trip_circuit_breaker(
+        ctx: Context<TripCircuitBreaker>,
+        mint: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.token_registry;
+        let slot = Clock::get()?.slot;
+        let window_volume = registry.trip_circuit_breaker(amount, slot)?;
+
+        emit!(CircuitBreakerTripped {
+            mint,
+            by: ctx.accounts.watcher.key(),
+            attempted_amount: amount,
+            window_volume,
+            limit: registry.circuit_breaker_limit,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Clears a trip and the outflow window. `TokenRegistry::paused`, an
+    /// admin's own pause, is left as it is.
+    pub fn reset_circuit_breaker(ctx: Context<SetTokenPaused>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.token_registry.reset_circuit_breaker();
+
+        emit!(CircuitBreakerReset {
+            mint,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn register_chain(
+        ctx: Context<RegisterChain>,
+        chain_id: [u8; 32],
+        address_len: u8,
+        confirmations: u32,
+        replay_mode: ReplayMode,
+        address_format: AddressFormat,
+        bech32_hrp: String,
+    ) -> Result<()> {
+        require!(
+            address_len > 0 && address_len <= ctx.accounts.config.max_target_addr_len,
+            BridgeError::InvalidTargetAddress
+        );
+        address_format.check_route(address_len, &bech32_hrp)?;
+        check_target_chain(&chain_id)?;
+
+        let route = &mut ctx.accounts.chain_route;
+        route.chain_id = chain_id;
+        route.enabled = true;
+        route.address_len = address_len;
+        route.confirmations = confirmations;
+        route.replay_mode = replay_mode;
+        route.min_retention_slots = 0;
+        route.restricted_delivery = false;
+        route.strict_ordering = false;
+        route.next_expected_nonce = 0;
+        route.address_format = address_format;
+        route.bech32_hrp = bech32_hrp;
+        route.bump = ctx.bumps.chain_route;
+
+        emit!(RouteRegistered {
+            chain_id,
+            address_len,
+            confirmations,
+            replay_mode,
+            address_format,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Trusts `emitter_address` on Wormhole chain `wormhole_chain` to speak for
+    /// our `source_chain`, for routes bootstrapped through Wormhole before
+    /// guardians run there. Registering again replaces the emitter.
+    pub fn register_wormhole_emitter(
+        ctx: Context<RegisterWormholeEmitter>,
+        wormhole_chain: u16,
+        emitter_address: [u8; 32],
+        source_chain: [u8; 32],
+    ) -> Result<()> {
+        let emitter = &mut ctx.accounts.wormhole_emitter;
+        emitter.wormhole_chain = wormhole_chain;
+        emitter.emitter_address = emitter_address;
+        emitter.source_chain = source_chain;
+        emitter.bump = ctx.bumps.wormhole_emitter;
+
+        emit!(WormholeEmitterRegistered {
+            wormhole_chain,
+            emitter_address,
+            source_chain,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Changes how `lock` and `burn_wrapped` check target addresses toward
+    /// `chain_id`. The route's `address_len` has to fit the new format.
+    pub fn set_route_address_format(
+        ctx: Context<SetRouteEnabled>,
+        chain_id: [u8; 32],
+        address_format: AddressFormat,
+        bech32_hrp: String,
+    ) -> Result<()> {
+        let route = &mut ctx.accounts.chain_route;
+        address_format.check_route(route.address_len, &bech32_hrp)?;
+        route.address_format = address_format;
+        route.bech32_hrp = bech32_hrp.clone();
+
+        emit!(RouteAddressFormatUpdated {
+            chain_id,
+            admin: ctx.accounts.admin.key(),
+            address_format,
+            bech32_hrp,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn enable_route(ctx: Context<SetRouteEnabled>, chain_id: [u8; 32]) -> Result<()> {
+        ctx.accounts.chain_route.enabled = true;
+
+        emit!(RouteUnpaused {
+            chain_id,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn disable_route(ctx: Context<SetRouteEnabled>, chain_id: [u8; 32]) -> Result<()> {
+        ctx.accounts.chain_route.enabled = false;
+
+        emit!(RoutePaused {
+            chain_id,
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_route_retention(
+        ctx: Context<SetRouteEnabled>,
+        chain_id: [u8; 32],
+        min_retention_slots: u64,
+    ) -> Result<()> {
+        let route = &mut ctx.accounts.chain_route;
+        let old_min_retention_slots = route.min_retention_slots;
+        route.min_retention_slots = min_retention_slots;
+
+        emit!(RouteRetentionUpdated {
+            chain_id,
+            admin: ctx.accounts.admin.key(),
+            old_min_retention_slots,
+            new_min_retention_slots: min_retention_slots,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Limits every delivery from `chain_id`, attestation execution included,
+    /// to relayers in the `RelayerRegistry`, or opens them to anyone again.
+    pub fn set_route_restricted_delivery(
+        ctx: Context<SetRouteEnabled>,
+        chain_id: [u8; 32],
+        restricted_delivery: bool,
+    ) -> Result<()> {
+        ctx.accounts.chain_route.restricted_delivery = restricted_delivery;
+
+        emit!(RouteDeliveryRestrictionUpdated {
+            chain_id,
+            admin: ctx.accounts.admin.key(),
+            restricted_delivery,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Turns strict ordering on or off for transfers from `chain_id`. While
+    /// on, every delivery path accepts only the transfer whose source nonce
+    /// is `next_expected_nonce`, then moves it up by one.
+    pub fn set_route_strict_ordering(
+        ctx: Context<SetRouteEnabled>,
+        chain_id: [u8; 32],
+        strict_ordering: bool,
+        next_expected_nonce: u64,
+    ) -> Result<()> {
+        let route = &mut ctx.accounts.chain_route;
+        route.strict_ordering = strict_ordering;
+        route.next_expected_nonce = next_expected_nonce;
+
+        emit!(RouteOrderingUpdated {
+            chain_id,
+            admin: ctx.accounts.admin.key(),
+            strict_ordering,
+            next_expected_nonce,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Gives up on the expected nonce of a strictly ordered route so later
+    /// transfers are no longer blocked behind one that will never arrive.
+    /// The skipped transfer can no longer be delivered in order.
+    pub fn skip_nonce(ctx: Context<SetRouteEnabled>, chain_id: [u8; 32], nonce: u64) -> Result<()> {
+        let route = &mut ctx.accounts.chain_route;
+        require!(route.strict_ordering, BridgeError::RouteNotOrdered);
+        require!(nonce == route.next_expected_nonce, BridgeError::OutOfOrderDelivery);
+        route.next_expected_nonce = nonce.checked_add(1).ok_or(BridgeError::CounterOverflow)?;
+
+        emit!(NonceSkipped {
+            chain_id,
+            admin: ctx.accounts.admin.key(),
+            skipped_nonce: nonce,
+            next_expected_nonce: route.next_expected_nonce,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Maps `mint` to the native token that represents it on `target_chain`,
+    /// so locks toward that chain ask for a release of the native asset
+    /// instead of a wrapped mint. Overwrites an earlier mapping.
+    pub fn set_destination_token(
+        ctx: Context<SetDestinationToken>,
+        mint: Pubkey,
+        target_chain: [u8; 32],
+        token: Vec<u8>,
+    ) -> Result<()> {
+        require!(
+            token.len() == ctx.accounts.chain_route.address_len as usize,
+            BridgeError::InvalidTargetAddress
+        );
+
+        let destination = &mut ctx.accounts.destination_token;
+        let old_token = std::mem::replace(&mut destination.token, token.clone());
+        destination.mint = mint;
+        destination.target_chain = target_chain;
+        destination.bump = ctx.bumps.destination_token;
+        // Only a fresh mapping was funded by this payer
+        if old_token.is_empty() {
+            destination.rent_payer = ctx.accounts.payer.key();
+        }
+
+        emit!(DestinationTokenSet {
+            mint,
+            target_chain,
+            old_token: Some(old_token).filter(|old| !old.is_empty()),
+            new_token: Some(token),
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Drops the mapping, so locks toward `target_chain` take the wrapped path again.
+    pub fn clear_destination_token(
+        ctx: Context<ClearDestinationToken>,
+        mint: Pubkey,
+        target_chain: [u8; 32],
+    ) -> Result<()> {
+        emit!(DestinationTokenSet {
+            mint,
+            target_chain,
+            old_token: Some(ctx.accounts.destination_token.token.clone()),
+            new_token: None,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_admin(ctx: Context<AdminAction>, new_admin: Pubkey) -> Result<()> {
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+
+        // Overwrites any earlier proposal that was never accepted
+        let old_pending_admin = bridge_state.pending_admin();
+        bridge_state.set_pending_admin(Some(new_admin));
+
+        emit!(AdminProposed {
+            current_admin: bridge_state.admin,
+            old_pending_admin,
+            proposed_admin: new_admin,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_admin_proposal(ctx: Context<AdminAction>) -> Result<()> {
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+
+        let cancelled = bridge_state
+            .pending_admin()
+            .ok_or(BridgeError::NoPendingAdmin)?;
+        bridge_state.set_pending_admin(None);
+
+        emit!(AdminProposalCancelled {
+            admin: bridge_state.admin,
+            cancelled_admin: cancelled,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+
+        let old_admin = bridge_state.admin;
+        bridge_state.admin = ctx.accounts.new_admin.key();
+        bridge_state.set_pending_admin(None);
+
+        emit!(AdminChanged {
+            old_admin,
+            new_admin: bridge_state.admin,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause(ctx: Context<AdminAction>) -> Result<()> {
+        ctx.accounts.bridge_state.load_mut()?.paused = 1;
+
+        emit!(Paused {
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<AdminAction>) -> Result<()> {
+        ctx.accounts.bridge_state.load_mut()?.paused = 0;
+
+        emit!(Unpaused {
+            by: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn queue_emergency_withdraw(
+        ctx: Context<QueueEmergencyWithdraw>,
+        mint: Pubkey,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(amount <= ctx.accounts.vault.amount, BridgeError::InvalidAmount);
+
+        let slot = Clock::get()?.slot;
+        let withdraw = &mut ctx.accounts.emergency_withdraw;
+        withdraw.mint = mint;
+        withdraw.amount = amount;
+        withdraw.destination = destination;
+        withdraw.rent_payer = ctx.accounts.payer.key();
+        withdraw.queued_slot = slot;
+        withdraw.executable_after_slot =
+            slot.saturating_add(ctx.accounts.config.emergency_withdraw_delay_slots);
+        withdraw.bump = ctx.bumps.emergency_withdraw;
+
+        emit!(EmergencyWithdrawQueued {
+            mint,
+            amount,
+            destination,
+            admin: ctx.accounts.admin.key(),
+            executable_after_slot: withdraw.executable_after_slot,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Moves a queued amount out of the vault once its timelock has passed.
+    /// It is written off the books, and the vault invariant is not checked:
+    /// after an exploit the vault is already short of the books, which is
+    /// the case this path exists for.
+    pub fn execute_emergency_withdraw(
+        ctx: Context<ExecuteEmergencyWithdraw>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgeNotPaused);
+
+        let withdraw = &ctx.accounts.emergency_withdraw;
+        let slot = Clock::get()?.slot;
+        require!(
+            slot >= withdraw.executable_after_slot,
+            BridgeError::EmergencyWithdrawTimelocked
+        );
+
+        let seeds: &[&[u8]] = &[b"vault", mint.as_ref(), &[ctx.bumps.vault]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            withdraw.amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+        ctx.accounts.token_registry.write_off(withdraw.amount);
+
+        emit!(EmergencyWithdrawExecuted {
+            mint,
+            amount: withdraw.amount,
+            destination: withdraw.destination,
+            admin: ctx.accounts.admin.key(),
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_emergency_withdraw(
+        ctx: Context<CancelEmergencyWithdraw>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        let withdraw = &ctx.accounts.emergency_withdraw;
+
+        emit!(EmergencyWithdrawCancelled {
+            mint,
+            amount: withdraw.amount,
+            destination: withdraw.destination,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// The insurance vault has no direct withdrawal; payouts wait out the
+    /// emergency timelock like a vault withdrawal, but need no pause.
+    pub fn queue_insurance_withdraw(
+        ctx: Context<QueueInsuranceWithdraw>,
+        mint: Pubkey,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(amount <= ctx.accounts.insurance_vault.amount, BridgeError::InvalidAmount);
+
+        let slot = Clock::get()?.slot;
+        let withdraw = &mut ctx.accounts.insurance_withdraw;
+        withdraw.mint = mint;
+        withdraw.amount = amount;
+        withdraw.destination = destination;
+        withdraw.rent_payer = ctx.accounts.payer.key();
+        withdraw.queued_slot = slot;
+        withdraw.executable_after_slot =
+            slot.saturating_add(ctx.accounts.config.emergency_withdraw_delay_slots);
+        withdraw.bump = ctx.bumps.insurance_withdraw;
+
+        emit!(InsuranceWithdrawQueued {
+            mint,
+            amount,
+            destination,
+            admin: ctx.accounts.admin.key(),
+            executable_after_slot: withdraw.executable_after_slot,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_insurance_withdraw(
+        ctx: Context<ExecuteInsuranceWithdraw>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        let withdraw = &ctx.accounts.insurance_withdraw;
+        let slot = Clock::get()?.slot;
+        require!(
+            slot >= withdraw.executable_after_slot,
+            BridgeError::EmergencyWithdrawTimelocked
+        );
+
+        let seeds: &[&[u8]] = &[b"insurance_vault", mint.as_ref(), &[ctx.bumps.insurance_vault]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.insurance_vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.insurance_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            withdraw.amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+
+        emit!(InsuranceWithdrawExecuted {
+            mint,
+            amount: withdraw.amount,
+            destination: withdraw.destination,
+            admin: ctx.accounts.admin.key(),
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_insurance_withdraw(
+        ctx: Context<CancelInsuranceWithdraw>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        let withdraw = &ctx.accounts.insurance_withdraw;
+
+        emit!(InsuranceWithdrawCancelled {
+            mint,
+            amount: withdraw.amount,
+            destination: withdraw.destination,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out accrued protocol fees; `u64::MAX` withdraws the whole balance.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, mint: Pubkey, amount: u64) -> Result<()> {
+        // Referral shares sit in the same vault until their referrers claim them
+        let available = ctx
+            .accounts
+            .fee_vault
+            .amount
+            .saturating_sub(ctx.accounts.token_registry.referral_liability);
+        let amount = if amount == u64::MAX { available } else { amount };
+        require!(amount > 0 && amount <= available, BridgeError::InvalidAmount);
+
+        let seeds: &[&[u8]] = &[b"fee_vault", mint.as_ref(), &[ctx.bumps.fee_vault]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.fee_vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.fee_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+
+        emit!(FeesWithdrawn {
+            mint,
+            amount,
+            destination: ctx.accounts.destination.key(),
+            authority: ctx.accounts.authority.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Moves whatever `mint`'s vault holds beyond its locked deposits and LP
+    /// capital, such as tokens sent to the vault directly, to the fee vault.
+    pub fn sync_surplus<'info>(
+        ctx: Context<'_, '_, '_, 'info, SyncSurplus<'info>>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        let amount = ctx.accounts.token_registry.surplus(ctx.accounts.vault.amount);
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            &[b"vault", mint.as_ref(), &[ctx.bumps.vault]],
+            ctx.remaining_accounts,
+            &[(&ctx.accounts.fee_vault, amount)],
+        )?;
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+
+        emit!(SurplusSynced {
+            mint,
+            amount,
+            total_locked: ctx.accounts.token_registry.total_locked,
+            admin: ctx.accounts.admin.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Opens the referrer's claimable balance for `mint`, paid for by the referrer.
+    pub fn register_referrer(ctx: Context<RegisterReferrer>, mint: Pubkey) -> Result<()> {
+        let account = &mut ctx.accounts.referrer_account;
+        account.referrer = ctx.accounts.referrer.key();
+        account.mint = mint;
+        account.claimable = 0;
+        account.bump = ctx.bumps.referrer_account;
+
+        emit!(ReferrerRegistered {
+            referrer: account.referrer,
+            mint,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>, mint: Pubkey) -> Result<()> {
+        let amount = ctx.accounts.referrer_account.claimable;
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let seeds: &[&[u8]] = &[b"fee_vault", mint.as_ref(), &[ctx.bumps.fee_vault]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.fee_vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.fee_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+        ctx.accounts.referrer_account.claimable = 0;
+        let registry = &mut ctx.accounts.token_registry;
+        registry.referral_liability = registry.referral_liability.saturating_sub(amount);
+
+        emit!(ReferralFeesClaimed {
+            referrer: ctx.accounts.referrer.key(),
+            mint,
+            amount,
+            destination: ctx.accounts.destination.key(),
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_fee_exemption(ctx: Context<AddFeeExemption>, user: Pubkey) -> Result<()> {
+        let exemption = &mut ctx.accounts.fee_exemption;
+        exemption.user = user;
+        exemption.rent_payer = ctx.accounts.payer.key();
+        exemption.bump = ctx.bumps.fee_exemption;
+
+        emit!(FeeExemptionAdded {
+            admin: ctx.accounts.admin.key(),
+            user,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_fee_exemption(ctx: Context<RemoveFeeExemption>, user: Pubkey) -> Result<()> {
+        emit!(FeeExemptionRemoved {
+            admin: ctx.accounts.admin.key(),
+            user,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Bars `address` from locking or burning and from receiving deliveries.
+    /// A release refused this way leaves no replay record behind, so it
+    /// can still be posted as an attestation and redirected by the admin.
+    pub fn block_address(ctx: Context<BlockAddress>, address: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        let entry = &mut ctx.accounts.blocked_address;
+        entry.address = address;
+        entry.rent_payer = ctx.accounts.payer.key();
+        entry.blocked_slot = clock.slot;
+        entry.bump = ctx.bumps.blocked_address;
+
+        emit!(AddressBlocklisted {
+            compliance_authority: ctx.accounts.compliance_authority.key(),
+            address,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn unblock_address(ctx: Context<UnblockAddress>, address: Pubkey) -> Result<()> {
+        emit!(AddressUnblocked {
+            compliance_authority: ctx.accounts.compliance_authority.key(),
+            address,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Points a posted, unexecuted attestation whose recipient is blocked at
+    /// `new_recipient` instead, for `execute_release` / `execute_mint` to pay.
+    pub fn redirect_blocked_release(
+        ctx: Context<RedirectBlockedRelease>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        new_recipient: Pubkey,
+    ) -> Result<()> {
+        require!(
+            BlockedAddress::is_blocked(&ctx.accounts.blocked_address),
+            BridgeError::AddressNotBlocked
+        );
+        let attestation = &mut ctx.accounts.attestation;
+        require!(!attestation.executed, BridgeError::AlreadyProcessed);
+        require!(!attestation.cancelled, BridgeError::ReleaseCancelled);
+        let old_recipient = attestation.recipient;
+        attestation.recipient = new_recipient;
+
+        emit!(BlockedReleaseRedirected {
+            admin: ctx.accounts.admin.key(),
+            source_chain,
+            source_tx,
+            old_recipient,
+            new_recipient,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Simulate to preview what `lock` / `burn_wrapped` would charge; the
+    /// result is the instruction's return data.
+    pub fn quote_fee(
+        ctx: Context<QuoteFee>,
+        _mint: Pubkey,
+        amount: u64,
+        _target_chain: [u8; 32],
+    ) -> Result<FeeQuote> {
+        require!(!ctx.accounts.token_registry.delisted, BridgeError::TokenDelisted);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.config.check_amount(amount)?;
+        ctx.accounts.token_registry.check_amount(amount)?;
+        ChainRoute::load(&ctx.accounts.chain_route)?;
+
+        let exempt = ctx.accounts.fee_exemption.is_some();
+        ctx.accounts.token_registry.quote_fee(&ctx.accounts.config, amount, exempt)
+    }
+
+    /// Locks `amount` for `target_addr` on `target_chain`; `options` carries
+    /// the optional parts, see `LockOptions`.
+    pub fn lock<'info>(
+        ctx: Context<'_, '_, '_, 'info, Lock<'info>>,
+        amount: u64,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+        options: LockOptions,
+    ) -> Result<()> {
+        let locked = lock_tokens(
+            ctx.accounts.lock_accounts(&ctx.bumps, ctx.remaining_accounts),
+            amount,
+            target_chain,
+            target_addr,
+            options,
+        )?;
+        emit!(locked);
+
+        Ok(())
+    }
+
+    /// `lock` carrying an application payload for the destination, such as a
+    /// swap to run on arrival. `Locked` is emitted as usual, followed by
+    /// `LockedWithPayload` with the same nonce and the digest guardians sign,
+    /// which binds the payload hash to the transfer. Payload transfers always
+    /// go at `DeliveryTier::Standard`, whatever `options.tier` says.
+    pub fn lock_with_payload<'info>(
+        ctx: Context<'_, '_, '_, 'info, Lock<'info>>,
+        amount: u64,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+        options: LockOptions,
+        payload: Vec<u8>,
+        consistency_level: ConsistencyLevel,
+    ) -> Result<()> {
+        require!(
+            !payload.is_empty() && payload.len() <= ctx.accounts.config.max_payload_len as usize,
+            BridgeError::PayloadTooLarge
+        );
+
+        let locked = lock_tokens(
+            ctx.accounts.lock_accounts(&ctx.bumps, ctx.remaining_accounts),
+            amount,
+            target_chain,
+            target_addr,
+            LockOptions {
+                tier: DeliveryTier::Standard,
+                ..options
+            },
+        )?;
+        let payload_hash = keccak::hash(&payload).to_bytes();
+        let message = PayloadMessage {
+            token: locked.token,
+            amount: locked.net_amount,
+            sender: locked.source,
+            target_chain: locked.target_chain,
+            target_addr: locked.target_addr.clone(),
+            nonce: locked.nonce,
+            relayer_fee: locked.relayer_fee,
+            payload_hash,
+            consistency_level,
+        };
+        let digest = message.digest()?;
+        let nonce = locked.nonce;
+        let slot = locked.slot;
+        emit!(locked);
+        emit!(LockedWithPayload {
+            sender: message.sender,
+            nonce,
+            payload_hash,
+            payload,
+            consistency_level,
+            digest,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// `lock` triggered by an operator against tokens the owner approved to
+    /// the bridge beforehand. The approval must go to the
+    /// `[b"lock_delegate", owner, operator]` PDA, so it only ever serves the
+    /// operator it names. `Locked::source` is the owner and
+    /// `Locked::operator` the signer; the arguments are `lock`'s.
+    pub fn lock_delegated<'info>(
+        ctx: Context<'_, '_, '_, 'info, LockDelegated<'info>>,
+        amount: u64,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+        options: LockOptions,
+    ) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let operator = ctx.accounts.operator.key();
+        let seeds: &[&[u8]] = &[
+            b"lock_delegate",
+            owner.as_ref(),
+            operator.as_ref(),
+            &[ctx.bumps.lock_delegate],
+        ];
+        let locked = lock_tokens(
+            ctx.accounts.lock_accounts(&ctx.bumps, &[seeds], ctx.remaining_accounts),
+            amount,
+            target_chain,
+            target_addr,
+            options,
+        )?;
+        emit!(locked);
+
+        Ok(())
+    }
+
+    /// `lock` of an order the owner signed off-chain, submitted by a relayer
+    /// who pays the fees. The owner's ed25519 signature over
+    /// `LockOrder::digest` is checked in a preceding ed25519 instruction, and
+    /// the tokens move through the owner's `[b"order_delegate", owner]`
+    /// approval, made beforehand or by an owner-signed `approve` in the same
+    /// transaction. Each order nonce can be used once.
+    pub fn lock_with_authorization<'info>(
+        ctx: Context<'_, '_, '_, 'info, LockWithAuthorization<'info>>,
+        order: LockOrder,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(clock.slot <= order.expiry_slot, BridgeError::OrderExpired);
+
+        let digest = order.digest()?;
+        let mut authorized = false;
+        for_each_signature(&ctx.accounts.instructions, |key, signed| {
+            if key == GuardianKey::Ed25519(order.owner) && signed == digest.as_slice() {
+                authorized = true;
+            }
+        })?;
+        require!(authorized, BridgeError::OrderNotAuthorized);
+
+        let record = &mut ctx.accounts.lock_order;
+        record.owner = order.owner;
+        record.nonce = order.nonce;
+        record.used_slot = clock.slot;
+        record.bump = ctx.bumps.lock_order;
+
+        // Fees, referral and memos are not part of what the owner signed
+        let seeds: &[&[u8]] = &[b"order_delegate", order.owner.as_ref(), &[ctx.bumps.order_delegate]];
+        let locked = lock_tokens(
+            ctx.accounts.lock_accounts(&ctx.bumps, &[seeds], ctx.remaining_accounts),
+            order.amount,
+            order.target_chain,
+            order.target_addr,
+            LockOptions::default(),
+        )?;
+        emit!(locked);
+
+        Ok(())
+    }
+
+    /// Several `lock`s by one user to the same destination. Each entry brings
+    /// the complete `Lock` account list for its mint in `remaining_accounts`,
+    /// in entry order, and is validated exactly as `lock` would validate it,
+    /// so an unregistered or paused mint anywhere fails the whole batch. The
+    /// `Locked` events carry the first entry's nonce as `batch_id`. Mints
+    /// with a transfer hook cannot be batched.
+    pub fn batch_lock<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchLock<'info>>,
+        entries: Vec<LockEntry>,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+    ) -> Result<()> {
+        require!(
+            !entries.is_empty() && entries.len() <= MAX_LOCK_BATCH,
+            BridgeError::InvalidLockBatch
+        );
+
+        let mut accounts = ctx.remaining_accounts;
+        let mut batch_id = None;
+        for entry in &entries {
+            let ix_data = (entry.amount, target_chain).try_to_vec()?;
+            let mut bumps = LockBumps::default();
+            let mut lock = Lock::try_accounts(
+                &crate::ID,
+                &mut accounts,
+                &ix_data,
+                &mut bumps,
+                &mut BTreeSet::new(),
+            )?;
+            require_keys_eq!(lock.user.key(), ctx.accounts.user.key(), BridgeError::InvalidLockBatch);
+            require_keys_eq!(lock.token_mint.key(), entry.mint, BridgeError::InvalidLockBatch);
+
+            let mut locked = lock_tokens(
+                lock.lock_accounts(&bumps, &[]),
+                entry.amount,
+                target_chain,
+                target_addr.clone(),
+                LockOptions::default(),
+            )?;
+            locked.batch_id = Some(*batch_id.get_or_insert(locked.nonce));
+            lock.exit(&crate::ID)?;
+            emit!(locked);
+        }
+        require!(accounts.is_empty(), BridgeError::InvalidLockBatch);
+
+        Ok(())
+    }
+
+    /// `lock` for SOL itself: moves `amount` lamports into the user's own
+    /// wSOL account and syncs it, then locks the wrapped SOL as usual, so
+    /// `Locked` names the native mint. The wSOL account must already exist
+    /// and is left open for later use.
+    pub fn lock_native<'info>(
+        ctx: Context<'_, '_, '_, 'info, Lock<'info>>,
+        amount: u64,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+        options: LockOptions,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.token_mint.key() == native_mint::ID,
+            BridgeError::NotNativeMint
+        );
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.user_token_account.to_account_info(),
+            },
+        ))?;
+
+        lock(ctx, amount, target_chain, target_addr, options)
+    }
+
+    /// Returns an unattested lock's net amount from the vault to its source
+    /// once `PendingLock::refundable_after_slot` has passed, or
+    /// `DEADLINE_REFUND_GRACE_SECS` after a deadline the destination
+    /// enforces. Fails once `attest_lock` has recorded the lock, since the
+    /// destination may already have paid it out. The record stays behind,
+    /// marked refunded.
+    pub fn refund_expired_lock<'info>(
+        mut ctx: Context<'_, '_, '_, 'info, RefundLock<'info>>,
+        nonce: u64,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_lock;
+        let clock = Clock::get()?;
+        let past_deadline = pending.deadline_unix != 0
+            && clock.unix_timestamp
+                > pending.deadline_unix.saturating_add(DEADLINE_REFUND_GRACE_SECS);
+        require!(
+            past_deadline || clock.slot >= pending.refundable_after_slot,
+            BridgeError::RefundNotYetAvailable
+        );
+        refund_pending_lock(&mut ctx, nonce)
+    }
+
+    /// Withdraws a lock the guardians have not yet marked attested. Guardians
+    /// must refuse to sign for a cancelled nonce, and `attest_lock` fails on
+    /// one, so whichever of the two lands first decides the lock's fate.
+    pub fn cancel_lock(ctx: Context<CancelLock>, nonce: u64) -> Result<()> {
+        let pending = &mut ctx.accounts.pending_lock;
+        require!(!pending.refunded, BridgeError::LockAlreadyRefunded);
+        require!(!pending.cancelled, BridgeError::LockIsCancelled);
+        pending.cancelled = true;
+        pending.cancelled_slot = Clock::get()?.slot;
+
+        emit!(LockCancelled {
+            nonce,
+            source: pending.source,
+            mint: pending.mint,
+            amount: pending.amount,
+            slot: pending.cancelled_slot,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a cancelled lock once `LOCK_CANCEL_DELAY_SLOTS` have passed,
+    /// unless it was attested anyway.
+    pub fn refund_cancelled_lock<'info>(
+        mut ctx: Context<'_, '_, '_, 'info, RefundLock<'info>>,
+        nonce: u64,
+    ) -> Result<()> {
+        let pending = &ctx.accounts.pending_lock;
+        require!(pending.cancelled, BridgeError::LockNotCancelled);
+        require!(
+            Clock::get()?.slot >= pending.cancelled_slot.saturating_add(LOCK_CANCEL_DELAY_SLOTS),
+            BridgeError::RefundNotYetAvailable
+        );
+        refund_pending_lock(&mut ctx, nonce)
+    }
+
+    /// Guardians record on-chain that they are attesting lock `nonce`, on
+    /// signatures over `LockAttestationMessage::digest`, before releasing the
+    /// attestation to the destination. From then on `cancel_lock` and both
+    /// refunds fail for good; the marker is never closed.
+    pub fn attest_lock(ctx: Context<AttestLock>, nonce: u64) -> Result<()> {
+        let pending = &ctx.accounts.pending_lock;
+        require!(!pending.cancelled, BridgeError::LockIsCancelled);
+        require!(!pending.refunded, BridgeError::LockAlreadyRefunded);
+
+        let message = LockAttestationMessage {
+            nonce,
+            source: pending.source,
+            token: pending.mint,
+            amount: pending.amount,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+
+        let attestation = &mut ctx.accounts.lock_attestation;
+        attestation.nonce = nonce;
+        attestation.guardian_set_index = ctx.accounts.guardian_set.index;
+        attestation.attested_slot = Clock::get()?.slot;
+        attestation.bump = ctx.bumps.lock_attestation;
+
+        Ok(())
+    }
+
+    /// Closes the `PendingLock` of a lock the destination has delivered, on
+    /// guardian signatures over `LockSettlement::digest`, so it can no longer
+    /// be refunded. The rent goes back to whoever paid it.
+    pub fn settle_lock(ctx: Context<SettleLock>, nonce: u64) -> Result<()> {
+        let pending = &ctx.accounts.pending_lock;
+        require!(!pending.refunded, BridgeError::LockAlreadyRefunded);
+
+        let message = LockSettlement {
+            nonce,
+            token: pending.mint,
+            amount: pending.amount,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+
+        emit!(LockSettled {
+            nonce,
+            mint: pending.mint,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Delivers a guardian-attested transfer out of the vault. The threshold
+    /// signatures are the whole authorization: any fee payer may submit it,
+    /// so recipients can deliver their own transfers while no relayer does,
+    /// and the only identity that matters is the attested recipient. Routes
+    /// with `restricted_delivery` set take registered relayers only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn release<'info>(
+        ctx: Context<'_, '_, '_, 'info, Release<'info>>, 
+        amount: u64, 
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        memo: Option<String>,
+        tier: DeliveryTier,
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = TransferMessage {
+            source_chain,
+            source_tx,
+            token: ctx.accounts.token_mint.key(),
+            amount,
+            recipient: ctx.accounts.user.key(),
+            nonce,
+            relayer_fee,
+            native_drop,
+            deadline_unix,
+            memo,
+            tier,
+            source_addr,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        let guardian_set_index = ctx.accounts.guardian_set.index;
+        deliver_release(ctx, message, guardian_set_index)
+    }
+
+    /// `release` of a transfer covered by a root from `post_attestation_root`
+    /// instead of its own signatures. `merkle_proof` leads from the message
+    /// digest to the root, hashing sorted pairs; a root over a single
+    /// transfer is that transfer's digest and takes an empty proof. Replay
+    /// protection is the usual `(source_chain, source_tx)` marker, so a
+    /// transfer included in several roots is still delivered once.
+    #[allow(clippy::too_many_arguments)]
+    pub fn release_with_proof<'info>(
+        ctx: Context<'_, '_, '_, 'info, Release<'info>>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        memo: Option<String>,
+        tier: DeliveryTier,
+        source_addr: [u8; 32],
+        merkle_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+        require!(merkle_proof.len() <= MAX_MERKLE_PROOF_DEPTH, BridgeError::InvalidMerkleProof);
+
+        let message = TransferMessage {
+            source_chain,
+            source_tx,
+            token: ctx.accounts.token_mint.key(),
+            amount,
+            recipient: ctx.accounts.user.key(),
+            nonce,
+            relayer_fee,
+            native_drop,
+            deadline_unix,
+            memo,
+            tier,
+            source_addr,
+        };
+        let Some(attestation_root) = ctx.accounts.attestation_root.as_ref() else {
+            return err!(BridgeError::AttestationRootRequired);
+        };
+        // The root is only as good as the set that signed it
+        require!(
+            attestation_root.guardian_set_index == ctx.accounts.guardian_set.index,
+            BridgeError::WrongGuardianSet
+        );
+        require!(
+            merkle_root_of(message.digest(ctx.accounts.guardian_set.index)?, &merkle_proof)
+                == attestation_root.root,
+            BridgeError::InvalidMerkleProof
+        );
+
+        let guardian_set_index = attestation_root.guardian_set_index;
+        deliver_release(ctx, message, guardian_set_index)
+    }
+
+    /// Stores a Merkle root over a batch of `TransferMessage` digests once its
+    /// guardian quorum checks out, so `release_with_proof` can deliver each
+    /// transfer without verifying signatures again.
+    pub fn post_attestation_root(
+        ctx: Context<PostAttestationRoot>,
+        message: AttestationRootMessage,
+    ) -> Result<()> {
+        require!(message.leaf_count > 0, BridgeError::InvalidMerkleProof);
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+
+        let attestation_root = &mut ctx.accounts.attestation_root;
+        attestation_root.root = message.root;
+        attestation_root.leaf_count = message.leaf_count;
+        attestation_root.guardian_set_index = ctx.accounts.guardian_set.index;
+        attestation_root.posted_slot = Clock::get()?.slot;
+        attestation_root.bump = ctx.bumps.attestation_root;
+
+        emit!(AttestationRootPosted {
+            root: message.root,
+            leaf_count: message.leaf_count,
+            guardian_set_index: attestation_root.guardian_set_index,
+            slot: attestation_root.posted_slot,
+        });
+
+        Ok(())
+    }
+
+    /// `release` of wrapped SOL paid out as lamports, so the recipient needs
+    /// no token account and may be any non-executable account, program-owned
+    /// ones included. The net passes through a one-shot wSOL account that is
+    /// closed into the payer, who forwards exactly the net to the recipient
+    /// and keeps only its own rent back. Amounts large enough to queue are
+    /// paid as wSOL by `execute_release` instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn release_native(
+        ctx: Context<ReleaseNative>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        memo: Option<String>,
+        tier: DeliveryTier,
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = TransferMessage {
+            source_chain,
+            source_tx,
+            token: native_mint::ID,
+            amount,
+            recipient: ctx.accounts.recipient.key(),
+            nonce,
+            relayer_fee,
+            native_drop,
+            deadline_unix,
+            memo,
+            tier,
+            source_addr,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.check_deadline()?;
+        check_memo(&message.memo)?;
+
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        if ctx.accounts.config.is_large_release(amount) {
+            accept_delivery(
+                &ctx.accounts.source_route,
+                ctx.accounts.relayer_registry.as_ref(),
+                ctx.accounts.payer.key,
+                Some(&ctx.accounts.blocked_address),
+                nonce,
+            )?;
+            return queue_release(
+                &ctx.accounts.payer,
+                &ctx.accounts.queued_release,
+                &ctx.accounts.system_program,
+                ctx.bumps.queued_release,
+                &message,
+                ctx.accounts.guardian_set.index,
+                slot,
+                ctx.accounts.config.execution_delay(amount),
+            );
+        }
+        // Checked ahead of any state change so an underfunded vault fails with
+        // its own error and the same transfer can be delivered once refilled
+        require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+        write_memo(ctx.accounts.memo_program.as_ref(), &message.memo)?;
+        check_not_frozen(
+            &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+            &[],
+            source_chain,
+            source_tx,
+            slot,
+        )?;
+        if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+        ctx.accounts.token_registry.sub_locked(amount)?;
+        let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+        let fee = protocol_fee(amount, fee_bps);
+        let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+        let relayer_fee = message.relayer_fee_for(ctx.accounts.payer.key).min(amount - fee);
+        let payout = amount - fee - relayer_fee;
+
+        // A recipient with no lamports yet must end up rent-exempt
+        let recipient = ctx.accounts.recipient.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(recipient.data_len());
+        require!(
+            recipient.lamports().saturating_add(payout) >= rent_floor,
+            BridgeError::NativeAmountBelowRent
+        );
+
+        let payer_key = ctx.accounts.payer.key();
+        let unwrap_seeds: &[&[u8]] = &[b"native_unwrap", payer_key.as_ref(), &[ctx.bumps.unwrap_account]];
+        create_self_owned_token_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.unwrap_account,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            unwrap_seeds,
+        )?;
+        let vault_seeds: &[&[u8]] = &[b"vault", native_mint::ID.as_ref(), &[ctx.bumps.vault]];
+        for (to, value) in [
+            (ctx.accounts.unwrap_account.to_account_info(), payout),
+            (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee),
+            (ctx.accounts.fee_vault.to_account_info(), treasury_fee),
+        ] {
+            if value == 0 {
+                continue;
+            }
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.vault.to_account_info(),
+                        mint: ctx.accounts.token_mint.to_account_info(),
+                        to,
+                        authority: ctx.accounts.vault.to_account_info(),
+                    },
+                    &[vault_seeds],
+                ),
+                value,
+                ctx.accounts.token_mint.decimals,
+            )?;
+        }
+
+        // Closing unwraps: the payer gets the net plus the rent it just paid
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.unwrap_account.to_account_info(),
+                destination: ctx.accounts.payer.to_account_info(),
+                authority: ctx.accounts.unwrap_account.to_account_info(),
+            },
+            &[unwrap_seeds],
+        ))?;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: recipient,
+                },
+            ),
+            payout,
+        )?;
+        let native_drop = message
+            .native_drop_for(ctx.accounts.payer.key)
+            .min(ctx.accounts.config.max_native_drop);
+        drop_native(&ctx.accounts.payer, &ctx.accounts.recipient, &ctx.accounts.system_program, native_drop)?;
+        // Marked processed only once the payout went through
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+        emit!(Released {
+            transfer_id: message.transfer_id(&ctx.accounts.token_mint.key().to_bytes()),
+            recipient: ctx.accounts.recipient.key(),
+            mint: ctx.accounts.token_mint.key(),
+            amount,
+            fee,
+            transfer_fee: 0,
+            relayer_fee,
+            native_drop,
+            source_tx,
+            source_chain,
+            memo: message.memo,
+            tier: message.tier,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            vault_balance: ctx.accounts.vault.amount,
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn release_by_nonce<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseByNonce<'info>>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        source_nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        memo: Option<String>,
+        tier: DeliveryTier,
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::Nonce,
+            BridgeError::WrongReplayMode
+        );
+
+        let mint = ctx.accounts.vault.mint;
+        let message = TransferMessage {
+            source_chain,
+            source_tx,
+            token: mint,
+            amount,
+            recipient: ctx.accounts.user.key(),
+            nonce: source_nonce,
+            relayer_fee,
+            native_drop,
+            deadline_unix,
+            memo,
+            tier,
+            source_addr,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.check_deadline()?;
+        check_memo(&message.memo)?;
+
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        let large = ctx.accounts.config.is_large_release(amount);
+        if !large {
+            require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+            write_memo(ctx.accounts.memo_program.as_ref(), &message.memo)?;
+            check_not_frozen(
+                &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+                &[&ctx.accounts.user_token_account],
+                source_chain,
+                source_tx,
+                slot,
+            )?;
+            if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+                return err!(BridgeError::CircuitBreakerTripped);
+            }
+            ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+            ctx.accounts.token_registry.sub_locked(amount)?;
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            source_nonce,
+        )?;
+
+        let bitmap = &mut ctx.accounts.replay_bitmap;
+        bitmap.source_chain = source_chain;
+        bitmap.page_index = source_nonce / NONCES_PER_BITMAP_PAGE;
+        bitmap.bump = ctx.bumps.replay_bitmap;
+        bitmap.consume(source_nonce)?;
+
+        if large {
+            return queue_release(
+                &ctx.accounts.payer,
+                &ctx.accounts.queued_release,
+                &ctx.accounts.system_program,
+                ctx.bumps.queued_release,
+                &message,
+                ctx.accounts.guardian_set.index,
+                slot,
+                ctx.accounts.config.execution_delay(amount),
+            );
+        }
+
+        let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+        let fee = protocol_fee(amount, fee_bps);
+        let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+        let relayer_fee = message.relayer_fee_for(ctx.accounts.payer.key).min(amount - fee);
+        let payout = amount - fee - relayer_fee;
+        let seeds: &[&[u8]] = &[b"vault", mint.as_ref(), &[ctx.bumps.vault]];
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            seeds,
+            ctx.remaining_accounts,
+            &[
+                (&ctx.accounts.user_token_account, payout),
+                (&ctx.accounts.relayer_token_account, relayer_fee),
+                (&ctx.accounts.fee_vault, treasury_fee),
+            ],
+        )?;
+        let native_drop = message
+            .native_drop_for(ctx.accounts.payer.key)
+            .min(ctx.accounts.config.max_native_drop);
+        drop_native(
+            &ctx.accounts.payer,
+            &ctx.accounts.user,
+            &ctx.accounts.system_program,
+            native_drop,
+        )?;
+
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+        emit!(Released {
+            transfer_id: message.transfer_id(&ctx.accounts.token_mint.key().to_bytes()),
+            recipient: ctx.accounts.user.key(),
+            mint: ctx.accounts.token_mint.key(),
+            amount,
+            fee,
+            transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, payout)?,
+            relayer_fee,
+            native_drop,
+            source_tx,
+            source_chain,
+            memo: message.memo,
+            tier: message.tier,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            vault_balance: ctx.accounts.vault.amount,
+        });
+
+        Ok(())
+    }
+
+    /// `release` for a bridge-wrapped token, minted instead of paid from a
+    /// vault; just as permissionless.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint_wrapped(
+        ctx: Context<MintWrapped>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        memo: Option<String>,
+        tier: DeliveryTier,
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = TransferMessage {
+            source_chain,
+            source_tx,
+            token: ctx.accounts.wrapped_mint.key(),
+            amount,
+            recipient: ctx.accounts.user.key(),
+            nonce,
+            relayer_fee,
+            native_drop,
+            deadline_unix,
+            memo,
+            tier,
+            source_addr,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.check_deadline()?;
+        check_memo(&message.memo)?;
+
+        // `amount` is in source-chain units; limits and minting use local ones
+        let (local_amount, dust) = ctx.accounts.wrapped_asset.to_local(amount)?;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        if ctx.accounts.config.is_large_release(local_amount) {
+            accept_delivery(
+                &ctx.accounts.source_route,
+                ctx.accounts.relayer_registry.as_ref(),
+                ctx.accounts.payer.key,
+                Some(&ctx.accounts.blocked_address),
+                nonce,
+            )?;
+            return queue_release(
+                &ctx.accounts.payer,
+                &ctx.accounts.queued_release,
+                &ctx.accounts.system_program,
+                ctx.bumps.queued_release,
+                &message,
+                ctx.accounts.guardian_set.index,
+                slot,
+                ctx.accounts.config.execution_delay(local_amount),
+            );
+        }
+        write_memo(ctx.accounts.memo_program.as_ref(), &message.memo)?;
+        let recipient_account_created = create_or_check_recipient_ata(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.user,
+            &ctx.accounts.wrapped_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+        )?;
+        check_recipient_not_frozen(&ctx.accounts.user_token_account, source_chain, source_tx, slot)?;
+        if ctx.accounts.token_registry.record_outflow(local_amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(local_amount, slot)?;
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+        
+        ctx.accounts
+            .wrapped_asset
+            .check_supply_cap(ctx.accounts.wrapped_mint.supply, local_amount)?;
+
+        let (relayer_fee, _) = ctx
+            .accounts
+            .wrapped_asset
+            .to_local(message.relayer_fee_for(ctx.accounts.payer.key))?;
+        let relayer_fee = relayer_fee.min(local_amount);
+
+        // Mint wrapped tokens
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            for (to, value) in [
+                (ctx.accounts.user_token_account.to_account_info(), local_amount - relayer_fee),
+                (ctx.accounts.relayer_token_account.to_account_info(), relayer_fee),
+            ] {
+                if value == 0 {
+                    continue;
+                }
+                token_interface::mint_to(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        MintTo {
+                            mint: ctx.accounts.wrapped_mint.to_account_info(),
+                            to,
+                            authority: ctx.accounts.wrapped_mint.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    value,
+                )?;
+            }
+            Ok(())
+        })?;
+        let native_drop = message
+            .native_drop_for(ctx.accounts.payer.key)
+            .min(ctx.accounts.config.max_native_drop);
+        drop_native(&ctx.accounts.payer, &ctx.accounts.user, &ctx.accounts.system_program, native_drop)?;
+        
+        ctx.accounts.wrapped_mint.reload()?;
+        emit!(WrappedMinted {
+            transfer_id: message.transfer_id(&source_token),
+            recipient: *ctx.accounts.user.key,
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            source_token,
+            amount,
+            minted: local_amount,
+            dust,
+            relayer_fee,
+            native_drop,
+            source_tx,
+            source_chain,
+            memo: message.memo,
+            tier: message.tier,
+            recipient_account_created,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            wrapped_supply: ctx.accounts.wrapped_mint.supply,
+        });
+        
+        Ok(())
+    }
+
+    /// `mint_wrapped` on the word of a Wormhole VAA instead of our guardians.
+    /// The posted-VAA account must be the one the core bridge derived from
+    /// the VAA's own body hash, so it can only exist once Wormhole has
+    /// verified it; its emitter must be the one registered for its chain,
+    /// and its payload is a borsh `TransferMessage`. Replay is keyed on the
+    /// VAA hash and, as everywhere else, on `(source_chain, source_tx)`.
+    /// Amounts large enough to queue are refused.
+    pub fn mint_wrapped_from_vaa(
+        ctx: Context<MintWrappedFromVaa>,
+        vaa_hash: [u8; 32],
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        source_token: [u8; 32],
+    ) -> Result<()> {
+        let state = ctx.accounts.bridge_state.load()?;
+        require!(!state.is_paused(), BridgeError::BridgePaused);
+        let guardian_set_index = state.guardian_set_index;
+        drop(state);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let vaa = PostedVaa::parse(&ctx.accounts.posted_vaa.try_borrow_data()?)?;
+        require!(vaa.hash() == vaa_hash, BridgeError::InvalidVaa);
+        let emitter = &ctx.accounts.wormhole_emitter;
+        require!(
+            vaa.emitter_chain == emitter.wormhole_chain
+                && vaa.emitter_address == emitter.emitter_address,
+            BridgeError::UnknownEmitter
+        );
+
+        let message =
+            TransferMessage::try_from_slice(&vaa.payload).map_err(|_| error!(BridgeError::InvalidVaa))?;
+        require!(
+            message.source_chain == emitter.source_chain
+                && message.source_chain == source_chain
+                && message.source_tx == source_tx,
+            BridgeError::InvalidVaa
+        );
+        require_keys_eq!(message.token, ctx.accounts.wrapped_mint.key(), BridgeError::WrappedAssetMismatch);
+        require_keys_eq!(message.recipient, ctx.accounts.user.key(), BridgeError::RecipientMismatch);
+        message.check_deadline()?;
+        check_memo(&message.memo)?;
+
+        let amount = message.amount;
+        let (local_amount, dust) = ctx.accounts.wrapped_asset.to_local(amount)?;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        require!(!ctx.accounts.config.is_large_release(local_amount), BridgeError::VaaNotQueueable);
+        write_memo(ctx.accounts.memo_program.as_ref(), &message.memo)?;
+        let recipient_account_created = create_or_check_recipient_ata(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.user,
+            &ctx.accounts.wrapped_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+        )?;
+        check_recipient_not_frozen(&ctx.accounts.user_token_account, source_chain, source_tx, slot)?;
+        if ctx.accounts.token_registry.record_outflow(local_amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            message.nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(local_amount, slot)?;
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            guardian_set_index,
+            slot,
+        )?;
+        let processed_vaa = &mut ctx.accounts.processed_vaa;
+        processed_vaa.vaa_hash = vaa_hash;
+        processed_vaa.processed_slot = slot;
+        processed_vaa.bump = ctx.bumps.processed_vaa;
+
+        ctx.accounts
+            .wrapped_asset
+            .check_supply_cap(ctx.accounts.wrapped_mint.supply, local_amount)?;
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            token_interface::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.wrapped_mint.to_account_info(),
+                        to: ctx.accounts.user_token_account.to_account_info(),
+                        authority: ctx.accounts.wrapped_mint.to_account_info(),
+                    },
+                    &[seeds]
+                ),
+                local_amount
+            )
+        })?;
+
+        emit!(VaaRedeemed {
+            vaa_hash,
+            emitter_chain: vaa.emitter_chain,
+            sequence: vaa.sequence,
+            source_chain,
+            source_tx,
+            slot,
+        });
+        ctx.accounts.wrapped_mint.reload()?;
+        emit!(WrappedMinted {
+            transfer_id: message.transfer_id(&source_token),
+            recipient: message.recipient,
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            source_token,
+            amount,
+            minted: local_amount,
+            dust,
+            relayer_fee: 0,
+            native_drop: 0,
+            source_tx,
+            source_chain,
+            memo: message.memo,
+            tier: message.tier,
+            recipient_account_created,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            wrapped_supply: ctx.accounts.wrapped_mint.supply,
+        });
+
+        Ok(())
+    }
+
+    /// `release` into a program instead of a wallet. The attested recipient
+    /// is the program id; the net lands in an escrow token account owned by
+    /// its `[b"bridge_escrow"]` PDA, then the program's `on_bridge_receive`
+    /// is called with the payload and the remaining accounts, without any
+    /// bridge signature. A failing callee fails the whole instruction, so the
+    /// transfer stays undelivered and can be retried. Large amounts cannot
+    /// be queued with a call and are refused.
+    #[allow(clippy::too_many_arguments)]
+    pub fn release_with_call<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseWithCall<'info>>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        source_addr: [u8; 32],
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = CallMessage {
+            transfer: TransferMessage {
+                source_chain,
+                source_tx,
+                token: ctx.accounts.vault.mint,
+                amount,
+                recipient: ctx.accounts.recipient_program.key(),
+                nonce,
+                relayer_fee,
+                native_drop,
+                deadline_unix,
+                memo: None,
+                tier: DeliveryTier::Standard,
+                source_addr,
+            },
+            source_addr,
+            payload_hash: keccak::hash(&payload).to_bytes(),
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.transfer.check_deadline()?;
+
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        require!(!ctx.accounts.config.is_large_release(amount), BridgeError::CallNotQueueable);
+        // Checked ahead of any state change so an underfunded vault fails with
+        // its own error and the same transfer can be delivered once refilled
+        require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+        check_not_frozen(
+            &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+            &[&ctx.accounts.escrow],
+            source_chain,
+            source_tx,
+            slot,
+        )?;
+        if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+        ctx.accounts.token_registry.sub_locked(amount)?;
+        let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+        let fee = protocol_fee(amount, fee_bps);
+        let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+        let mint = ctx.accounts.vault.mint;
+        let seeds: &[&[u8]] = &[b"vault", mint.as_ref(), &[ctx.bumps.vault]];
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            seeds,
+            &[],
+            &[(&ctx.accounts.escrow, amount - fee), (&ctx.accounts.fee_vault, treasury_fee)],
+        )?;
+
+        call_bridge_receiver(
+            &ctx.accounts.recipient_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_authority,
+            &ctx.accounts.token_mint,
+            ctx.remaining_accounts,
+            BridgeReceiveArgs { payload, amount: amount - fee, source_chain, source_addr },
+        )?;
+        // Marked processed only once the payout and the call went through
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+        emit!(Released {
+            transfer_id: message.transfer.transfer_id(&ctx.accounts.token_mint.key().to_bytes()),
+            recipient: ctx.accounts.recipient_program.key(),
+            mint: ctx.accounts.token_mint.key(),
+            amount,
+            fee,
+            transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, amount - fee)?,
+            relayer_fee: 0,
+            native_drop: 0,
+            source_tx,
+            source_chain,
+            memo: None,
+            tier: DeliveryTier::Standard,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            vault_balance: ctx.accounts.vault.amount,
+        });
+        emit!(CallDelivered {
+            recipient_program: ctx.accounts.recipient_program.key(),
+            escrow: ctx.accounts.escrow.key(),
+            amount: amount - fee,
+            payload_hash: message.payload_hash,
+            source_addr,
+            source_tx,
+            source_chain,
+        });
+
+        Ok(())
+    }
+
+    /// `mint_wrapped` into a program, with the same escrow and callback
+    /// rules as `release_with_call`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint_wrapped_with_call<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintWrappedWithCall<'info>>,
+        amount: u64,
+        source_tx: [u8; 32],
+        source_chain: [u8; 32],
+        source_token: [u8; 32],
+        nonce: u64,
+        relayer_fee: u64,
+        native_drop: u64,
+        deadline_unix: i64,
+        source_addr: [u8; 32],
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = CallMessage {
+            transfer: TransferMessage {
+                source_chain,
+                source_tx,
+                token: ctx.accounts.wrapped_mint.key(),
+                amount,
+                recipient: ctx.accounts.recipient_program.key(),
+                nonce,
+                relayer_fee,
+                native_drop,
+                deadline_unix,
+                memo: None,
+                tier: DeliveryTier::Standard,
+                source_addr,
+            },
+            source_addr,
+            payload_hash: keccak::hash(&payload).to_bytes(),
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.transfer.check_deadline()?;
+
+        let (local_amount, dust) = ctx.accounts.wrapped_asset.to_local(amount)?;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        require!(!ctx.accounts.config.is_large_release(local_amount), BridgeError::CallNotQueueable);
+        check_not_frozen(&[], &[&ctx.accounts.escrow], source_chain, source_tx, slot)?;
+        if ctx.accounts.token_registry.record_outflow(local_amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(local_amount, slot)?;
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+
+        ctx.accounts
+            .wrapped_asset
+            .check_supply_cap(ctx.accounts.wrapped_mint.supply, local_amount)?;
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            token_interface::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.wrapped_mint.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                        authority: ctx.accounts.wrapped_mint.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                local_amount,
+            )
+        })?;
+
+        call_bridge_receiver(
+            &ctx.accounts.recipient_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_authority,
+            &ctx.accounts.wrapped_mint,
+            ctx.remaining_accounts,
+            BridgeReceiveArgs { payload, amount: local_amount, source_chain, source_addr },
+        )?;
+
+        ctx.accounts.wrapped_mint.reload()?;
+        emit!(WrappedMinted {
+            transfer_id: message.transfer.transfer_id(&source_token),
+            recipient: ctx.accounts.recipient_program.key(),
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            source_token,
+            amount,
+            minted: local_amount,
+            dust,
+            relayer_fee: 0,
+            native_drop: 0,
+            source_tx,
+            source_chain,
+            memo: None,
+            tier: DeliveryTier::Standard,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            wrapped_supply: ctx.accounts.wrapped_mint.supply,
+        });
+        emit!(CallDelivered {
+            recipient_program: ctx.accounts.recipient_program.key(),
+            escrow: ctx.accounts.escrow.key(),
+            amount: local_amount,
+            payload_hash: message.payload_hash,
+            source_addr,
+            source_tx,
+            source_chain,
+        });
+
+        Ok(())
+    }
+
+    pub fn post_attestation(ctx: Context<PostAttestation>, message: TransferMessage) -> Result<()> {
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        message.check_deadline()?;
+        check_memo(&message.memo)?;
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            None,
+            message.nonce,
+        )?;
+
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.source_chain = message.source_chain;
+        attestation.source_tx = message.source_tx;
+        attestation.token = message.token;
+        attestation.amount = message.amount;
+        attestation.recipient = message.recipient;
+        attestation.nonce = message.nonce;
+        attestation.relayer_fee = message.relayer_fee;
+        attestation.native_drop = message.native_drop;
+        attestation.deadline_unix = message.deadline_unix;
+        attestation.memo = message.memo.clone();
+        attestation.tier = message.tier;
+        attestation.source_addr = message.source_addr;
+        attestation.guardian_set_index = ctx.accounts.guardian_set.index;
+        attestation.posted_slot = Clock::get()?.slot;
+        attestation.executable_after_slot = attestation
+            .posted_slot
+            .saturating_add(ctx.accounts.config.execution_delay(message.amount));
+        attestation.executed = false;
+        attestation.held = false;
+        attestation.cancelled = false;
+        attestation.vetoed_by = None;
+        attestation.bump = ctx.bumps.attestation;
+
+        emit!(AttestationPosted {
+            source_chain: message.source_chain,
+            source_tx: message.source_tx,
+            token: message.token,
+            amount: message.amount,
+            recipient: message.recipient,
+            nonce: message.nonce,
+            guardian_set_index: attestation.guardian_set_index,
+            slot: attestation.posted_slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteRelease<'info>>,
+        _source_chain: [u8; 32],
+        _source_tx: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        BlockedAddress::check(&ctx.accounts.blocked_address)?;
+        RelayerRegistry::check_delivery(
+            ctx.accounts.relayer_registry.as_ref(),
+            &ctx.accounts.source_route,
+            ctx.accounts.executor.key,
+        )?;
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+
+        let attestation = &mut ctx.accounts.attestation;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+
+        attestation.check_executable(slot)?;
+        check_deadline(attestation.deadline_unix)?;
+        require!(!attestation.executed, BridgeError::AlreadyProcessed);
+        let amount = attestation.amount;
+        // Checked ahead of any state change so an underfunded vault fails with
+        // its own error and the same transfer can be delivered once refilled
+        require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+        write_memo(ctx.accounts.memo_program.as_ref(), &attestation.memo)?;
+        check_not_frozen(
+            &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+            &[&ctx.accounts.recipient_token_account, &ctx.accounts.relayer_token_account],
+            attestation.source_chain,
+            attestation.source_tx,
+            slot,
+        )?;
+        if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+        ctx.accounts.token_registry.sub_locked(amount)?;
+
+        let mint_key = attestation.token;
+        let seeds: &[&[u8]] = &[b"vault", mint_key.as_ref(), &[ctx.bumps.vault]];
+        let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+        let fee = protocol_fee(amount, fee_bps);
+        let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+
+        let relayer_fee =
+            attestation.relayer_fee_for(&ctx.accounts.executor.key()).min(amount - fee);
+
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            seeds,
+            ctx.remaining_accounts,
+            &[
+                (&ctx.accounts.recipient_token_account, amount - fee - relayer_fee),
+                (&ctx.accounts.relayer_token_account, relayer_fee),
+                (&ctx.accounts.fee_vault, treasury_fee),
+            ],
+        )?;
+        let native_drop = attestation
+            .native_drop_for(&ctx.accounts.executor.key())
+            .min(ctx.accounts.config.max_native_drop);
+        drop_native(
+            &ctx.accounts.executor,
+            &ctx.accounts.recipient,
+            &ctx.accounts.system_program,
+            native_drop,
+        )?;
+        // Marked executed and processed only once the payout went through
+        attestation.executed = true;
+        record_processed(
+            &ctx.accounts.executor,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            attestation.source_chain,
+            attestation.source_tx,
+            attestation.guardian_set_index,
+            slot,
+        )?;
+
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+        emit!(Released {
+            transfer_id: attestation.transfer_id(&ctx.accounts.token_mint.key().to_bytes()),
+            recipient: attestation.recipient,
+            mint: ctx.accounts.token_mint.key(),
+            amount,
+            fee,
+            transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, amount - fee - relayer_fee)?,
+            relayer_fee,
+            native_drop,
+            source_tx: attestation.source_tx,
+            source_chain: attestation.source_chain,
+            memo: attestation.memo.clone(),
+            tier: attestation.tier,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            vault_balance: ctx.accounts.vault.amount,
+        });
+
+        Ok(())
+    }
+
+    /// `execute_release` for up to `MAX_RELEASE_BATCH` attestations of one
+    /// token. Each entry brings `(attestation, processed_record,
+    /// recipient_token_account, recipient, source_route, blocked_address)` in
+    /// `remaining_accounts`, in entry order, followed by any transfer-hook
+    /// accounts. One bad entry fails the
+    /// whole transaction, so nothing is delivered unless everything is.
+    pub fn batch_release<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchRelease<'info>>,
+        entries: Vec<BatchReleaseEntry>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            !entries.is_empty() && entries.len() <= MAX_RELEASE_BATCH,
+            BridgeError::InvalidReleaseBatch
+        );
+        let entry_accounts_len = entries.len() * 6;
+        require!(
+            ctx.remaining_accounts.len() >= entry_accounts_len,
+            BridgeError::InvalidReleaseBatch
+        );
+        let (entry_accounts, hook_accounts) = ctx.remaining_accounts.split_at(entry_accounts_len);
+
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        let mint_key = ctx.accounts.token_mint.key();
+        let total = entries
+            .iter()
+            .try_fold(0u64, |total, entry| total.checked_add(entry.amount))
+            .ok_or(BridgeError::InvalidAmount)?;
+        // Checked ahead of any state change so an underfunded vault fails with
+        // its own error and the same transfer can be delivered once refilled
+        require!(ctx.accounts.vault.amount >= total, BridgeError::InsufficientVaultBalance);
+        if ctx.accounts.token_registry.record_outflow(total, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        ctx.accounts.token_registry.record_daily_outflow(total, slot)?;
+        ctx.accounts.token_registry.sub_locked(total)?;
+
+        let executor = ctx.accounts.executor.key();
+        let seeds: &[&[u8]] = &[b"vault", mint_key.as_ref(), &[ctx.bumps.vault]];
+        let mut vault_balance = ctx.accounts.vault.amount;
+        for (entry, accounts) in entries.iter().zip(entry_accounts.chunks_exact(6)) {
+            let [attestation_info, record_info, recipient_token_info, recipient_info, route_info, blocked_info] =
+                accounts
+            else {
+                return err!(BridgeError::InvalidReleaseBatch);
+            };
+            let (blocked_key, _) =
+                Pubkey::find_program_address(&[b"blocked", entry.recipient.as_ref()], &crate::ID);
+            require_keys_eq!(blocked_info.key(), blocked_key, BridgeError::InvalidReleaseBatch);
+            BlockedAddress::check(blocked_info)?;
+            let (route_key, _) =
+                Pubkey::find_program_address(&[b"route", entry.source_chain.as_ref()], &crate::ID);
+            require_keys_eq!(route_info.key(), route_key, BridgeError::InvalidReleaseBatch);
+            RelayerRegistry::check_delivery(
+                ctx.accounts.relayer_registry.as_ref(),
+                route_info,
+                &executor,
+            )?;
+            let mut attestation = Account::<Attestation>::try_from(attestation_info)?;
+            require!(
+                attestation.source_chain == entry.source_chain
+                    && attestation.source_tx == entry.source_tx
+                    && attestation.amount == entry.amount,
+                BridgeError::InvalidReleaseBatch
+            );
+            require_keys_eq!(attestation.token, mint_key, BridgeError::MintMismatch);
+            require_keys_eq!(attestation.recipient, entry.recipient, BridgeError::RecipientMismatch);
+            attestation.check_executable(slot)?;
+            check_deadline(attestation.deadline_unix)?;
+            require!(!attestation.executed, BridgeError::AlreadyProcessed);
+
+            let recipient_token_account = InterfaceAccount::<TokenAccount>::try_from(recipient_token_info)?;
+            require_keys_eq!(recipient_token_account.owner, entry.recipient, BridgeError::RecipientMismatch);
+            require_keys_eq!(recipient_token_account.mint, mint_key, BridgeError::RecipientMismatch);
+            require_keys_eq!(recipient_info.key(), entry.recipient, BridgeError::RecipientMismatch);
+            write_memo(ctx.accounts.memo_program.as_ref(), &attestation.memo)?;
+            check_not_frozen(
+                &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+                &[&recipient_token_account, &ctx.accounts.relayer_token_account],
+                entry.source_chain,
+                entry.source_tx,
+                slot,
+            )?;
+
+            let (record_key, record_bump) = Pubkey::find_program_address(
+                &[b"processed", entry.source_chain.as_ref(), entry.source_tx.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(record_info.key(), record_key, BridgeError::InvalidReleaseBatch);
+            require!(record_info.data_is_empty(), BridgeError::AlreadyProcessed);
+
+            let amount = entry.amount;
+            let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, vault_balance);
+            let fee = protocol_fee(amount, fee_bps);
+            let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+            let relayer_fee = attestation.relayer_fee_for(&executor).min(amount - fee);
+            pay_out_release(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_mint,
+                &ctx.accounts.vault,
+                seeds,
+                hook_accounts,
+                &[
+                    (&recipient_token_account, amount - fee - relayer_fee),
+                    (&ctx.accounts.relayer_token_account, relayer_fee),
+                    (&ctx.accounts.fee_vault, treasury_fee),
+                ],
+            )?;
+            vault_balance = vault_balance.saturating_sub(amount);
+            let native_drop = attestation
+                .native_drop_for(&executor)
+                .min(ctx.accounts.config.max_native_drop);
+            drop_native(
+                &ctx.accounts.executor,
+                recipient_info,
+                &ctx.accounts.system_program,
+                native_drop,
+            )?;
+            // Marked executed and processed only once the payout went through
+            record_processed(
+                &ctx.accounts.executor,
+                &UncheckedAccount::try_from(record_info),
+                &ctx.accounts.system_program,
+                record_bump,
+                entry.source_chain,
+                entry.source_tx,
+                attestation.guardian_set_index,
+                slot,
+            )?;
+            attestation.executed = true;
+            attestation.exit(&crate::ID)?;
+
+            ctx.accounts.vault.reload()?;
+            emit!(Released {
+                transfer_id: attestation.transfer_id(&mint_key.to_bytes()),
+                recipient: entry.recipient,
+                mint: ctx.accounts.token_mint.key(),
+                amount,
+                fee,
+                transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, amount - fee - relayer_fee)?,
+                relayer_fee,
+                native_drop,
+                source_tx: entry.source_tx,
+                source_chain: entry.source_chain,
+                memo: attestation.memo.clone(),
+                tier: attestation.tier,
+                recipient_account_created: false,
+                slot,
+                unix_timestamp: clock.unix_timestamp,
+                vault_balance: ctx.accounts.vault.amount,
+            });
+        }
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+
+        Ok(())
+    }
+
+    pub fn execute_mint(
+        ctx: Context<ExecuteMint>,
+        source_chain: [u8; 32],
+        _source_tx: [u8; 32],
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        BlockedAddress::check(&ctx.accounts.blocked_address)?;
+        RelayerRegistry::check_delivery(
+            ctx.accounts.relayer_registry.as_ref(),
+            &ctx.accounts.source_route,
+            ctx.accounts.executor.key,
+        )?;
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+
+        let attestation = &mut ctx.accounts.attestation;
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+
+        attestation.check_executable(slot)?;
+        check_deadline(attestation.deadline_unix)?;
+        require!(!attestation.executed, BridgeError::AlreadyProcessed);
+        let amount = attestation.amount;
+        let (local_amount, dust) = ctx.accounts.wrapped_asset.to_local(amount)?;
+        write_memo(ctx.accounts.memo_program.as_ref(), &attestation.memo)?;
+        check_not_frozen(
+            &[],
+            &[&ctx.accounts.recipient_token_account, &ctx.accounts.relayer_token_account],
+            attestation.source_chain,
+            attestation.source_tx,
+            slot,
+        )?;
+        if ctx.accounts.token_registry.record_outflow(local_amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        ctx.accounts.token_registry.record_daily_outflow(local_amount, slot)?;
+
+        attestation.executed = true;
+        record_processed(
+            &ctx.accounts.executor,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            attestation.source_chain,
+            attestation.source_tx,
+            attestation.guardian_set_index,
+            slot,
+        )?;
+
+        ctx.accounts
+            .wrapped_asset
+            .check_supply_cap(ctx.accounts.wrapped_mint.supply, local_amount)?;
+        let (relayer_fee, _) = ctx
+            .accounts
+            .wrapped_asset
+            .to_local(attestation.relayer_fee_for(&ctx.accounts.executor.key()))?;
+
+        ctx.accounts.wrapped_asset.with_mint_seeds(|seeds| {
+            for (to, value) in [
+                (&ctx.accounts.recipient_token_account, local_amount - relayer_fee),
+                (&ctx.accounts.relayer_token_account, relayer_fee),
+            ] {
+                if value == 0 {
+                    continue;
+                }
+                token_interface::mint_to(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        MintTo {
+                            mint: ctx.accounts.wrapped_mint.to_account_info(),
+                            to: to.to_account_info(),
+                            authority: ctx.accounts.wrapped_mint.to_account_info(),
+                        },
+                        &[seeds],
+                    ),
+                    value,
+                )?;
+            }
+            Ok(())
+        })?;
+        let native_drop = attestation
+            .native_drop_for(&ctx.accounts.executor.key())
+            .min(ctx.accounts.config.max_native_drop);
+        drop_native(
+            &ctx.accounts.executor,
+            &ctx.accounts.recipient,
+            &ctx.accounts.system_program,
+            native_drop,
+        )?;
+
+        ctx.accounts.wrapped_mint.reload()?;
+        emit!(WrappedMinted {
+            transfer_id: attestation.transfer_id(&ctx.accounts.wrapped_asset.source_token),
+            recipient: attestation.recipient,
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            source_token: ctx.accounts.wrapped_asset.source_token,
+            amount,
+            minted: local_amount,
+            dust,
+            relayer_fee,
+            native_drop,
+            source_tx: attestation.source_tx,
+            source_chain,
+            memo: attestation.memo.clone(),
+            tier: attestation.tier,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            wrapped_supply: ctx.accounts.wrapped_mint.supply,
+        });
+
+        Ok(())
+    }
+
+    /// Fast path for small transfers: a relayer claims a release on its own
+    /// word, locking `amount * optimistic_bond_bps` of the token as a bond,
+    /// and the claim pays out once `optimistic_window_slots` pass without a
+    /// challenge. The replay marker is taken now, so the transfer cannot also
+    /// be delivered through the guardian path while the claim stands. Amounts
+    /// large enough to queue cannot go this way.
+    pub fn optimistic_release(
+        ctx: Context<OptimisticRelease>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        amount: u64,
+        recipient: Pubkey,
+        nonce: u64,
+        source_addr: [u8; 32],
+    ) -> Result<()> {
+        let state = ctx.accounts.bridge_state.load()?;
+        require!(!state.is_paused(), BridgeError::BridgePaused);
+        let guardian_set_index = state.guardian_set_index;
+        drop(state);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+        RelayerRegistry::check_delivery(
+            ctx.accounts.relayer_registry.as_ref(),
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer.key,
+        )?;
+
+        let config = &ctx.accounts.config;
+        require!(config.optimistic_window_slots > 0, BridgeError::OptimisticReleaseDisabled);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(!config.is_large_release(amount), BridgeError::OptimisticAmountTooLarge);
+        let bond = config.optimistic_bond(amount)?;
+
+        let slot = Clock::get()?.slot;
+        record_processed(
+            &ctx.accounts.relayer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            guardian_set_index,
+            slot,
+        )?;
+
+        let bond_seeds: &[&[u8]] = &[
+            b"optimistic_bond",
+            source_chain.as_ref(),
+            source_tx.as_ref(),
+            &[ctx.bumps.bond_escrow],
+        ];
+        create_self_owned_token_account(
+            &ctx.accounts.relayer,
+            &ctx.accounts.bond_escrow,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            bond_seeds,
+        )?;
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.relayer_token_account.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.bond_escrow.to_account_info(),
+                    authority: ctx.accounts.relayer.to_account_info(),
+                },
+            ),
+            bond,
+            ctx.accounts.token_mint.decimals,
+        )?;
+
+        let claim = &mut ctx.accounts.optimistic_release;
+        claim.source_chain = source_chain;
+        claim.source_tx = source_tx;
+        claim.token = ctx.accounts.token_mint.key();
+        claim.amount = amount;
+        claim.recipient = recipient;
+        claim.nonce = nonce;
+        claim.source_addr = source_addr;
+        claim.relayer = ctx.accounts.relayer.key();
+        claim.bond = bond;
+        claim.posted_slot = slot;
+        claim.finalizable_after_slot = slot.saturating_add(config.optimistic_window_slots);
+        claim.finalized = false;
+        claim.challenged = false;
+        claim.bump = ctx.bumps.optimistic_release;
+
+        emit!(OptimisticReleasePosted {
+            source_chain,
+            source_tx,
+            token: claim.token,
+            amount,
+            recipient,
+            relayer: claim.relayer,
+            bond,
+            finalizable_after_slot: claim.finalizable_after_slot,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Voids an optimistic claim inside its window with a guardian-signed
+    /// `TransferMessage` for the same source transaction that disagrees on
+    /// the token, amount or recipient; a fabricated transaction is answered
+    /// with an attestation of amount 0. The bond goes to the token's
+    /// insurance vault and the replay marker is released, so the attested
+    /// transfer can then be delivered normally.
+    pub fn challenge_optimistic_release(
+        ctx: Context<ChallengeOptimisticRelease>,
+        message: TransferMessage,
+    ) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        let claim = &ctx.accounts.optimistic_release;
+        require!(slot < claim.finalizable_after_slot, BridgeError::ChallengeWindowClosed);
+        require!(
+            message.source_chain == claim.source_chain && message.source_tx == claim.source_tx,
+            BridgeError::InvalidSlashEvidence
+        );
+        require!(claim.conflicts_with(&message), BridgeError::InvalidSlashEvidence);
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+
+        let bond_seeds: &[&[u8]] = &[
+            b"optimistic_bond",
+            claim.source_chain.as_ref(),
+            claim.source_tx.as_ref(),
+            &[ctx.bumps.bond_escrow],
+        ];
+        let slashed = sweep_and_close_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.bond_escrow,
+            &ctx.accounts.insurance_vault,
+            &ctx.accounts.relayer,
+            bond_seeds,
+        )?;
+
+        // Release the replay marker so the attested transfer stays deliverable
+        let record_info = ctx.accounts.processed_record.to_account_info();
+        let relayer_info = ctx.accounts.relayer.to_account_info();
+        **relayer_info.try_borrow_mut_lamports()? += record_info.lamports();
+        **record_info.try_borrow_mut_lamports()? = 0;
+        record_info.assign(&system_program::ID);
+        record_info.realloc(0, false)?;
+
+        let claim = &mut ctx.accounts.optimistic_release;
+        claim.challenged = true;
+
+        emit!(OptimisticReleaseChallenged {
+            source_chain: claim.source_chain,
+            source_tx: claim.source_tx,
+            relayer: claim.relayer,
+            challenger: ctx.accounts.challenger.key(),
+            slashed,
+            guardian_set_index: ctx.accounts.guardian_set.index,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out an unchallenged optimistic claim once its window has passed
+    /// and hands the bond back to the relayer. Anyone may crank it.
+    pub fn finalize_optimistic_release<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinalizeOptimisticRelease<'info>>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+
+        let clock = Clock::get()?;
+        let slot = clock.slot;
+        let claim = &ctx.accounts.optimistic_release;
+        require!(slot >= claim.finalizable_after_slot, BridgeError::ChallengeWindowOpen);
+        let (source_chain, source_tx, amount) = (claim.source_chain, claim.source_tx, claim.amount);
+        // Checked ahead of any state change so an underfunded vault fails with
+        // its own error and the same transfer can be delivered once refilled
+        require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+
+        check_not_frozen(
+            &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+            &[&ctx.accounts.recipient_token_account],
+            source_chain,
+            source_tx,
+            slot,
+        )?;
+        if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+            return err!(BridgeError::CircuitBreakerTripped);
+        }
+        // Taken here rather than at the claim, which a challenge may void
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            &ctx.accounts.optimistic_release.relayer,
+            Some(&ctx.accounts.blocked_address),
+            ctx.accounts.optimistic_release.nonce,
+        )?;
+        ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+        ctx.accounts.token_registry.sub_locked(amount)?;
+
+        let mint_key = ctx.accounts.token_mint.key();
+        let seeds: &[&[u8]] = &[b"vault", mint_key.as_ref(), &[ctx.bumps.vault]];
+        let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+        let fee = protocol_fee(amount, fee_bps);
+        let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            seeds,
+            ctx.remaining_accounts,
+            &[
+                (&ctx.accounts.recipient_token_account, amount - fee),
+                (&ctx.accounts.fee_vault, treasury_fee),
+            ],
+        )?;
+
+        let bond_seeds: &[&[u8]] = &[
+            b"optimistic_bond",
+            source_chain.as_ref(),
+            source_tx.as_ref(),
+            &[ctx.bumps.bond_escrow],
+        ];
+        let bond_returned = sweep_and_close_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.bond_escrow,
+            &ctx.accounts.relayer_token_account,
+            &ctx.accounts.relayer,
+            bond_seeds,
+        )?;
+
+        let claim = &mut ctx.accounts.optimistic_release;
+        claim.finalized = true;
+
+        ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+        emit!(Released {
+            transfer_id: claim.transfer_id(),
+            recipient: claim.recipient,
+            mint: ctx.accounts.token_mint.key(),
+            amount,
+            fee,
+            transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, amount - fee)?,
+            relayer_fee: 0,
+            native_drop: 0,
+            source_tx,
+            source_chain,
+            memo: None,
+            tier: DeliveryTier::Standard,
+            recipient_account_created: false,
+            slot,
+            unix_timestamp: clock.unix_timestamp,
+            vault_balance: ctx.accounts.vault.amount,
+        });
+        emit!(OptimisticReleaseFinalized {
+            source_chain,
+            source_tx,
+            relayer: claim.relayer,
+            bond_returned,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Read-only and signer-free, so relayers can simulate it to learn
+    /// whether a transfer was delivered; the answer comes back as return data.
+    pub fn check_processed(
+        ctx: Context<CheckProcessed>,
+        _source_chain: [u8; 32],
+        _source_tx: [u8; 32],
+    ) -> Result<bool> {
+        let record = &ctx.accounts.processed_record;
+        Ok(record.owner == &crate::ID && !record.data_is_empty())
+    }
+
+    /// Reclaims a replay record whose retention has passed and whose guardian
+    /// set is retired. Guardian digests bind the signing set's index, so no
+    /// live set can verify the closed transfer's signatures again.
+    pub fn close_processed_record(
+        ctx: Context<CloseProcessedRecord>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+    ) -> Result<()> {
+        let record = &ctx.accounts.processed_record;
+        let slot = Clock::get()?.slot;
+        if let Some(blocker) = record.close_blocker(
+            &ctx.accounts.config,
+            &ctx.accounts.source_route,
+            &ctx.accounts.guardian_set,
+            ctx.accounts.bridge_state.load()?.guardian_set_index,
+            slot,
+        )? {
+            return Err(blocker.into());
+        }
+
+        emit!(ProcessedRecordClosed {
+            source_chain,
+            source_tx,
+            rent_collector: ctx.accounts.rent_collector.key(),
+            lamports: record.to_account_info().lamports(),
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless crank over `(record, guardian_set, source_route)`
+    /// triples in remaining accounts. Records `close_processed_record` would
+    /// refuse are skipped; the rest are closed with `prune_incentive_bps` of
+    /// their rent going to the cranker and the remainder to the rent
+    /// collector.
+    pub fn prune_processed(ctx: Context<PruneProcessed>, max_records: u8) -> Result<()> {
+        require!(
+            max_records > 0 && max_records <= MAX_PRUNE_BATCH,
+            BridgeError::InvalidPruneBatch
+        );
+        let triples = ctx.remaining_accounts.chunks_exact(3);
+        require!(triples.remainder().is_empty(), BridgeError::InvalidPruneBatch);
+
+        let config = &ctx.accounts.config;
+        let current_index = ctx.accounts.bridge_state.load()?.guardian_set_index;
+        let slot = Clock::get()?.slot;
+        let cranker = ctx.accounts.cranker.to_account_info();
+        let rent_collector = ctx.accounts.rent_collector.to_account_info();
+
+        let mut pruned: u32 = 0;
+        let mut skipped: u32 = 0;
+        let mut incentive_lamports: u64 = 0;
+        for accounts in triples.take(max_records as usize) {
+            let [record_info, guardian_set_info, route_info] = accounts else {
+                return err!(BridgeError::InvalidPruneBatch);
+            };
+            require!(record_info.owner == &crate::ID, BridgeError::InvalidPruneBatch);
+            let record = ProcessedRecord::try_deserialize(&mut &record_info.try_borrow_data()?[..])?;
+
+            let (guardian_set_key, _) = Pubkey::find_program_address(
+                &[b"guardian_set", record.guardian_set_index.to_le_bytes().as_ref()],
+                &crate::ID,
+            );
+            let (route_key, _) =
+                Pubkey::find_program_address(&[b"route", record.source_chain.as_ref()], &crate::ID);
+            require_keys_eq!(guardian_set_info.key(), guardian_set_key, BridgeError::InvalidPruneBatch);
+            require_keys_eq!(route_info.key(), route_key, BridgeError::InvalidPruneBatch);
+
+            if record
+                .close_blocker(config, route_info, guardian_set_info, current_index, slot)?
+                .is_some()
+            {
+                skipped += 1;
+                continue;
+            }
+
+            let lamports = record_info.lamports();
+            let incentive = (lamports as u128 * config.prune_incentive_bps as u128
+                / BPS_DENOMINATOR as u128) as u64;
+            **record_info.try_borrow_mut_lamports()? = 0;
+            **cranker.try_borrow_mut_lamports()? += incentive;
+            **rent_collector.try_borrow_mut_lamports()? += lamports - incentive;
+            record_info.assign(&system_program::ID);
+            record_info.realloc(0, false)?;
+
+            pruned += 1;
+            incentive_lamports += incentive;
+        }
+
+        emit!(ProcessedRecordsPruned {
+            cranker: cranker.key(),
+            pruned,
+            skipped,
+            incentive_lamports,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_watcher(ctx: Context<AddWatcher>, watcher: Pubkey) -> Result<()> {
+        let watcher_set = &mut ctx.accounts.watcher_set;
+        if watcher_set.bump == 0 {
+            watcher_set.bump = ctx.bumps.watcher_set;
+        }
+
+        require!(!watcher_set.watchers.contains(&watcher), BridgeError::DuplicateWatcher);
+        require!(watcher_set.watchers.len() < MAX_WATCHERS, BridgeError::WatcherSetFull);
+        watcher_set.watchers.push(watcher);
+
+        emit!(WatcherAdded {
+            admin: ctx.accounts.admin.key(),
+            watcher,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_watcher(ctx: Context<RemoveWatcher>, watcher: Pubkey) -> Result<()> {
+        let watcher_set = &mut ctx.accounts.watcher_set;
+
+        let position = watcher_set
+            .watchers
+            .iter()
+            .position(|w| *w == watcher)
+            .ok_or(BridgeError::WatcherNotFound)?;
+        watcher_set.watchers.remove(position);
+
+        emit!(WatcherRemoved {
+            admin: ctx.accounts.admin.key(),
+            watcher,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_relayer(ctx: Context<AddRelayer>, relayer: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.relayer_registry;
+        if registry.bump == 0 {
+            registry.bump = ctx.bumps.relayer_registry;
+        }
+
+        require!(!registry.relayers.contains(&relayer), BridgeError::DuplicateRelayer);
+        require!(registry.relayers.len() < MAX_RELAYERS, BridgeError::RelayerRegistryFull);
+        registry.relayers.push(relayer);
+
+        emit!(RelayerAdded {
+            admin: ctx.accounts.admin.key(),
+            relayer,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_relayer(ctx: Context<RemoveRelayer>, relayer: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.relayer_registry;
+
+        let position = registry
+            .relayers
+            .iter()
+            .position(|r| *r == relayer)
+            .ok_or(BridgeError::RelayerNotFound)?;
+        registry.relayers.remove(position);
+
+        emit!(RelayerRemoved {
+            admin: ctx.accounts.admin.key(),
+            relayer,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Holds a queued release for the admin to resolve. Only possible during
+    /// its delay, before `executable_after_slot`.
+    pub fn veto_release(
+        ctx: Context<VetoRelease>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+    ) -> Result<()> {
+        let attestation = &mut ctx.accounts.attestation;
+        require!(!attestation.executed, BridgeError::AlreadyProcessed);
+        require!(!attestation.cancelled, BridgeError::ReleaseCancelled);
+        let clock = Clock::get()?;
+        require!(
+            clock.slot < attestation.executable_after_slot,
+            BridgeError::VetoWindowClosed
+        );
+
+        attestation.held = true;
+        attestation.vetoed_by = Some(ctx.accounts.watcher.key());
+
+        emit!(ReleaseVetoed {
+            watcher: ctx.accounts.watcher.key(),
+            source_chain,
+            source_tx,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Clears a watcher hold, either re-enabling the release or cancelling it
+    /// for good. A cancelled attestation is kept rather than closed so the same
+    /// guardian signatures cannot simply be posted again.
+    pub fn resolve_veto(
+        ctx: Context<ResolveVeto>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        approve: bool,
+    ) -> Result<()> {
+        let attestation = &mut ctx.accounts.attestation;
+        require!(attestation.held, BridgeError::ReleaseNotHeld);
+
+        attestation.held = false;
+        attestation.cancelled = !approve;
+
+        emit!(VetoResolved {
+            admin: ctx.accounts.admin.key(),
+            source_chain,
+            source_tx,
+            approved: approve,
+            vetoed_by: attestation.vetoed_by,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// `on_behalf_of`, `deadline_unix`, both memos and `tier` are handled like
+    /// they are for `lock`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn burn_wrapped(
+        ctx: Context<BurnWrapped>,
+        amount: u64,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+        relayer_fee: u64,
+        native_drop: u64,
+        referrer: Option<Pubkey>,
+        on_behalf_of: Option<Pubkey>,
+        deadline_unix: i64,
+        delivery_memo: Option<String>,
+        memo: Option<String>,
+        tier: DeliveryTier,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        BlockedAddress::check(&ctx.accounts.blocked_address)?;
+        require!(!ctx.accounts.token_registry.paused, BridgeError::TokenPaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(
+            target_addr.len() <= ctx.accounts.config.max_target_addr_len as usize,
+            BridgeError::InvalidTargetAddress
+        );
+        require!(
+            ctx.accounts.wrapped_asset.multi_hop || target_chain == ctx.accounts.wrapped_asset.source_chain,
+            BridgeError::NotHomeChain
+        );
+        ctx.accounts.config.check_amount(amount)?;
+        check_deadline(deadline_unix)?;
+        check_memo(&delivery_memo)?;
+        ctx.accounts.config.check_memo(&memo)?;
+        ctx.accounts.token_registry.check_amount(amount)?;
+
+        check_target_chain(&target_chain)?;
+        let route = ChainRoute::load(&ctx.accounts.chain_route)?;
+        route.validate_target(&target_addr)?;
+
+        let clock = Clock::get()?;
+        record_user_activity(
+            ctx.accounts.user_activity.as_mut(),
+            ctx.bumps.user_activity,
+            &ctx.accounts.config,
+            ctx.accounts.user.key(),
+            ctx.accounts.token_registry.mint,
+            amount,
+            clock.epoch,
+        )?;
+
+        let exempt = ctx.accounts.fee_exemption.is_some();
+        let quote = ctx.accounts.token_registry.quote_fee(&ctx.accounts.config, amount, exempt)?;
+        // The source side only sees whole source units, so the gross must be a
+        // multiple of one and the fee is rounded up to one
+        let asset = &ctx.accounts.wrapped_asset;
+        asset.to_source(amount)?;
+        let unit = asset.source_unit()?;
+        let priority_fee = ctx.accounts.config.priority_fee(tier, amount);
+        let fee = quote
+            .fee
+            .checked_add(priority_fee)
+            .and_then(|fee| fee.div_ceil(unit).checked_mul(unit))
+            .ok_or(BridgeError::CounterOverflow)?;
+        let net_amount = amount.checked_sub(fee).filter(|net| *net > 0).ok_or(BridgeError::InvalidAmount)?;
+        let source_amount = asset.to_source(net_amount)?;
+        require!(relayer_fee < source_amount, BridgeError::InvalidRelayerFee);
+        require!(
+            native_drop <= ctx.accounts.config.max_native_drop,
+            BridgeError::NativeDropTooLarge
+        );
+        let (treasury_fee, insurance_fee) = ctx.accounts.config.split_fee(fee);
+        let referral_fee = accrue_referral(
+            &mut ctx.accounts.token_registry,
+            ctx.accounts.referrer_account.as_mut(),
+            referrer,
+            treasury_fee,
+            ctx.accounts.config.referral_share_bps,
+        )?;
+
+        write_memo(ctx.accounts.memo_program.as_ref(), &memo)?;
+
+        // The fee stays wrapped in the fee vaults; only the net leaves supply
+        for (to, value) in [
+            (&ctx.accounts.fee_vault, treasury_fee),
+            (&ctx.accounts.insurance_vault, insurance_fee),
+        ] {
+            if value == 0 {
+                continue;
+            }
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.user_token_account.to_account_info(),
+                        mint: ctx.accounts.wrapped_mint.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    }
+                ),
+                value,
+                ctx.accounts.wrapped_mint.decimals
+            )?;
+        }
+
+        // Burn wrapped tokens
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.wrapped_mint.to_account_info(),
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                }
+            ),
+            net_amount
+        )?;
+        
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+        let sequence = ctx.accounts.burn_sequence.advance(
+            ctx.accounts.wrapped_mint.key(),
+            target_chain,
+            ctx.bumps.burn_sequence,
+            bridge_state.nonce,
+            &clock,
+        )?;
+        bridge_state.next_nonce()?;
+        
+        ctx.accounts.wrapped_mint.reload()?;
+        emit!(WrappedBurned {
+            transfer_id: transfer_id(
+                &LOCAL_CHAIN_ID,
+                &target_chain,
+                ctx.accounts.user.key.as_ref(),
+                &target_addr,
+                &ctx.accounts.wrapped_asset.source_token,
+                source_amount,
+                bridge_state.nonce,
+            ),
+            source: *ctx.accounts.user.key,
+            on_behalf_of,
+            wrapped_mint: ctx.accounts.wrapped_mint.key(),
+            amount: net_amount,
+            gross_amount: amount,
+            fee,
+            net_amount,
+            source_amount,
+            source_decimals: ctx.accounts.wrapped_asset.source_decimals,
+            insurance_fee,
+            priority_fee,
+            exempt,
+            relayer_fee,
+            native_drop,
+            referrer,
+            referral_fee,
+            self_referral: referrer == Some(ctx.accounts.user.key()),
+            target_chain,
+            target_addr,
+            nonce: bridge_state.nonce,
+            sequence,
+            deadline_unix,
+            delivery_memo,
+            memo,
+            tier,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            wrapped_supply: ctx.accounts.wrapped_mint.supply,
+        });
+        
+        Ok(())
+    }
+
+    /// Escrows a Metaplex NFT, a mint with supply 1 and no decimals, in its
+    /// own `[b"nft_vault", mint]` account, recorded by an `NftEscrow` until
+    /// `release_nft`, and emits `NftLocked` with the metadata's name, symbol,
+    /// URI and creators and their hash, which guardians attest for
+    /// `mint_wrapped_nft` on the destination. Wrapped NFTs go home through
+    /// `burn_wrapped_nft` instead.
+    /// Programmable NFTs are refused, since moving them needs the Token
+    /// Metadata program and their rule set.
+    pub fn lock_nft(
+        ctx: Context<LockNft>,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        BlockedAddress::check(&ctx.accounts.blocked_address)?;
+        require!(
+            target_addr.len() <= ctx.accounts.config.max_target_addr_len as usize,
+            BridgeError::InvalidTargetAddress
+        );
+        check_target_chain(&target_chain)?;
+        let route = ChainRoute::load(&ctx.accounts.chain_route)?;
+        route.validate_target(&target_addr)?;
+
+        let source_metadata = &ctx.accounts.metadata;
+        require!(
+            !matches!(
+                source_metadata.token_standard,
+                Some(TokenStandard::ProgrammableNonFungible)
+                    | Some(TokenStandard::ProgrammableNonFungibleEdition)
+            ) && source_metadata.programmable_config.is_none(),
+            BridgeError::ProgrammableNftUnsupported
+        );
+        let metadata = NftMetadata::from_metaplex(source_metadata);
+        let metadata_hash = metadata.hash()?;
+
+        let mint = ctx.accounts.nft_mint.key();
+        if ctx.accounts.nft_vault.data_is_empty() {
+            create_self_owned_token_account(
+                &ctx.accounts.payer,
+                &ctx.accounts.nft_vault,
+                &ctx.accounts.nft_mint,
+                &ctx.accounts.token_program,
+                &ctx.accounts.system_program,
+                &[b"nft_vault", mint.as_ref(), &[ctx.bumps.nft_vault]],
+            )?;
+        }
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            1,
+            0,
+        )?;
+
+        let clock = Clock::get()?;
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+        bridge_state.next_nonce()?;
+        let escrow = &mut ctx.accounts.nft_escrow;
+        escrow.mint = mint;
+        escrow.source = ctx.accounts.user.key();
+        escrow.rent_payer = ctx.accounts.payer.key();
+        escrow.nonce = bridge_state.nonce;
+        escrow.locked_slot = clock.slot;
+        escrow.bump = ctx.bumps.nft_escrow;
+
+        emit!(NftLocked {
+            source: ctx.accounts.user.key(),
+            mint,
+            target_chain,
+            target_addr,
+            nonce: bridge_state.nonce,
+            metadata,
+            metadata_hash,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Mints the wrapped counterpart of a foreign NFT on guardian signatures
+    /// over `NftTransferMessage::digest`. The mint is the
+    /// `[b"wrapped_nft", source_chain, source_token]` PDA, created on first
+    /// delivery with no decimals and itself as mint authority, and minted
+    /// again only once `burn_wrapped_nft` has brought its supply back to 0.
+    /// One token goes to the recipient's associated token account; the first
+    /// delivery also creates a Token Metadata account mirroring the attested
+    /// fields, with the creators left unverified. Replay protection and
+    /// delivery rules are those of `mint_wrapped`.
+    pub fn mint_wrapped_nft(
+        ctx: Context<MintWrappedNft>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        source_token: [u8; 32],
+        nonce: u64,
+        metadata: NftMetadata,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let message = NftTransferMessage {
+            source_chain,
+            source_tx,
+            source_token,
+            recipient: ctx.accounts.user.key(),
+            nonce,
+            metadata,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+        let data = message.metadata.to_data()?;
+
+        let slot = Clock::get()?.slot;
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+
+        let recipient_account_created = create_or_check_recipient_ata(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.user,
+            &ctx.accounts.wrapped_nft_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        let mint_info = ctx.accounts.wrapped_nft_mint.to_account_info();
+        let seeds: &[&[u8]] = &[
+            b"wrapped_nft",
+            source_chain.as_ref(),
+            source_token.as_ref(),
+            &[ctx.bumps.wrapped_nft_mint],
+        ];
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: mint_info.clone(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: mint_info.clone(),
+                },
+                &[seeds],
+            ),
+            1,
+        )?;
+        if ctx.accounts.metadata.data_is_empty() {
+            create_metadata_accounts_v3(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_metadata_program.to_account_info(),
+                    CreateMetadataAccountsV3 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        mint: mint_info.clone(),
+                        mint_authority: mint_info.clone(),
+                        payer: ctx.accounts.payer.to_account_info(),
+                        update_authority: mint_info.clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                data,
+                true,
+                true,
+                None,
+            )?;
+        }
+
+        let origin = &mut ctx.accounts.origin;
+        origin.wrapped_mint = mint_info.key();
+        origin.source_chain = source_chain;
+        origin.source_token = source_token;
+        origin.source_decimals = 0;
+        origin.bump = ctx.bumps.origin;
+
+        emit!(WrappedNftMinted {
+            recipient: message.recipient,
+            wrapped_mint: mint_info.key(),
+            source_chain,
+            source_token,
+            source_tx,
+            metadata_hash: message.metadata.hash()?,
+            recipient_account_created,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Burns a wrapped NFT to send it back to its home chain and emits
+    /// `WrappedNftBurned` with its origin, for guardians to attest the
+    /// release there. The mint is left at supply 0, ready for
+    /// `mint_wrapped_nft` should the NFT return.
+    pub fn burn_wrapped_nft(
+        ctx: Context<BurnWrappedNft>,
+        target_chain: [u8; 32],
+        target_addr: Vec<u8>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        BlockedAddress::check(&ctx.accounts.blocked_address)?;
+        require!(target_chain == ctx.accounts.origin.source_chain, BridgeError::NotHomeChain);
+        require!(
+            target_addr.len() <= ctx.accounts.config.max_target_addr_len as usize,
+            BridgeError::InvalidTargetAddress
+        );
+        check_target_chain(&target_chain)?;
+        let route = ChainRoute::load(&ctx.accounts.chain_route)?;
+        route.validate_target(&target_addr)?;
+
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.wrapped_nft_mint.to_account_info(),
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        ctx.accounts.wrapped_nft_mint.reload()?;
+        require!(ctx.accounts.wrapped_nft_mint.supply == 0, BridgeError::NotAnNft);
+
+        let mut bridge_state = ctx.accounts.bridge_state.load_mut()?;
+        bridge_state.next_nonce()?;
+
+        let origin = &ctx.accounts.origin;
+        emit!(WrappedNftBurned {
+            source: ctx.accounts.user.key(),
+            wrapped_mint: origin.wrapped_mint,
+            source_chain: origin.source_chain,
+            source_token: origin.source_token,
+            target_addr,
+            nonce: bridge_state.nonce,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Returns an NFT escrowed by `lock_nft` on guardian signatures over
+    /// `NftReleaseMessage::digest`, after the `mint_wrapped` replay checks.
+    /// The NFT goes to the recipient's associated token account, and the
+    /// vault and `NftEscrow` are closed to whoever locked it, so an NFT that
+    /// was never locked or is already back has no escrow to release from.
+    pub fn release_nft(
+        ctx: Context<ReleaseNft>,
+        source_chain: [u8; 32],
+        source_tx: [u8; 32],
+        nonce: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(
+            ChainRoute::replay_mode_of(&ctx.accounts.source_route)? == ReplayMode::TxHash,
+            BridgeError::WrongReplayMode
+        );
+
+        let mint = ctx.accounts.nft_mint.key();
+        let message = NftReleaseMessage {
+            source_chain,
+            source_tx,
+            mint,
+            recipient: ctx.accounts.user.key(),
+            nonce,
+        };
+        verify_guardian_signatures(
+            &ctx.accounts.instructions,
+            &ctx.accounts.guardian_set,
+            &message,
+        )?;
+
+        let slot = Clock::get()?.slot;
+        // Fails with its own error, before any state change, if the NFT is
+        // not in escrow
+        require!(ctx.accounts.nft_vault.amount == 1, BridgeError::InsufficientVaultBalance);
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            nonce,
+        )?;
+
+        let recipient_account_created = create_or_check_recipient_ata(
+            &ctx.accounts.payer,
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.user,
+            &ctx.accounts.nft_mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+        )?;
+
+        let seeds: &[&[u8]] = &[b"nft_vault", mint.as_ref(), &[ctx.bumps.nft_vault]];
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.nft_vault.to_account_info(),
+                },
+                &[seeds],
+            ),
+            1,
+            0,
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.nft_vault.to_account_info(),
+                destination: ctx.accounts.rent_receiver.to_account_info(),
+                authority: ctx.accounts.nft_vault.to_account_info(),
+            },
+            &[seeds],
+        ))?;
+        // Marked processed only once the NFT went out
+        record_processed(
+            &ctx.accounts.payer,
+            &ctx.accounts.processed_record,
+            &ctx.accounts.system_program,
+            ctx.bumps.processed_record,
+            source_chain,
+            source_tx,
+            ctx.accounts.guardian_set.index,
+            slot,
+        )?;
+
+        emit!(NftReleased {
+            recipient: message.recipient,
+            mint,
+            source_chain,
+            source_tx,
+            lock_nonce: ctx.accounts.nft_escrow.nonce,
+            recipient_account_created,
+            slot,
+        });
+
+        Ok(())
+    }
+
+    /// Deposits `amount` of `mint` into its vault as release liquidity and
+    /// mints LP shares from the `[b"lp_mint", mint]` PDA, priced against
+    /// `TokenRegistry::lp_assets`. Shares round down, in the pool's favour.
+    /// Only what the vault receives is credited, net of any Token-2022
+    /// transfer fee.
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidity<'info>>,
+        mint: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        require!(!ctx.accounts.token_registry.paused, BridgeError::TokenPaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let received = amount - token_transfer_fee(&ctx.accounts.token_mint, amount)?;
+        let registry = &ctx.accounts.token_registry;
+        let shares = lp_shares_for(received, registry.lp_assets, ctx.accounts.lp_mint.supply)?;
+        require!(shares > 0, BridgeError::InvalidAmount);
+
+        transfer_checked_with_hook(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.provider_token_account.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.provider.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.token_mint.decimals,
+        )?;
+        let lp_mint_info = ctx.accounts.lp_mint.to_account_info();
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: lp_mint_info.clone(),
+                    to: ctx.accounts.provider_lp_account.to_account_info(),
+                    authority: lp_mint_info,
+                },
+                &[&[b"lp_mint", mint.as_ref(), &[ctx.bumps.lp_mint]]],
+            ),
+            shares,
+        )?;
+
+        let registry = &mut ctx.accounts.token_registry;
+        registry.lp_assets = registry.lp_assets.saturating_add(received);
+
+        emit!(LiquidityAdded {
+            provider: ctx.accounts.provider.key(),
+            mint,
+            amount: received,
+            shares,
+            lp_assets: registry.lp_assets,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Burns `shares` for their pro-rata part of `lp_assets`, rounded down.
+    /// Only LP capital still in the vault can be withdrawn, so the locked
+    /// deposits backing the other side stay put. Releases beyond
+    /// `total_locked` draw on LP capital, subject to the daily limit and
+    /// circuit breaker as any other release, and later locks pay it back.
+    pub fn remove_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveLiquidity<'info>>,
+        mint: Pubkey,
+        shares: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+        ctx.accounts.token_registry.check_outflow_allowed()?;
+        require!(shares > 0, BridgeError::InvalidAmount);
+
+        let registry = &ctx.accounts.token_registry;
+        let amount = lp_assets_for(shares, registry.lp_assets, ctx.accounts.lp_mint.supply)?;
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(
+            amount <= registry.free_liquidity(ctx.accounts.vault.amount),
+            BridgeError::InsufficientLiquidity
+        );
+
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    from: ctx.accounts.provider_lp_account.to_account_info(),
+                    authority: ctx.accounts.provider.to_account_info(),
+                },
+            ),
+            shares,
+        )?;
+        pay_out_release(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint,
+            &ctx.accounts.vault,
+            &[b"vault", mint.as_ref(), &[ctx.bumps.vault]],
+            ctx.remaining_accounts,
+            &[(&ctx.accounts.provider_token_account, amount)],
+        )?;
+
+        let registry = &mut ctx.accounts.token_registry;
+        registry.lp_assets -= amount;
+        registry.check_vault(&mut ctx.accounts.vault)?;
+
+        emit!(LiquidityRemoved {
+            provider: ctx.accounts.provider.key(),
+            mint,
+            amount,
+            shares,
+            lp_assets: registry.lp_assets,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Created in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub bridge_state: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BridgeConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + GuardianSet::INIT_SPACE,
+        seeds = [b"guardian_set", 0u32.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The legacy layout may predate any usable `admin`, so the program's
+    /// upgrade authority performs the migration.
+    pub upgrade_authority: Signer<'info>,
+
+    /// CHECK: Parsed as the legacy layout and rewritten in the handler
+    #[account(mut, seeds = [b"bridge_state"], bump, owner = crate::ID)]
+    pub bridge_state: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ BridgeError::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::Bridge>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ BridgeError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct MigrateProcessed<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Chooses the source chain for each legacy hash, which the old list
+    /// never stored, so only the upgrade authority may drain it.
+    pub upgrade_authority: Signer<'info>,
+
+    /// CHECK: Parsed as the legacy layout and rewritten in the handler
+    #[account(mut, seeds = [b"bridge_state"], bump, owner = crate::ID)]
+    pub bridge_state: UncheckedAccount<'info>,
+
+    /// CHECK: Created in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ BridgeError::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::Bridge>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(upgrade_authority.key())
+            @ BridgeError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    /// Wrapped mints are burned, never vaulted
+    #[account(constraint = !is_bridge_wrapped(&token_mint) @ BridgeError::UseBurnWrappedInstead)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Only checked to exist, so vaults are never opened for unregistered mints
+    #[account(
+        seeds = [b"token", token_mint.key().as_ref()],
+        bump,
+        constraint = TokenRegistry::is_registered(&token_registry) @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: UncheckedAccount<'info>,
+
+    /// CHECK: Created and initialized as a token account in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"vault", token_mint.key().as_ref()],
+        bump,
+        constraint = vault.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Created and initialized as a token account in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"fee_vault", token_mint.key().as_ref()],
+        bump,
+        constraint = fee_vault.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub fee_vault: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Created and initialized as a token account in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"insurance_vault", token_mint.key().as_ref()],
+        bump,
+        constraint = insurance_vault.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub insurance_vault: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RotateGuardianSet<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = current_guardian_set.bump
+    )]
+    pub current_guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + GuardianSet::INIT_SPACE,
+        seeds = [b"guardian_set", (bridge_state.load()?.guardian_set_index + 1).to_le_bytes().as_ref()],
+        bump
+    )]
+    pub new_guardian_set: Account<'info, GuardianSet>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u32)]
+pub struct CloseGuardianSet<'info> {
+    pub admin: Signer<'info>,
+
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut)]
+    pub receiver: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = receiver,
+        seeds = [b"guardian_set", index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = index != bridge_state.load()?.guardian_set_index @ BridgeError::GuardianSetActive,
+        constraint = guardian_set.expires_at_slot < Clock::get()?.slot @ BridgeError::GuardianSetActive
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(guardian: GuardianKey)]
+pub struct AddGuardian<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    #[account(
+        seeds = [b"guardian_stake", guardian_stake.staker.as_ref()],
+        bump = guardian_stake.bump,
+        constraint = guardian_stake.guardian == guardian @ BridgeError::GuardianMismatch,
+        constraint = guardian_stake.is_bonded(staking_config.min_stake) @ BridgeError::GuardianNotStaked
+    )]
+    pub guardian_stake: Account<'info, GuardianStake>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGuardianSet<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStaking<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    pub stake_mint: Account<'info, token::Mint>,
+
+    /// Receives slashed stake
+    #[account(constraint = treasury.mint == stake_mint.key() @ BridgeError::InvalidConfig)]
+    pub treasury: Account<'info, token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + StakingConfig::INIT_SPACE,
+        seeds = [b"staking"],
+        bump
+    )]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"stake_vault"],
+        bump,
+        token::mint = stake_mint,
+        token::authority = stake_vault
+    )]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeGuardian<'info> {
+    /// Pays for the stake record on the first stake and gets its rent back
+    /// on unstake
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub staker: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = staker_token_account.owner == staker.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = staker_token_account.mint == staking_config.stake_mint
+            @ BridgeError::MintMismatch
+    )]
+    pub staker_token_account: Account<'info, token::TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + GuardianStake::INIT_SPACE,
+        seeds = [b"guardian_stake", staker.key().as_ref()],
+        bump
+    )]
+    pub guardian_stake: Account<'info, GuardianStake>,
+
+    #[account(mut, seeds = [b"stake_vault"], bump = staking_config.vault_bump)]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    pub staker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_stake", staker.key().as_ref()],
+        bump = guardian_stake.bump
+    )]
+    pub guardian_stake: Account<'info, GuardianStake>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub staker: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = staker_token_account.owner == staker.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = staker_token_account.mint == staking_config.stake_mint
+            @ BridgeError::MintMismatch
+    )]
+    pub staker_token_account: Account<'info, token::TokenAccount>,
+
+    #[account(
+        mut,
+        close = rent_receiver,
+        seeds = [b"guardian_stake", staker.key().as_ref()],
+        bump = guardian_stake.bump
+    )]
+    pub guardian_stake: Account<'info, GuardianStake>,
+
+    /// CHECK: Paid for the stake record; receives its rent back
+    #[account(mut, address = guardian_stake.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"stake_vault"], bump = staking_config.vault_bump)]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    #[account(
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SlashGuardian<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_stake", guardian_stake.staker.as_ref()],
+        bump = guardian_stake.bump,
+        constraint = !guardian_stake.slashed @ BridgeError::GuardianSlashed
+    )]
+    pub guardian_stake: Account<'info, GuardianStake>,
+
+    #[account(mut, seeds = [b"stake_vault"], bump = staking_config.vault_bump)]
+    pub stake_vault: Account<'info, token::TokenAccount>,
+
+    #[account(mut, address = staking_config.treasury @ BridgeError::TreasuryMismatch)]
+    pub treasury: Account<'info, token::TokenAccount>,
+
+    #[account(seeds = [b"staking"], bump = staking_config.bump)]
+    pub staking_config: Account<'info, StakingConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterToken<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TokenRegistry::INIT_SPACE,
+        seeds = [b"token", token_mint.key().as_ref()],
+        bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct CreateWrapped<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + WrappedAssetRegistry::INIT_SPACE,
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    /// CHECK: Created and initialized as a mint in the handler; must not exist yet
+    #[account(
+        mut,
+        seeds = [b"wrapped_mint", source_chain.as_ref(), source_token.as_ref()],
+        bump,
+        constraint = wrapped_mint.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub wrapped_mint: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + WrappedOrigin::INIT_SPACE,
+        seeds = [b"origin", wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub origin: Account<'info, WrappedOrigin>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + TokenRegistry::INIT_SPACE,
+        seeds = [b"token", wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct CreateWrappedMetadata<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch)]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Metadata PDA of `wrapped_mint`, created by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), wrapped_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct UpdateWrappedMetadata<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    /// CHECK: Update authority signer only; must be the registered mint
+    #[account(address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch)]
+    pub wrapped_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Must be the metadata PDA of `wrapped_mint`, so another mint's
+    /// metadata fails the seeds check
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), wrapped_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key(),
+        owner = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct SetWrappedSupplyCap<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct RepairWrappedAuthority<'info> {
+    pub admin: Signer<'info>,
+
+    /// Holder of the misplaced mint or freeze authority
+    pub current_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(mut, address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch)]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct RegisterLegacyWrappedAsset<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"wrapped_mint", source_chain.as_ref()],
+        bump,
+        constraint = has_wrapped_authorities(&wrapped_mint) @ BridgeError::InvalidWrappedMintAuthority
+    )]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + WrappedAssetRegistry::INIT_SPACE,
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + WrappedOrigin::INIT_SPACE,
+        seeds = [b"origin", wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub origin: Account<'info, WrappedOrigin>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct MigrateTokenRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: May be too short to deserialize; resized in the handler
+    #[account(mut, seeds = [b"token", mint.as_ref()], bump, owner = crate::ID)]
+    pub token_registry: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetTokenPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token", mint.as_ref()],
+        bump = token_registry.bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct TripCircuitBreaker<'info> {
+    pub watcher: Signer<'info>,
+
+    #[account(
+        seeds = [b"watchers"],
+        bump = watcher_set.bump,
+        constraint = watcher_set.watchers.contains(&watcher.key()) @ BridgeError::Unauthorized
+    )]
+    pub watcher_set: Account<'info, WatcherSet>,
+
+    #[account(
+        mut,
+        seeds = [b"token", mint.as_ref()],
+        bump = token_registry.bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct CloseTokenAccounts<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        close = rent_collector,
+        seeds = [b"token", mint.as_ref()],
+        bump = token_registry.bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the swept dust; owned by `config.fee_collector`
+    #[account(
+        mut,
+        constraint = treasury.mint == mint @ BridgeError::MintMismatch,
+        constraint = treasury.owner == config.fee_collector @ BridgeError::Unauthorized
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = config.rent_collector @ BridgeError::RentRecipientMismatch)]
+    pub rent_collector: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: [u8; 32])]
+pub struct RegisterChain<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ChainRoute::INIT_SPACE,
+        seeds = [b"route", chain_id.as_ref()],
+        bump
+    )]
+    pub chain_route: Account<'info, ChainRoute>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wormhole_chain: u16)]
+pub struct RegisterWormholeEmitter<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + WormholeEmitter::INIT_SPACE,
+        seeds = [b"wormhole_emitter", wormhole_chain.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub wormhole_emitter: Account<'info, WormholeEmitter>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(chain_id: [u8; 32])]
+pub struct SetRouteEnabled<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"route", chain_id.as_ref()],
+        bump = chain_route.bump
+    )]
+    pub chain_route: Account<'info, ChainRoute>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey, target_chain: [u8; 32])]
+pub struct SetDestinationToken<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(seeds = [b"route", target_chain.as_ref()], bump = chain_route.bump)]
+    pub chain_route: Account<'info, ChainRoute>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + DestinationToken::INIT_SPACE,
+        seeds = [b"destination_token", mint.as_ref(), target_chain.as_ref()],
+        bump
+    )]
+    pub destination_token: Account<'info, DestinationToken>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey, target_chain: [u8; 32])]
+pub struct ClearDestinationToken<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"destination_token", mint.as_ref(), target_chain.as_ref()],
+        bump = destination_token.bump,
+        close = rent_receiver
+    )]
+    pub destination_token: Account<'info, DestinationToken>,
+
+    /// CHECK: Refunded the rent of the closed mapping; must be whoever funded it
+    #[account(mut, address = destination_token.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+/// Admin accounts are never `mut` and never double as the rent payer, so a
+/// multisig PDA only has to sign to drive a privileged instruction.
+#[derive(Accounts)]
+pub struct AdminAction<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.pending_admin() == Some(new_admin.key()) @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct QueueEmergencyWithdraw<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// One pending withdrawal per mint; cancel it before queueing another
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EmergencyWithdraw::INIT_SPACE,
+        seeds = [b"emergency_withdraw", mint.as_ref()],
+        bump
+    )]
+    pub emergency_withdraw: Account<'info, EmergencyWithdraw>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ExecuteEmergencyWithdraw<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"emergency_withdraw", mint.as_ref()],
+        bump = emergency_withdraw.bump,
+        close = rent_receiver
+    )]
+    pub emergency_withdraw: Account<'info, EmergencyWithdraw>,
+
+    #[account(mut, seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        mut,
+        address = emergency_withdraw.destination @ BridgeError::WithdrawDestinationMismatch
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Refunded the rent of the closed request; must be whoever funded it
+    #[account(mut, address = emergency_withdraw.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct CancelEmergencyWithdraw<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"emergency_withdraw", mint.as_ref()],
+        bump = emergency_withdraw.bump,
+        close = rent_receiver
+    )]
+    pub emergency_withdraw: Account<'info, EmergencyWithdraw>,
+
+    /// CHECK: Refunded the rent of the closed request; must be whoever funded it
+    #[account(mut, address = emergency_withdraw.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct QueueInsuranceWithdraw<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(seeds = [b"insurance_vault", mint.as_ref()], bump)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// One pending payout per mint; cancel it before queueing another
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + EmergencyWithdraw::INIT_SPACE,
+        seeds = [b"insurance_withdraw", mint.as_ref()],
+        bump
+    )]
+    pub insurance_withdraw: Account<'info, EmergencyWithdraw>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ExecuteInsuranceWithdraw<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_withdraw", mint.as_ref()],
+        bump = insurance_withdraw.bump,
+        close = rent_receiver
+    )]
+    pub insurance_withdraw: Account<'info, EmergencyWithdraw>,
+
+    #[account(mut, seeds = [b"insurance_vault", mint.as_ref()], bump)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        address = insurance_withdraw.destination @ BridgeError::WithdrawDestinationMismatch
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Refunded the rent of the closed request; must be whoever funded it
+    #[account(mut, address = insurance_withdraw.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct CancelInsuranceWithdraw<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_withdraw", mint.as_ref()],
+        bump = insurance_withdraw.bump,
+        close = rent_receiver
+    )]
+    pub insurance_withdraw: Account<'info, EmergencyWithdraw>,
+
+    /// CHECK: Refunded the rent of the closed request; must be whoever funded it
+    #[account(mut, address = insurance_withdraw.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct WithdrawFees<'info> {
+    /// The admin or `config.fee_collector`
+    #[account(
+        constraint = authority.key() == config.fee_collector
+            || authority.key() == bridge_state.load()?.admin
+            @ BridgeError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(mut, seeds = [b"fee_vault", mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, constraint = destination.mint == mint @ BridgeError::MintMismatch)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SyncSurplus<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    #[account(mut, seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RegisterReferrer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub referrer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ReferrerAccount::INIT_SPACE,
+        seeds = [b"referrer", referrer.key().as_ref(), mint.as_ref()],
+        bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+
+    #[account(seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ClaimReferralFees<'info> {
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.key().as_ref(), mint.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, constraint = destination.mint == mint @ BridgeError::MintMismatch)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct BlockAddress<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(address = config.compliance_authority @ BridgeError::NotComplianceAuthority)]
+    pub compliance_authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BlockedAddress::INIT_SPACE,
+        seeds = [b"blocked", address.as_ref()],
+        bump
+    )]
+    pub blocked_address: Account<'info, BlockedAddress>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct UnblockAddress<'info> {
+    #[account(address = config.compliance_authority @ BridgeError::NotComplianceAuthority)]
+    pub compliance_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"blocked", address.as_ref()],
+        bump = blocked_address.bump,
+        close = rent_receiver
+    )]
+    pub blocked_address: Account<'info, BlockedAddress>,
+
+    /// CHECK: Refunded the rent of the closed entry; must be whoever funded it
+    #[account(mut, address = blocked_address.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct RedirectBlockedRelease<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    /// CHECK: Blocklist entry of the attested recipient; must exist
+    #[account(seeds = [b"blocked", attestation.recipient.as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AddFeeExemption<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FeeExemption::INIT_SPACE,
+        seeds = [b"fee_exemption", user.as_ref()],
+        bump
+    )]
+    pub fee_exemption: Account<'info, FeeExemption>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct RemoveFeeExemption<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_exemption", user.as_ref()],
+        bump = fee_exemption.bump,
+        close = rent_receiver
+    )]
+    pub fee_exemption: Account<'info, FeeExemption>,
+
+    /// CHECK: Refunded the rent of the closed exemption; must be whoever funded it
+    #[account(mut, address = fee_exemption.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey, amount: u64, target_chain: [u8; 32])]
+pub struct QuoteFee<'info> {
+    #[account(seeds = [b"token", mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// The sender's exemption, if quoting for an exempt user
+    #[account(seeds = [b"fee_exemption", fee_exemption.user.as_ref()], bump = fee_exemption.bump)]
+    pub fee_exemption: Option<Account<'info, FeeExemption>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, target_chain: [u8; 32])]
+pub struct Lock<'info> {
+    /// Pays for the records this creates; the user itself or a relayer
+    /// sponsoring it
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(constraint = !is_bridge_wrapped(&token_mint) @ BridgeError::UseBurnWrappedInstead)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// Loaded ahead of the vaults, so an unregistered mint fails here before
+    /// anything is transferred
+    #[account(
+        mut,
+        seeds = [b"token", token_mint.key().as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == token_mint.key() @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"vault", token_mint.key().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", token_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault", token_mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Only the user's own exemption derives here; omit when not exempt
+    #[account(seeds = [b"fee_exemption", user.key().as_ref()], bump = fee_exemption.bump)]
+    pub fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// Required only when a referrer is named and a referral share is configured
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.referrer.as_ref(), token_mint.key().as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required only while a per-user rate limit is configured
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserActivity::INIT_SPACE,
+        seeds = [b"user_activity", user.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub user_activity: Option<Account<'info, UserActivity>>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    /// CHECK: Native token mapped for `target_chain`, if any; loaded in the handler
+    #[account(seeds = [b"destination_token", token_mint.key().as_ref(), target_chain.as_ref()], bump)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    /// CHECK: `PendingLock` for the nonce this lock takes, created in the
+    /// handler while refunds are enabled; its address is checked there
+    #[account(mut)]
+    pub pending_lock: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TransferSequence::INIT_SPACE,
+        seeds = [b"lock_sequence", token_mint.key().as_ref(), target_chain.as_ref()],
+        bump
+    )]
+    pub lock_sequence: Account<'info, TransferSequence>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when a memo is written
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, target_chain: [u8; 32])]
+pub struct LockDelegated<'info> {
+    /// Signs and pays in place of the owner
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    /// CHECK: Owner of `user_token_account`; does not sign
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: `owner`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", owner.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: Signs the transfers as the account's delegate; derived from
+    /// owner and operator so one operator cannot spend another's approval
+    #[account(seeds = [b"lock_delegate", owner.key().as_ref(), operator.key().as_ref()], bump)]
+    pub lock_delegate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == owner.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == token_mint.key() @ BridgeError::MintMismatch,
+        constraint = user_token_account.delegate == COption::Some(lock_delegate.key())
+            @ BridgeError::DelegateNotApproved,
+        constraint = user_token_account.delegated_amount >= amount @ BridgeError::DelegatedAmountTooLow
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(constraint = !is_bridge_wrapped(&token_mint) @ BridgeError::UseBurnWrappedInstead)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token", token_mint.key().as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == token_mint.key() @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, seeds = [b"vault", token_mint.key().as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", token_mint.key().as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"insurance_vault", token_mint.key().as_ref()], bump)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// The owner's exemption, not the operator's; omit when not exempt
+    #[account(seeds = [b"fee_exemption", owner.key().as_ref()], bump = fee_exemption.bump)]
+    pub fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// Required only when a referrer is named and a referral share is configured
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.referrer.as_ref(), token_mint.key().as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// The owner's activity, so delegated locks count against the owner's limit
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + UserActivity::INIT_SPACE,
+        seeds = [b"user_activity", owner.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub user_activity: Option<Account<'info, UserActivity>>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    /// CHECK: Native token mapped for `target_chain`, if any; loaded in the handler
+    #[account(seeds = [b"destination_token", token_mint.key().as_ref(), target_chain.as_ref()], bump)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    /// CHECK: `PendingLock` for the nonce this lock takes, created in the
+    /// handler while refunds are enabled; its address is checked there
+    #[account(mut)]
+    pub pending_lock: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + TransferSequence::INIT_SPACE,
+        seeds = [b"lock_sequence", token_mint.key().as_ref(), target_chain.as_ref()],
+        bump
+    )]
+    pub lock_sequence: Account<'info, TransferSequence>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when a memo is written
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(order: LockOrder)]
+pub struct LockWithAuthorization<'info> {
+    /// Submits the signed order and pays for everything
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    /// CHECK: The order's signer; does not sign the transaction
+    #[account(address = order.owner @ BridgeError::OrderNotAuthorized)]
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: `owner`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", owner.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: Signs the transfers as the account's delegate, for any relayer
+    /// carrying an order the owner signed
+    #[account(seeds = [b"order_delegate", order.owner.as_ref()], bump)]
+    pub order_delegate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == order.owner
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == token_mint.key() @ BridgeError::MintMismatch,
+        constraint = user_token_account.delegate == COption::Some(order_delegate.key())
+            @ BridgeError::DelegateNotApproved,
+        constraint = user_token_account.delegated_amount >= order.amount @ BridgeError::DelegatedAmountTooLow
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        address = order.mint @ BridgeError::MintMismatch,
+        constraint = !is_bridge_wrapped(&token_mint) @ BridgeError::UseBurnWrappedInstead
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token", token_mint.key().as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == token_mint.key() @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, seeds = [b"vault", token_mint.key().as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", token_mint.key().as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"insurance_vault", token_mint.key().as_ref()], bump)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Spent marker for the order nonce; a replayed order fails to create it
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + LockOrderRecord::INIT_SPACE,
+        seeds = [b"lock_order", order.owner.as_ref(), order.nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub lock_order: Account<'info, LockOrderRecord>,
+
+    /// The owner's exemption; omit when not exempt
+    #[account(seeds = [b"fee_exemption", order.owner.as_ref()], bump = fee_exemption.bump)]
+    pub fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The owner's activity, so relayed locks count against the owner's limit
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + UserActivity::INIT_SPACE,
+        seeds = [b"user_activity", order.owner.as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub user_activity: Option<Account<'info, UserActivity>>,
+
+    /// CHECK: Route for the order's target chain; loaded in the handler
+    #[account(seeds = [b"route", order.target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    /// CHECK: Native token mapped for the order's target chain, if any; loaded in the handler
+    #[account(seeds = [b"destination_token", token_mint.key().as_ref(), order.target_chain.as_ref()], bump)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    /// CHECK: `PendingLock` for the nonce this lock takes, created in the
+    /// handler while refunds are enabled; its address is checked there
+    #[account(mut)]
+    pub pending_lock: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + TransferSequence::INIT_SPACE,
+        seeds = [b"lock_sequence", token_mint.key().as_ref(), order.target_chain.as_ref()],
+        bump
+    )]
+    pub lock_sequence: Account<'info, TransferSequence>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Instructions sysvar, read for the owner's ed25519 signature
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CancelLock<'info> {
+    #[account(address = pending_lock.source @ BridgeError::Unauthorized)]
+    pub source: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_lock", nonce.to_le_bytes().as_ref()],
+        bump = pending_lock.bump
+    )]
+    pub pending_lock: Account<'info, PendingLock>,
+
+    /// CHECK: Created by `attest_lock`; once it exists the lock cannot be cancelled
+    #[account(
+        seeds = [b"lock_attestation", nonce.to_le_bytes().as_ref()],
+        bump,
+        constraint = lock_attestation.data_is_empty() @ BridgeError::AlreadyAttested
+    )]
+    pub lock_attestation: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct AttestLock<'info> {
+    /// Anyone may submit the guardians' signatures; pays for the marker
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(seeds = [b"pending_lock", nonce.to_le_bytes().as_ref()], bump = pending_lock.bump)]
+    pub pending_lock: Account<'info, PendingLock>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + LockAttestation::INIT_SPACE,
+        seeds = [b"lock_attestation", nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub lock_attestation: Account<'info, LockAttestation>,
+
+    #[account(
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Instructions sysvar, read for the guardians' signatures
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Shared by `refund_expired_lock` and `refund_cancelled_lock`, so both
+/// refuse a lock `attest_lock` has recorded
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct RefundLock<'info> {
+    #[account(address = pending_lock.source @ BridgeError::Unauthorized)]
+    pub source: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_lock", nonce.to_le_bytes().as_ref()],
+        bump = pending_lock.bump
+    )]
+    pub pending_lock: Account<'info, PendingLock>,
+
+    /// CHECK: Created by `attest_lock`; once it exists the lock cannot be refunded
+    #[account(
+        seeds = [b"lock_attestation", nonce.to_le_bytes().as_ref()],
+        bump,
+        constraint = lock_attestation.data_is_empty() @ BridgeError::AlreadyAttested
+    )]
+    pub lock_attestation: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = source_token_account.owner == source.key() @ BridgeError::RecipientMismatch,
+        constraint = source_token_account.mint == pending_lock.mint @ BridgeError::MintMismatch
+    )]
+    pub source_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"vault", pending_lock.mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pending_lock.mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", pending_lock.mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct SettleLock<'info> {
+    /// Anyone may submit the guardians' settlement
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        close = rent_receiver,
+        seeds = [b"pending_lock", nonce.to_le_bytes().as_ref()],
+        bump = pending_lock.bump
+    )]
+    pub pending_lock: Account<'info, PendingLock>,
+
+    /// CHECK: Refunded the rent of the closed record; must be whoever funded it
+    #[account(mut, address = pending_lock.rent_payer @ BridgeError::Unauthorized)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"guardian_set", bridge_state.load()?.guardian_set_index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Instructions sysvar, read for the guardians' signatures
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BatchLock<'info> {
+    /// Must also be the `user` of every entry's accounts
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32])]
+pub struct Release<'info> {
+    /// Whoever delivers the transfer, our relayer or anyone else; pays for
+    /// the records and, when the recipient has none yet, its associated
+    /// token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The recipient the guardians attested; the signed message names
+    /// this key, so it need not sign. This is the only identity the
+    /// instruction checks. Writable for the native drop
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+    
+    /// CHECK: The recipient's associated token account, created in the
+    /// handler if it does not exist yet and otherwise checked there to be
+    /// owned by `user` and to hold `token_mint`; its address already pins both
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &token_mint.key(), &token_program.key())
+            @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: UncheckedAccount<'info>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// Receives the relayer fee; unused when the recipient delivers
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == payer.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The same `[b"vault", mint]` account `lock` pays into
+    #[account(
+        mut,
+        seeds = [b"vault", token_mint.key().as_ref()],
+        bump,
+        constraint = vault.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", token_mint.key().as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", token_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+    
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// CHECK: Attestation PDA, created in the handler only when the amount is queued
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = queued_release.data_is_empty() @ BridgeError::AlreadyQueued
+    )]
+    pub queued_release: UncheckedAccount<'info>,
+    
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+
+    /// Root covering the transfer; only read by `release_with_proof`
+    #[account(seeds = [b"attestation_root", attestation_root.root.as_ref()], bump = attestation_root.bump)]
+    pub attestation_root: Option<Account<'info, AttestationRoot>>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(message: AttestationRootMessage)]
+pub struct PostAttestationRoot<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AttestationRoot::INIT_SPACE,
+        seeds = [b"attestation_root", message.root.as_ref()],
+        bump
+    )]
+    pub attestation_root: Account<'info, AttestationRoot>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32])]
+pub struct ReleaseNative<'info> {
+    /// Funds the records and the one-shot wSOL account, and forwards the
+    /// unwrapped lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Attested recipient; only credited with lamports, so any
+    /// writable non-executable account works
+    #[account(mut, constraint = !recipient.executable @ BridgeError::InvalidRecipient)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: `recipient`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", recipient.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: Created as a wSOL account and closed again in the handler
+    #[account(
+        mut,
+        seeds = [b"native_unwrap", payer.key().as_ref()],
+        bump,
+        constraint = unwrap_account.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub unwrap_account: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"vault", native_mint::ID.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = native_mint::ID @ BridgeError::NotNativeMint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// Receives the relayer fee; unused when the recipient delivers
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == payer.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", native_mint::ID.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", native_mint::ID.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    /// CHECK: Attestation PDA, created in the handler only when the amount is queued
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = queued_release.data_is_empty() @ BridgeError::AlreadyQueued
+    )]
+    pub queued_release: UncheckedAccount<'info>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    #[account(address = token::ID @ BridgeError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32], source_nonce: u64)]
+pub struct ReleaseByNonce<'info> {
+    /// Whoever delivers the transfer; pays for the bitmap page and any
+    /// queued release, and may be `user` itself
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The recipient the guardians attested; the signed message names this key
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ BridgeError::RecipientMismatch,
+        constraint = user_token_account.mint == vault.mint @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"vault", vault.mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vault.mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// Receives the relayer fee; unused when the recipient delivers
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == payer.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"fee_vault", vault.mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", vault.mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, which must be in nonce replay mode;
+    /// advanced under strict ordering
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ReplayBitmap::INIT_SPACE,
+        seeds = [
+            b"replay_bitmap",
+            source_chain.as_ref(),
+            (source_nonce / NONCES_PER_BITMAP_PAGE).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub replay_bitmap: Account<'info, ReplayBitmap>,
+
+    /// CHECK: Attestation PDA, created in the handler only when the amount is queued
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = queued_release.data_is_empty() @ BridgeError::AlreadyQueued
+    )]
+    pub queued_release: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct MintWrapped<'info> {
+    /// Whoever delivers the transfer, our relayer or anyone else; pays for
+    /// the records and, when the recipient has none yet, its associated
+    /// token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The recipient the guardians attested; the signed message names
+    /// this key, so it need not sign. This is the only identity the
+    /// instruction checks. Writable for the native drop
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+    
+    /// CHECK: The recipient's associated token account, created in the
+    /// handler if it does not exist yet and checked there otherwise
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &wrapped_mint.key(), &token_program.key())
+            @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+    
+    /// Must still be its own mint authority, with no freeze authority
+    #[account(
+        mut,
+        address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch,
+        constraint = has_wrapped_authorities(&wrapped_mint) @ BridgeError::InvalidWrappedMintAuthority
+    )]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    /// Receives the relayer fee; unused when the recipient delivers
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == payer.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == wrapped_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", wrapped_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+    
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// CHECK: Attestation PDA, created in the handler only when the amount is queued
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = queued_release.data_is_empty() @ BridgeError::AlreadyQueued
+    )]
+    pub queued_release: UncheckedAccount<'info>,
+    
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(vaa_hash: [u8; 32], source_chain: [u8; 32], source_tx: [u8; 32], source_token: [u8; 32])]
+pub struct MintWrappedFromVaa<'info> {
+    /// Relayer redeeming the VAA; pays for the records and, when the
+    /// recipient has none yet, its associated token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The recipient named in the VAA payload, checked in the handler
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's associated token account, created in the
+    /// handler if it does not exist yet and checked there otherwise
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &wrapped_mint.key(), &token_program.key())
+            @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    /// Must still be its own mint authority, with no freeze authority
+    #[account(
+        mut,
+        address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch,
+        constraint = has_wrapped_authorities(&wrapped_mint) @ BridgeError::InvalidWrappedMintAuthority
+    )]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", wrapped_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    /// CHECK: Posted VAA, parsed in the handler; its address is the core
+    /// bridge's PDA for `vaa_hash`, which only the core bridge can create
+    #[account(
+        owner = WORMHOLE_CORE_BRIDGE_ID @ BridgeError::InvalidVaa,
+        seeds = [b"PostedVAA", vaa_hash.as_ref()],
+        bump,
+        seeds::program = WORMHOLE_CORE_BRIDGE_ID
+    )]
+    pub posted_vaa: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"wormhole_emitter", wormhole_emitter.wormhole_chain.to_le_bytes().as_ref()],
+        bump = wormhole_emitter.bump
+    )]
+    pub wormhole_emitter: Account<'info, WormholeEmitter>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProcessedVaa::INIT_SPACE,
+        seeds = [b"processed_vaa", vaa_hash.as_ref()],
+        bump
+    )]
+    pub processed_vaa: Account<'info, ProcessedVaa>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32])]
+pub struct ReleaseWithCall<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Attested recipient; must be a program implementing `on_bridge_receive`
+    #[account(constraint = recipient_program.executable @ BridgeError::InvalidRecipient)]
+    pub recipient_program: UncheckedAccount<'info>,
+
+    /// CHECK: `recipient_program`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", recipient_program.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient program's escrow authority PDA
+    #[account(seeds = [b"bridge_escrow"], bump, seeds::program = recipient_program.key())]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow.owner == escrow_authority.key() @ BridgeError::InvalidRecipient,
+        constraint = escrow.mint == vault.mint @ BridgeError::MintMismatch
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"vault", vault.mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vault.mint @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"fee_vault", vault.mint.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", vault.mint.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, source_tx: [u8; 32], source_chain: [u8; 32], source_token: [u8; 32])]
+pub struct MintWrappedWithCall<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Attested recipient; must be a program implementing `on_bridge_receive`
+    #[account(constraint = recipient_program.executable @ BridgeError::InvalidRecipient)]
+    pub recipient_program: UncheckedAccount<'info>,
+
+    /// CHECK: `recipient_program`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", recipient_program.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient program's escrow authority PDA
+    #[account(seeds = [b"bridge_escrow"], bump, seeds::program = recipient_program.key())]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow.owner == escrow_authority.key() @ BridgeError::InvalidRecipient,
+        constraint = escrow.mint == wrapped_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    /// Must still be its own mint authority, with no freeze authority
+    #[account(
+        mut,
+        address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch,
+        constraint = has_wrapped_authorities(&wrapped_mint) @ BridgeError::InvalidWrappedMintAuthority
+    )]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", wrapped_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(message: TransferMessage)]
+pub struct PostAttestation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [b"attestation", message.source_chain.as_ref(), message.source_tx.as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Only checked for absence; attesting a delivered transfer is refused
+    #[account(
+        seeds = [b"processed", message.source_chain.as_ref(), message.source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", message.source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct ExecuteRelease<'info> {
+    /// Anyone may execute once an attestation exists; pays for the replay marker
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == attestation.recipient @ BridgeError::RecipientMismatch,
+        constraint = recipient_token_account.mint == attestation.token @ BridgeError::RecipientMismatch
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the relayer fee; unused when the recipient executes
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == executor.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == attestation.token @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The recipient wallet, credited the attested native drop
+    #[account(mut, address = attestation.recipient @ BridgeError::RecipientMismatch)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", attestation.recipient.as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// CHECK: Route of the source chain, read for its relayer allowlist; may not exist
+    #[account(seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", attestation.token.as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = attestation.token @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"fee_vault", attestation.token.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", attestation.token.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct BatchRelease<'info> {
+    /// Pays for the replay markers and any native drops
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    /// Receives the relayer fee of every entry; unused for the executor's own transfers
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == executor.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(mut, seeds = [b"vault", token_mint.key().as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"fee_vault", token_mint.key().as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", token_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct ExecuteMint<'info> {
+    /// Anyone may execute once an attestation exists; pays for the replay marker
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == attestation.recipient @ BridgeError::RecipientMismatch,
+        constraint = recipient_token_account.mint == attestation.token @ BridgeError::RecipientMismatch
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the relayer fee; unused when the recipient executes
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == executor.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == attestation.token @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The recipient wallet, credited the attested native drop
+    #[account(mut, address = attestation.recipient @ BridgeError::RecipientMismatch)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", attestation.recipient.as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// CHECK: Route of the source chain, read for its relayer allowlist; may not exist
+    #[account(seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        seeds = [b"wrapped_asset", source_chain.as_ref(), wrapped_asset.source_token.as_ref()],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(
+        mut,
+        address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch,
+        constraint = wrapped_mint.key() == attestation.token @ BridgeError::InvalidSignaturePayload,
+        constraint = has_wrapped_authorities(&wrapped_mint) @ BridgeError::InvalidWrappedMintAuthority
+    )]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"token", attestation.token.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when the transfer carries a memo
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct OptimisticRelease<'info> {
+    /// Posts the bond and pays for the claim and replay marker
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == relayer.key() @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == token_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"vault", token_mint.key().as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"token", token_mint.key().as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + OptimisticReleaseClaim::INIT_SPACE,
+        seeds = [b"optimistic", source_chain.as_ref(), source_tx.as_ref()],
+        bump
+    )]
+    pub optimistic_release: Account<'info, OptimisticReleaseClaim>,
+
+    /// CHECK: Created as a self-owned token account for the bond in the handler
+    #[account(
+        mut,
+        seeds = [b"optimistic_bond", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = bond_escrow.data_is_empty() @ BridgeError::AlreadyInitialized
+    )]
+    pub bond_escrow: UncheckedAccount<'info>,
+
+    /// CHECK: Attestation PDA; a transfer already queued through the guardians cannot be claimed
+    #[account(
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = queued_release.data_is_empty() @ BridgeError::AlreadyQueued
+    )]
+    pub queued_release: UncheckedAccount<'info>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode; may not exist
+    #[account(seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeOptimisticRelease<'info> {
+    pub challenger: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"optimistic",
+            optimistic_release.source_chain.as_ref(),
+            optimistic_release.source_tx.as_ref()
+        ],
+        bump = optimistic_release.bump,
+        constraint = !optimistic_release.challenged @ BridgeError::OptimisticReleaseChallenged,
+        constraint = !optimistic_release.finalized @ BridgeError::AlreadyProcessed
+    )]
+    pub optimistic_release: Account<'info, OptimisticReleaseClaim>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"optimistic_bond",
+            optimistic_release.source_chain.as_ref(),
+            optimistic_release.source_tx.as_ref()
+        ],
+        bump
+    )]
+    pub bond_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"insurance_vault", optimistic_release.token.as_ref()], bump)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = optimistic_release.token @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Replay marker taken by the claim; closed in the handler
+    #[account(
+        mut,
+        seeds = [
+            b"processed",
+            optimistic_release.source_chain.as_ref(),
+            optimistic_release.source_tx.as_ref()
+        ],
+        bump
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// CHECK: The claiming relayer, refunded the rent of the bond escrow and replay marker
+    #[account(mut, address = optimistic_release.relayer @ BridgeError::RelayerMismatch)]
+    pub relayer: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeOptimisticRelease<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"optimistic",
+            optimistic_release.source_chain.as_ref(),
+            optimistic_release.source_tx.as_ref()
+        ],
+        bump = optimistic_release.bump,
+        constraint = !optimistic_release.challenged @ BridgeError::OptimisticReleaseChallenged,
+        constraint = !optimistic_release.finalized @ BridgeError::AlreadyProcessed
+    )]
+    pub optimistic_release: Account<'info, OptimisticReleaseClaim>,
+
+    /// CHECK: The claimed recipient's blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", optimistic_release.recipient.as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", optimistic_release.source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == optimistic_release.recipient @ BridgeError::RecipientMismatch,
+        constraint = recipient_token_account.mint == optimistic_release.token @ BridgeError::RecipientMismatch
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The claiming relayer, refunded the rent of the bond escrow
+    #[account(mut, address = optimistic_release.relayer @ BridgeError::RelayerMismatch)]
+    pub relayer: UncheckedAccount<'info>,
+
+    /// Gets the bond back
+    #[account(
+        mut,
+        constraint = relayer_token_account.owner == optimistic_release.relayer @ BridgeError::Unauthorized,
+        constraint = relayer_token_account.mint == optimistic_release.token
+            @ BridgeError::MintMismatch
+    )]
+    pub relayer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"optimistic_bond",
+            optimistic_release.source_chain.as_ref(),
+            optimistic_release.source_tx.as_ref()
+        ],
+        bump
+    )]
+    pub bond_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"vault", optimistic_release.token.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = optimistic_release.token @ BridgeError::MintMismatch)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"fee_vault", optimistic_release.token.as_ref()], bump)]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"token", optimistic_release.token.as_ref()], bump = token_registry.bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct CheckProcessed<'info> {
+    /// CHECK: Only inspected for existence
+    #[account(seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()], bump)]
+    pub processed_record: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct CloseProcessedRecord<'info> {
+    /// Permissionless: the rent always goes to `config.rent_collector`
+    pub closer: Signer<'info>,
+
+    #[account(
+        mut,
+        close = rent_collector,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump = processed_record.bump
+    )]
+    pub processed_record: Account<'info, ProcessedRecord>,
+
+    /// CHECK: Receives the reclaimed rent
+    #[account(mut, address = config.rent_collector @ BridgeError::RentRecipientMismatch)]
+    pub rent_collector: UncheckedAccount<'info>,
+
+    /// CHECK: The set that delivered the transfer; may already be closed
+    #[account(
+        seeds = [b"guardian_set", processed_record.guardian_set_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub guardian_set: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its retention floor; may not exist
+    #[account(seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct PruneProcessed<'info> {
+    /// Paid the incentive share of each closed record's rent
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    /// CHECK: Receives the rest of the reclaimed rent
+    #[account(mut, address = config.rent_collector @ BridgeError::RentRecipientMismatch)]
+    pub rent_collector: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct AddWatcher<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + WatcherSet::INIT_SPACE,
+        seeds = [b"watchers"],
+        bump
+    )]
+    pub watcher_set: Account<'info, WatcherSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveWatcher<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"watchers"], bump = watcher_set.bump)]
+    pub watcher_set: Account<'info, WatcherSet>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct AddRelayer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + RelayerRegistry::INIT_SPACE,
+        seeds = [b"relayers"],
+        bump
+    )]
+    pub relayer_registry: Account<'info, RelayerRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveRelayer<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Account<'info, RelayerRegistry>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct VetoRelease<'info> {
+    pub watcher: Signer<'info>,
+
+    #[account(
+        seeds = [b"watchers"],
+        bump = watcher_set.bump,
+        constraint = watcher_set.watchers.contains(&watcher.key()) @ BridgeError::Unauthorized
+    )]
+    pub watcher_set: Account<'info, WatcherSet>,
+
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct ResolveVeto<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"attestation", source_chain.as_ref(), source_tx.as_ref()],
+        bump = attestation.bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        has_one = admin @ BridgeError::Unauthorized,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, target_chain: [u8; 32])]
+pub struct BurnWrapped<'info> {
+    /// Pays for the records this creates; the user itself or a relayer
+    /// sponsoring it
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == wrapped_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Must be the mint a `WrappedAssetRegistry` of this program names, so
+    /// only bridge-created wrapped mints can be burned
+    #[account(mut, address = wrapped_asset.wrapped_mint @ BridgeError::WrappedAssetMismatch)]
+    pub wrapped_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [
+            b"wrapped_asset",
+            wrapped_asset.source_chain.as_ref(),
+            wrapped_asset.source_token.as_ref()
+        ],
+        bump = wrapped_asset.bump
+    )]
+    pub wrapped_asset: Account<'info, WrappedAssetRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"insurance_vault", wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Only the user's own exemption derives here; omit when not exempt
+    #[account(seeds = [b"fee_exemption", user.key().as_ref()], bump = fee_exemption.bump)]
+    pub fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    #[account(
+        mut,
+        seeds = [b"token", wrapped_mint.key().as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == wrapped_mint.key() @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    /// Required only when a referrer is named and a referral share is configured
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer_account.referrer.as_ref(), wrapped_mint.key().as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required only while a per-user rate limit is configured
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserActivity::INIT_SPACE,
+        seeds = [b"user_activity", user.key().as_ref(), wrapped_mint.key().as_ref()],
+        bump
+    )]
+    pub user_activity: Option<Account<'info, UserActivity>>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TransferSequence::INIT_SPACE,
+        seeds = [b"burn_sequence", wrapped_mint.key().as_ref(), target_chain.as_ref()],
+        bump
+    )]
+    pub burn_sequence: Account<'info, TransferSequence>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Memo program; required only when a memo is written
+    #[account(address = MEMO_PROGRAM_ID @ BridgeError::InvalidMemoProgram)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(target_chain: [u8; 32])]
+pub struct LockNft<'info> {
+    /// Pays for the escrow record and, on the mint's first lock, its vault;
+    /// gets both rents back when the NFT is released
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == nft_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        constraint = nft_mint.supply == 1 && nft_mint.decimals == 0 @ BridgeError::NotAnNft,
+        constraint = !is_bridge_wrapped(&nft_mint) @ BridgeError::UseBurnWrappedInstead
+    )]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), nft_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: Account<'info, MetadataAccount>,
+
+    /// CHECK: Self-owned escrow of `nft_mint`, created in the handler on the
+    /// mint's first lock
+    #[account(mut, seeds = [b"nft_vault", nft_mint.key().as_ref()], bump)]
+    pub nft_vault: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + NftEscrow::INIT_SPACE,
+        seeds = [b"nft_escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_escrow: Account<'info, NftEscrow>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32], source_token: [u8; 32])]
+pub struct MintWrappedNft<'info> {
+    /// Whoever delivers the transfer; pays for the mint, its metadata, the
+    /// records and, when the recipient has none yet, its associated token
+    /// account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The recipient the guardians attested; the signed message names
+    /// this key, so it need not sign
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's associated token account, created in the
+    /// handler if it does not exist yet and checked there otherwise
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &wrapped_nft_mint.key(), &token_program.key())
+            @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"wrapped_nft", source_chain.as_ref(), source_token.as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = wrapped_nft_mint,
+        mint::token_program = token_program,
+        constraint = wrapped_nft_mint.supply == 0 @ BridgeError::NftAlreadyMinted
+    )]
+    pub wrapped_nft_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Metadata PDA of `wrapped_nft_mint`, created by the Token
+    /// Metadata program on first delivery
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), wrapped_nft_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + WrappedOrigin::INIT_SPACE,
+        seeds = [b"origin", wrapped_nft_mint.key().as_ref()],
+        bump
+    )]
+    pub origin: Account<'info, WrappedOrigin>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(target_chain: [u8; 32])]
+pub struct BurnWrappedNft<'info> {
+    pub user: Signer<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = user_token_account.mint == wrapped_nft_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Only a mint `mint_wrapped_nft` created derives here from its origin
+    #[account(
+        mut,
+        seeds = [b"wrapped_nft", origin.source_chain.as_ref(), origin.source_token.as_ref()],
+        bump
+    )]
+    pub wrapped_nft_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"origin", wrapped_nft_mint.key().as_ref()], bump = origin.bump)]
+    pub origin: Account<'info, WrappedOrigin>,
+
+    /// CHECK: Route for `target_chain`; loaded in the handler so a missing route is `UnsupportedChain`
+    #[account(seeds = [b"route", target_chain.as_ref()], bump)]
+    pub chain_route: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, BridgeConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain: [u8; 32], source_tx: [u8; 32])]
+pub struct ReleaseNft<'info> {
+    /// Whoever delivers the transfer; pays for the replay record and, when
+    /// the recipient has none yet, its associated token account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The recipient the guardians attested; the signed message names
+    /// this key, so it need not sign
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: `user`'s blocklist entry, if any; loaded in the handler
+    #[account(seeds = [b"blocked", user.key().as_ref()], bump)]
+    pub blocked_address: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's associated token account, created in the
+    /// handler if it does not exist yet and checked there otherwise
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(&user.key(), &nft_mint.key(), &token_program.key())
+            @ BridgeError::RecipientMismatch
+    )]
+    pub user_token_account: UncheckedAccount<'info>,
+
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, seeds = [b"nft_vault", nft_mint.key().as_ref()], bump)]
+    pub nft_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Missing unless the NFT is escrowed right now
+    #[account(
+        mut,
+        close = rent_receiver,
+        seeds = [b"nft_escrow", nft_mint.key().as_ref()],
+        bump = nft_escrow.bump
+    )]
+    pub nft_escrow: Account<'info, NftEscrow>,
+
+    /// CHECK: Whoever paid for the escrow and vault at lock time; receives the rent back
+    #[account(mut, address = nft_escrow.rent_payer @ BridgeError::RentRecipientMismatch)]
+    pub rent_receiver: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    /// The current set, or a rotated-out set still inside its grace period
+    #[account(
+        seeds = [b"guardian_set", guardian_set.index.to_le_bytes().as_ref()],
+        bump = guardian_set.bump,
+        constraint = guardian_set.can_attest(bridge_state.load()?.guardian_set_index, Clock::get()?.slot)
+            @ BridgeError::GuardianSetExpired
+    )]
+    pub guardian_set: Account<'info, GuardianSet>,
+
+    /// CHECK: Instructions sysvar, read to find the signature verifications in this transaction
+    #[account(address = instructions_sysvar::ID @ BridgeError::InvalidInstructionsSysvar)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Route of the source chain, read for its replay mode and
+    /// advanced under strict ordering; may not exist
+    #[account(mut, seeds = [b"route", source_chain.as_ref()], bump)]
+    pub source_route: UncheckedAccount<'info>,
+
+    /// CHECK: Replay marker created in the handler; its existence means delivered
+    #[account(
+        mut,
+        seeds = [b"processed", source_chain.as_ref(), source_tx.as_ref()],
+        bump,
+        constraint = processed_record.data_is_empty() @ BridgeError::AlreadyProcessed
+    )]
+    pub processed_record: UncheckedAccount<'info>,
+
+    /// Registered relayers; only needed when the source route restricts delivery
+    #[account(seeds = [b"relayers"], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct AddLiquidity<'info> {
+    /// Pays for the LP mint and share account on first use; the provider
+    /// itself or someone sponsoring it
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub provider: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == provider.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = provider_token_account.mint == mint @ BridgeError::MintMismatch
+    )]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::TokenNotRegistered)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token", mint.as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == mint @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// LP shares of `mint`, created on the first deposit with the same decimals
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"lp_mint", mint.as_ref()],
+        bump,
+        mint::decimals = token_mint.decimals,
+        mint::authority = lp_mint,
+        mint::token_program = token_program
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = lp_mint,
+        associated_token::authority = provider,
+        associated_token::token_program = token_program
+    )]
+    pub provider_lp_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RemoveLiquidity<'info> {
+    pub provider: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = provider_token_account.owner == provider.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = provider_token_account.mint == mint @ BridgeError::MintMismatch
+    )]
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = mint @ BridgeError::TokenNotRegistered)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token", mint.as_ref()],
+        bump = token_registry.bump,
+        constraint = token_registry.mint == mint @ BridgeError::TokenNotRegistered
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+
+    #[account(mut, seeds = [b"vault", mint.as_ref()], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"lp_mint", mint.as_ref()], bump)]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = provider_lp_account.owner == provider.key()
+            @ BridgeError::TokenAccountOwnerMismatch,
+        constraint = provider_lp_account.mint == lp_mint.key() @ BridgeError::MintMismatch
+    )]
+    pub provider_lp_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"bridge_state"],
+        bump,
+        constraint = bridge_state.load()?.version == CURRENT_STATE_VERSION @ BridgeError::StateVersionMismatch
+    )]
+    pub bridge_state: AccountLoader<'info, BridgeState>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Fixed-layout root account, read without Borsh on every instruction.
+/// Fields are ordered by alignment so the `repr(C)` layout has no padding.
+#[account(zero_copy)]
+pub struct BridgeState {
+    /// Layout version; anything but `CURRENT_STATE_VERSION` must go through `migrate_state`
+    pub version: u8,
+    /// Emergency stop checked first by every user-facing instruction; 0 or 1
+    pub paused: u8,
+    /// 1 when `pending_admin` holds a proposal
+    pub has_pending_admin: u8,
+    pub _reserved: [u8; 1],
+    /// Index of the `GuardianSet` currently authorized to attest transfers
+    pub guardian_set_index: u32,
+    pub nonce: u64,
+    /// Authority required by every instruction that mutates bridge configuration.
+    /// May be a keypair or a PDA such as a Squads vault: a PDA signing through
+    /// `invoke_signed` is marked as a signer in the CPI and passes `Signer` checks.
+    pub admin: Pubkey,
+    /// Proposed successor; becomes `admin` only once it signs `accept_admin`
+    pub pending_admin: Pubkey,
+}
+
+impl BridgeState {
+    pub const SPACE: usize = 8 + std::mem::size_of::<BridgeState>();
+
+    pub fn new(admin: Pubkey, guardian_set_index: u32, nonce: u64) -> BridgeState {
+        BridgeState {
+            version: CURRENT_STATE_VERSION,
+            paused: 0,
+            has_pending_admin: 0,
+            _reserved: [0; 1],
+            guardian_set_index,
+            nonce,
+            admin,
+            pending_admin: Pubkey::default(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    /// Advances the outbound nonce and returns the value the new transfer
+    /// carries. Failing here is the only thing standing between a wrapped
+    /// counter and two transfers sharing a nonce.
+    pub fn next_nonce(&mut self) -> Result<u64> {
+        self.nonce = self.nonce.checked_add(1).ok_or(BridgeError::CounterOverflow)?;
+        Ok(self.nonce)
+    }
+
+    pub fn pending_admin(&self) -> Option<Pubkey> {
+        (self.has_pending_admin != 0).then_some(self.pending_admin)
+    }
+
+    pub fn set_pending_admin(&mut self, pending_admin: Option<Pubkey>) {
+        self.has_pending_admin = pending_admin.is_some() as u8;
+        self.pending_admin = pending_admin.unwrap_or_default();
+    }
+
+    /// Writes discriminator and body into an account already sized to `SPACE`,
+    /// for the handlers that create or rewrite it outside `AccountLoader`.
+    pub fn write(&self, info: &AccountInfo) -> Result<()> {
+        let mut data = info.try_borrow_mut_data()?;
+        data[..8].copy_from_slice(&BridgeState::DISCRIMINATOR);
+        data[8..BridgeState::SPACE].copy_from_slice(bytemuck::bytes_of(self));
+        Ok(())
+    }
+}
+
+/// `BridgeState` as Borsh-serialized at version 2, before it became zero-copy
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct BridgeStateV2 {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub paused: bool,
+    pub guardian_set_index: u32,
+    pub nonce: u64,
+}
+
+impl BridgeStateV2 {
+    pub fn matches(info: &AccountInfo) -> Result<bool> {
+        let data = info.try_borrow_data()?;
+        Ok(data.len() == 8 + BridgeStateV2::INIT_SPACE
+            && data[..8] == BridgeState::DISCRIMINATOR
+            && data[8] == 2)
+    }
+
+    pub fn load(info: &AccountInfo) -> Result<BridgeStateV2> {
+        let data = info.try_borrow_data()?;
+        BridgeStateV2::deserialize(&mut &data[8..])
+            .map_err(|_| error!(BridgeError::StateVersionMismatch))
+    }
+}
+
+/// Existence of this PDA marks a `(source_chain, source_tx)` transfer as delivered
+#[account]
+#[derive(InitSpace)]
+pub struct ProcessedRecord {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// Set whose signatures delivered the transfer; it must be retired before
+    /// the record can be closed, so those signatures cannot be replayed.
+    pub guardian_set_index: u32,
+    pub processed_slot: u64,
+    pub bump: u8,
+}
+
+impl ProcessedRecord {
+    pub fn retention_elapsed(&self, retention_slots: u64, slot: u64) -> bool {
+        slot >= self.processed_slot.saturating_add(retention_slots)
+    }
+
+    /// Why the record must stay, if it must: its retention, the longer of
+    /// the config's and the route's, has not passed, or the set that
+    /// delivered it can still attest. Guardian digests bind the set index,
+    /// so once that set is retired no live set verifies the transfer again.
+    /// Shared by `close_processed_record` and `prune_processed`.
+    pub fn close_blocker(
+        &self,
+        config: &BridgeConfig,
+        route: &AccountInfo,
+        guardian_set: &AccountInfo,
+        current_index: u32,
+        slot: u64,
+    ) -> Result<Option<BridgeError>> {
+        let retention = config.retention_slots.max(ChainRoute::min_retention_of(route)?);
+        if !self.retention_elapsed(retention, slot) {
+            return Ok(Some(BridgeError::ProcessedRecordRetained));
+        }
+        if !guardian_set_retired(guardian_set, current_index, slot)? {
+            return Ok(Some(BridgeError::GuardianSetActive));
+        }
+        Ok(None)
+    }
+}
+
+/// Consumed source nonces for one page of a nonce-mode route
+#[account]
+#[derive(InitSpace)]
+pub struct ReplayBitmap {
+    pub source_chain: [u8; 32],
+    /// Covers nonces `page_index * NONCES_PER_BITMAP_PAGE` onwards
+    pub page_index: u64,
+    pub bits: [u8; REPLAY_BITMAP_BYTES],
+    pub bump: u8,
+}
+
+impl ReplayBitmap {
+    /// Marks `source_nonce` as consumed, rejecting it if it already was.
+    pub fn consume(&mut self, source_nonce: u64) -> Result<()> {
+        let bit = (source_nonce % NONCES_PER_BITMAP_PAGE) as usize;
+        let mask = 1u8 << (bit % 8);
+        require!(self.bits[bit / 8] & mask == 0, BridgeError::AlreadyProcessed);
+        self.bits[bit / 8] |= mask;
+        Ok(())
+    }
+}
+
+/// `BridgeState` as laid out while replay protection was an in-account list.
+/// Version 0 has no version byte; version 1 is the same body behind one.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LegacyBridgeState {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub paused: bool,
+    pub guardian_set_index: u32,
+    pub nonce: u64,
+    #[max_len(MAX_PROCESSED)]
+    pub processed: Vec<[u8; 32]>,
+}
+
+impl LegacyBridgeState {
+    /// Both legacy layouts were allocated at a fixed size, which is how the
+    /// unversioned one is told apart. Returns the layout version alongside.
+    pub fn load(info: &AccountInfo) -> Result<(u8, LegacyBridgeState)> {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == BridgeState::DISCRIMINATOR,
+            BridgeError::StateVersionMismatch
+        );
+        let version = if data.len() == 8 + LegacyBridgeState::INIT_SPACE {
+            0
+        } else if data.len() == 9 + LegacyBridgeState::INIT_SPACE && data[8] == 1 {
+            1
+        } else {
+            return err!(BridgeError::StateVersionMismatch);
+        };
+        let state = LegacyBridgeState::deserialize(&mut &data[Self::body_offset(version)..])
+            .map_err(|_| error!(BridgeError::StateVersionMismatch))?;
+        Ok((version, state))
+    }
+
+    /// Writes the body back in place, keeping the layout it was loaded from.
+    pub fn store(&self, info: &AccountInfo, version: u8) -> Result<()> {
+        let mut data = info.try_borrow_mut_data()?;
+        self.serialize(&mut &mut data[Self::body_offset(version)..])
+            .map_err(|_| error!(BridgeError::StateVersionMismatch))
+    }
+
+    pub fn into_v2(self) -> BridgeStateV2 {
+        BridgeStateV2 {
+            version: 2,
+            admin: self.admin,
+            pending_admin: self.pending_admin,
+            paused: self.paused,
+            guardian_set_index: self.guardian_set_index,
+            nonce: self.nonce,
+        }
+    }
+
+    fn body_offset(version: u8) -> usize {
+        if version == 0 { 8 } else { 9 }
+    }
+}
+
+/// Canonical payload guardians sign to attest an inbound transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransferMessage {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// Local mint being released or minted
+    pub token: Pubkey,
+    /// The source event's `net_amount`, never its gross
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    /// Part of `amount` owed to whoever delivers on the recipient's behalf
+    pub relayer_fee: u64,
+    /// Lamports the delivering relayer funds to the recipient wallet
+    pub native_drop: u64,
+    /// Unix time after which the transfer must not be delivered; 0 for none
+    pub deadline_unix: i64,
+    /// Written through the SPL Memo program ahead of the payout
+    pub memo: Option<String>,
+    /// The source event's tier; always `Standard` for call deliveries
+    pub tier: DeliveryTier,
+    /// Sender on the source chain as a `transfer_id_word`; signed so the
+    /// release can derive its `transfer_id`
+    pub source_addr: [u8; 32],
+}
+
+impl GuardianMessage for TransferMessage {
+    const TAG: &'static [u8] = b"transfer";
+}
+
+impl TransferMessage {
+    pub fn check_deadline(&self) -> Result<()> {
+        check_deadline(self.deadline_unix)
+    }
+
+    /// Relayer fee due to `relayer`; nothing when recipients deliver their own transfer.
+    pub fn relayer_fee_for(&self, relayer: &Pubkey) -> u64 {
+        if *relayer == self.recipient {
+            0
+        } else {
+            self.relayer_fee
+        }
+    }
+
+    /// Lamports `relayer` must drop to the recipient; none on self-delivery.
+    pub fn native_drop_for(&self, relayer: &Pubkey) -> u64 {
+        if *relayer == self.recipient {
+            0
+        } else {
+            self.native_drop
+        }
+    }
+
+    /// `transfer_id` of this inbound transfer; `home_token` is the asset's
+    /// address on its home chain, `token` itself unless it is wrapped.
+    pub fn transfer_id(&self, home_token: &[u8; 32]) -> [u8; 32] {
+        transfer_id(
+            &self.source_chain,
+            &LOCAL_CHAIN_ID,
+            &self.source_addr,
+            self.recipient.as_ref(),
+            home_token,
+            self.amount,
+            self.nonce,
+        )
+    }
+}
+
+/// Canonical id of a transfer, computed the same way by this program,
+/// `contracts/TransferId.sol`, guardians and the backend
+/// (`shared/transfer-id.ts`):
+///
+/// keccak256(source_chain || dest_chain || source_addr || dest_addr ||
+/// token || amount || nonce)
+///
+/// Chains are their 32-byte ids. Addresses and `token`, the asset's address
+/// on its home chain, are each one `transfer_id_word`. `amount`, in the
+/// units the source event carries, and the source `nonce` are big-endian
+/// u64s, as `abi.encodePacked` lays them out.
+pub fn transfer_id(
+    source_chain: &[u8; 32],
+    dest_chain: &[u8; 32],
+    source_addr: &[u8],
+    dest_addr: &[u8],
+    token: &[u8],
+    amount: u64,
+    nonce: u64,
+) -> [u8; 32] {
+    keccak::hashv(&[
+        source_chain,
+        dest_chain,
+        &transfer_id_word(source_addr),
+        &transfer_id_word(dest_addr),
+        &transfer_id_word(token),
+        &amount.to_be_bytes(),
+        &nonce.to_be_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// An address as 32 bytes: left-padded with zeroes when shorter, like an EVM
+/// address in a word, and keccak256 of it when longer.
+pub fn transfer_id_word(addr: &[u8]) -> [u8; 32] {
+    if addr.len() > 32 {
+        return keccak::hash(addr).to_bytes();
+    }
+    let mut word = [0u8; 32];
+    word[32 - addr.len()..].copy_from_slice(addr);
+    word
+}
+
+/// keccak256 over `SIGNING_DOMAIN`, the length-prefixed `tag` naming the
+/// message type, `context` and the borsh `body`.
+fn signing_digest(tag: &[u8], context: &[u8], body: &[u8]) -> [u8; 32] {
+    keccak::hashv(&[SIGNING_DOMAIN, &[tag.len() as u8], tag, context, body]).to_bytes()
+}
+
+/// A message guardians sign. Its digest binds the index of the signing set,
+/// so a signature only ever verifies against that set: a later set sharing
+/// its members cannot be handed the same signatures, which is what lets
+/// replay records be closed once their set is retired.
+pub trait GuardianMessage: AnchorSerialize {
+    /// Separates this message type's digests from every other type's
+    const TAG: &'static [u8];
+
+    /// The exact 32 bytes every guardian of set `guardian_set_index` signs,
+    /// whichever key type it uses.
+    fn digest(&self, guardian_set_index: u32) -> Result<[u8; 32]> {
+        Ok(signing_digest(Self::TAG, &guardian_set_index.to_le_bytes(), &self.try_to_vec()?))
+    }
+}
+
+/// Message guardians sign to attest a whole batch of transfers at once
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AttestationRootMessage {
+    /// Merkle root over the batch's `TransferMessage` digests
+    pub root: [u8; 32],
+    pub leaf_count: u32,
+}
+
+impl GuardianMessage for AttestationRootMessage {
+    const TAG: &'static [u8] = b"attestation_root";
+}
+
+/// Token Metadata fields an NFT keeps across the bridge
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NftMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Vec<NftCreator>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NftCreator {
+    pub address: Pubkey,
+    /// Verified on the chain the NFT was locked on
+    pub verified: bool,
+    pub share: u8,
+}
+
+impl NftMetadata {
+    /// Reads the fields off a Metaplex account, dropping the NUL padding
+    /// Token Metadata stores strings with.
+    pub fn from_metaplex(metadata: &MetadataAccount) -> NftMetadata {
+        let trim = |field: &str| field.trim_end_matches('\0').to_string();
+        NftMetadata {
+            name: trim(&metadata.name),
+            symbol: trim(&metadata.symbol),
+            uri: trim(&metadata.uri),
+            seller_fee_basis_points: metadata.seller_fee_basis_points,
+            creators: metadata
+                .creators
+                .iter()
+                .flatten()
+                .map(|creator| NftCreator {
+                    address: creator.address,
+                    verified: creator.verified,
+                    share: creator.share,
+                })
+                .collect(),
+        }
+    }
+
+    /// keccak256 of the borsh encoding, carried in `NftLocked`.
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        Ok(keccak::hash(&self.try_to_vec()?).to_bytes())
+    }
+
+    /// Token Metadata data for the wrapped NFT. Foreign creators cannot sign
+    /// here, so they are listed unverified.
+    pub fn to_data(&self) -> Result<DataV2> {
+        require!(
+            self.name.len() <= mpl_token_metadata::MAX_NAME_LENGTH
+                && self.symbol.len() <= mpl_token_metadata::MAX_SYMBOL_LENGTH
+                && self.uri.len() <= mpl_token_metadata::MAX_URI_LENGTH
+                && self.creators.len() <= mpl_token_metadata::MAX_CREATOR_LIMIT,
+            BridgeError::MetadataTooLong
+        );
+        let creators = self
+            .creators
+            .iter()
+            .map(|creator| Creator {
+                address: creator.address,
+                verified: false,
+                share: creator.share,
+            })
+            .collect::<Vec<_>>();
+        Ok(DataV2 {
+            name: self.name.clone(),
+            symbol: self.symbol.clone(),
+            uri: self.uri.clone(),
+            seller_fee_basis_points: self.seller_fee_basis_points,
+            creators: (!creators.is_empty()).then_some(creators),
+            collection: None,
+            uses: None,
+        })
+    }
+}
+
+/// Canonical payload guardians sign to return an escrowed NFT
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NftReleaseMessage {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// The escrowed mint
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+}
+
+impl GuardianMessage for NftReleaseMessage {
+    const TAG: &'static [u8] = b"nft_release";
+}
+
+/// Canonical payload guardians sign to attest an inbound NFT
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NftTransferMessage {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// Identifies the NFT on `source_chain`, keying its wrapped mint
+    pub source_token: [u8; 32],
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub metadata: NftMetadata,
+}
+
+impl GuardianMessage for NftTransferMessage {
+    const TAG: &'static [u8] = b"nft_transfer";
+}
+
+/// Canonical message guardians sign for an inbound transfer that ends in a
+/// call into the recipient program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CallMessage {
+    /// `recipient` is the program to call
+    pub transfer: TransferMessage,
+    /// Sender on the source chain, passed on to the callee
+    pub source_addr: [u8; 32],
+    /// keccak256 of the payload handed to the callee
+    pub payload_hash: [u8; 32],
+}
+
+impl GuardianMessage for CallMessage {
+    const TAG: &'static [u8] = b"call";
+}
+
+/// Canonical message guardians sign once a lock has been delivered, so its
+/// `PendingLock` can be settled
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockSettlement {
+    pub nonce: u64,
+    pub token: Pubkey,
+    pub amount: u64,
+}
+
+impl GuardianMessage for LockSettlement {
+    const TAG: &'static [u8] = b"lock_settlement";
+}
+
+/// Canonical message guardians sign to mark a lock attested before
+/// attesting it to the destination
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockAttestationMessage {
+    pub nonce: u64,
+    pub source: Pubkey,
+    pub token: Pubkey,
+    pub amount: u64,
+}
+
+impl GuardianMessage for LockAttestationMessage {
+    const TAG: &'static [u8] = b"lock_attestation";
+}
+
+/// A lock the owner signs off-chain for `lock_with_authorization`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockOrder {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub target_chain: [u8; 32],
+    pub target_addr: Vec<u8>,
+    /// Chosen by the owner; each value can be used once
+    pub nonce: u64,
+    /// Last slot at which the order may be submitted
+    pub expiry_slot: u64,
+}
+
+impl LockOrder {
+    /// What the owner signs; domain-separated like guardian digests, with
+    /// no set index since no guardian set is involved.
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        Ok(signing_digest(b"lock_order", &[], &self.try_to_vec()?))
+    }
+}
+
+/// The optional parts of a `lock` and its variants. `Default` is a plain
+/// lock: no relayer fee, native drop, referrer, beneficiary, deadline or
+/// memos, at `DeliveryTier::Standard`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct LockOptions {
+    /// Part of the amount a relayer earns by delivering it
+    pub relayer_fee: u64,
+    /// Lamports requested for the recipient wallet on delivery
+    pub native_drop: u64,
+    pub referrer: Option<Pubkey>,
+    /// Beneficiary when the signer locks for someone else; `source` remains
+    /// the signer, who any refund goes back to
+    pub on_behalf_of: Option<Pubkey>,
+    /// When non-zero, the Unix time the destination stops delivering
+    pub deadline_unix: i64,
+    /// At most `MAX_MEMO_LEN` bytes, written through the SPL Memo program on
+    /// delivery, for exchanges that credit deposits by memo
+    pub delivery_memo: Option<String>,
+    /// The sender's own reference, capped by `max_memo_len` and written
+    /// through the memo program at lock time, for source-side accounting
+    pub memo: Option<String>,
+    /// `Priority` charges `priority_fee_bps` on top of the fee
+    pub tier: DeliveryTier,
+}
+
+/// One token in a `batch_lock`; referrals, relayer fees and native drops
+/// are not offered for batches
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockEntry {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// One attested transfer in a `batch_release`, checked against its attestation
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchReleaseEntry {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub amount: u64,
+    pub recipient: Pubkey,
+}
+
+/// Arguments of a recipient program's `on_bridge_receive`, borsh-encoded
+/// after its Anchor discriminator
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BridgeReceiveArgs {
+    pub payload: Vec<u8>,
+    /// What reached the escrow, in the escrow mint's units
+    pub amount: u64,
+    pub source_chain: [u8; 32],
+    pub source_addr: [u8; 32],
+}
+
+/// Canonical message guardians sign for an outbound `lock_with_payload`, so
+/// the destination can check the payload it is handed against `payload_hash`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PayloadMessage {
+    pub token: Pubkey,
+    /// The `Locked` event's `net_amount`
+    pub amount: u64,
+    pub sender: Pubkey,
+    pub target_chain: [u8; 32],
+    pub target_addr: Vec<u8>,
+    pub nonce: u64,
+    pub relayer_fee: u64,
+    /// keccak256 of the payload bytes
+    pub payload_hash: [u8; 32],
+    pub consistency_level: ConsistencyLevel,
+}
+
+impl PayloadMessage {
+    /// What guardians sign for the destination, which checks it against its
+    /// own copy of the set; domain-separated like every other digest.
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        Ok(signing_digest(b"payload", &[], &self.try_to_vec()?))
+    }
+}
+
+/// How urgently relayers should deliver a transfer. `Priority` pays
+/// `BridgeConfig::priority_fee_bps` extra at the source; relayers order
+/// their submissions by it, the program only carries it through.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum DeliveryTier {
+    #[default]
+    Standard,
+    Priority,
+}
+
+/// Solana commitment guardians wait for before attesting a payload lock
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConsistencyLevel {
+    Confirmed,
+    Finalized,
+}
+
+/// An NFT held in its `nft_vault` by `lock_nft`, keyed by the mint; closed
+/// by `release_nft`
+#[account]
+#[derive(InitSpace)]
+pub struct NftEscrow {
+    pub mint: Pubkey,
+    /// Who locked it
+    pub source: Pubkey,
+    /// Nonce of the `NftLocked` event
+    pub nonce: u64,
+    pub locked_slot: u64,
+    /// Paid for this record and the vault; both rents go back here
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+/// Counter of one outbound stream: locks of a mint at
+/// `[b"lock_sequence", mint, target_chain]`, burns of a wrapped mint at
+/// `[b"burn_sequence", wrapped_mint, target_chain]`. Unlike the shared
+/// `nonce`, it moves only for its own stream, so the destination can tell a
+/// gap in the stream from transfers of other tokens or directions.
+#[account]
+#[derive(InitSpace)]
+pub struct TransferSequence {
+    pub mint: Pubkey,
+    pub target_chain: [u8; 32],
+    /// Last sequence handed out
+    pub sequence: u64,
+    pub bump: u8,
+}
+
+impl TransferSequence {
+    /// Next sequence of the stream. A counter created by this transfer is
+    /// first seeded with `nonce`, the last shared nonce handed out, so a
+    /// stream continues above every nonce issued before it existed; the seed
+    /// is emitted so the destination knows where the stream starts.
+    pub fn advance(
+        &mut self,
+        mint: Pubkey,
+        target_chain: [u8; 32],
+        bump: u8,
+        nonce: u64,
+        clock: &Clock,
+    ) -> Result<u64> {
+        if self.mint == Pubkey::default() {
+            self.mint = mint;
+            self.target_chain = target_chain;
+            self.sequence = nonce;
+            self.bump = bump;
+            emit!(TransferSequenceStarted {
+                mint,
+                target_chain,
+                seed: nonce,
+                slot: clock.slot,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+        self.sequence = self.sequence.checked_add(1).ok_or(BridgeError::CounterOverflow)?;
+        Ok(self.sequence)
+    }
+}
+
+/// A lock that can still be refunded, keyed by its nonce. Closed by
+/// `settle_lock` once delivered; kept, marked refunded, after a refund.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingLock {
+    pub nonce: u64,
+    /// Signer or owner of the lock; the only one who can be refunded
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    /// What the vault received, and what a refund returns
+    pub amount: u64,
+    pub rent_payer: Pubkey,
+    pub refundable_after_slot: u64,
+    /// The lock's `deadline_unix`; once well past, refundable before `refundable_after_slot`
+    pub deadline_unix: i64,
+    pub refunded: bool,
+    /// Set by `cancel_lock`; refundable `LOCK_CANCEL_DELAY_SLOTS` after `cancelled_slot`
+    pub cancelled: bool,
+    pub cancelled_slot: u64,
+    pub bump: u8,
+}
+
+/// Guardians are attesting lock `nonce`; blocks `cancel_lock` and refunds
+#[account]
+#[derive(InitSpace)]
+pub struct LockAttestation {
+    pub nonce: u64,
+    pub guardian_set_index: u32,
+    pub attested_slot: u64,
+    pub bump: u8,
+}
+
+/// Marks a `LockOrder` nonce as spent, keyed by `(owner, nonce)`
+#[account]
+#[derive(InitSpace)]
+pub struct LockOrderRecord {
+    pub owner: Pubkey,
+    pub nonce: u64,
+    pub used_slot: u64,
+    pub bump: u8,
+}
+
+/// Guardian-verified transfer awaiting execution, keyed by `(source_chain, source_tx)`
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub token: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub relayer_fee: u64,
+    pub native_drop: u64,
+    /// `TransferMessage::deadline_unix`, checked again at execution
+    pub deadline_unix: i64,
+    #[max_len(MAX_MEMO_LEN)]
+    pub memo: Option<String>,
+    pub tier: DeliveryTier,
+    pub source_addr: [u8; 32],
+    pub guardian_set_index: u32,
+    pub posted_slot: u64,
+    /// First slot at which `execute_release` / `execute_mint` may run
+    pub executable_after_slot: u64,
+    /// Set once the transfer or mint has been performed
+    pub executed: bool,
+    /// Blocked by a watcher until the admin resolves the veto
+    pub held: bool,
+    /// Permanently blocked by the admin after a veto
+    pub cancelled: bool,
+    pub vetoed_by: Option<Pubkey>,
+    pub bump: u8,
+}
+
+/// Guardian-signed Merkle root from `post_attestation_root`, keyed by the root
+#[account]
+#[derive(InitSpace)]
+pub struct AttestationRoot {
+    pub root: [u8; 32],
+    pub leaf_count: u32,
+    pub guardian_set_index: u32,
+    pub posted_slot: u64,
+    pub bump: u8,
+}
+
+/// Relayer-bonded release claim from `optimistic_release`, keyed by
+/// `(source_chain, source_tx)`. Kept after it resolves so the same
+/// transfer cannot be claimed twice.
+#[account]
+#[derive(InitSpace)]
+pub struct OptimisticReleaseClaim {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub token: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    /// Source nonce and sender, claimed so the release has its `transfer_id`
+    pub nonce: u64,
+    pub source_addr: [u8; 32],
+    pub relayer: Pubkey,
+    pub bond: u64,
+    pub posted_slot: u64,
+    /// First slot at which `finalize_optimistic_release` may run; challenges must come before it
+    pub finalizable_after_slot: u64,
+    pub finalized: bool,
+    pub challenged: bool,
+    pub bump: u8,
+}
+
+impl OptimisticReleaseClaim {
+    /// An attested transfer disproves the claim unless it pays the same
+    /// token and amount to the same recipient, from the same nonce and sender.
+    pub fn conflicts_with(&self, message: &TransferMessage) -> bool {
+        message.token != self.token
+            || message.amount != self.amount
+            || message.recipient != self.recipient
+            || message.nonce != self.nonce
+            || message.source_addr != self.source_addr
+    }
+
+    pub fn transfer_id(&self) -> [u8; 32] {
+        transfer_id(
+            &self.source_chain,
+            &LOCAL_CHAIN_ID,
+            &self.source_addr,
+            self.recipient.as_ref(),
+            self.token.as_ref(),
+            self.amount,
+            self.nonce,
+        )
+    }
+}
+
+impl Attestation {
+    /// `TransferMessage::transfer_id` of the attested message.
+    pub fn transfer_id(&self, home_token: &[u8; 32]) -> [u8; 32] {
+        transfer_id(
+            &self.source_chain,
+            &LOCAL_CHAIN_ID,
+            &self.source_addr,
+            self.recipient.as_ref(),
+            home_token,
+            self.amount,
+            self.nonce,
+        )
+    }
+
+    /// Relayer fee due to `executor`; nothing when recipients deliver their own transfer.
+    pub fn relayer_fee_for(&self, executor: &Pubkey) -> u64 {
+        if *executor == self.recipient {
+            0
+        } else {
+            self.relayer_fee
+        }
+    }
+
+    /// Lamports `executor` must drop to the recipient; none on self-delivery.
+    pub fn native_drop_for(&self, executor: &Pubkey) -> u64 {
+        if *executor == self.recipient {
+            0
+        } else {
+            self.native_drop
+        }
+    }
+
+    /// Watchers get until `executable_after_slot` to raise a veto.
+    pub fn check_executable(&self, slot: u64) -> Result<()> {
+        require!(!self.cancelled, BridgeError::ReleaseCancelled);
+        require!(!self.held, BridgeError::ReleaseVetoed);
+        require!(
+            slot >= self.executable_after_slot,
+            BridgeError::ReleaseDelayActive
+        );
+        Ok(())
+    }
+}
+
+/// Relayers allowed to deliver from routes with `restricted_delivery` set
+#[account]
+#[derive(InitSpace)]
+pub struct RelayerRegistry {
+    #[max_len(MAX_RELAYERS)]
+    pub relayers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl RelayerRegistry {
+    /// Restricted routes only take deliveries from a registered `payer`.
+    pub fn check_delivery(
+        registry: Option<&Account<RelayerRegistry>>,
+        source_route: &AccountInfo,
+        payer: &Pubkey,
+    ) -> Result<()> {
+        if !ChainRoute::restricted_delivery_of(source_route)? {
+            return Ok(());
+        }
+        require!(
+            registry.is_some_and(|registry| registry.relayers.contains(payer)),
+            BridgeError::RelayerNotAuthorized
+        );
+        Ok(())
+    }
+}
+
+/// Keys allowed to put a pending release on hold
+#[account]
+#[derive(InitSpace)]
+pub struct WatcherSet {
+    #[max_len(MAX_WATCHERS)]
+    pub watchers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// A guardian identity, verified through the matching native signature program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum GuardianKey {
+    Ed25519(Pubkey),
+    /// Ethereum address derived from a secp256k1 public key
+    Secp256k1([u8; 20]),
+}
+
+/// Keys whose signatures attest inbound transfers, with the quorum required
+#[account]
+#[derive(InitSpace)]
+pub struct GuardianSet {
+    pub index: u32,
+    #[max_len(MAX_GUARDIANS)]
+    pub guardians: Vec<GuardianKey>,
+    /// Distinct guardian signatures needed to accept an attestation
+    pub threshold: u8,
+    /// Last slot a rotated-out set may attest; 0 while the set is current
+    pub expires_at_slot: u64,
+    pub bump: u8,
+}
+
+impl GuardianSet {
+    pub fn can_attest(&self, current_index: u32, slot: u64) -> bool {
+        self.index == current_index || slot <= self.expires_at_slot
+    }
+}
+
+/// Bonding rules for guardians, set once by `initialize_staking`
+#[account]
+#[derive(InitSpace)]
+pub struct StakingConfig {
+    pub stake_mint: Pubkey,
+    /// Token account that receives slashed stake
+    pub treasury: Pubkey,
+    /// Stake a guardian must hold to be added to a guardian set
+    pub min_stake: u64,
+    /// Slots between `request_unstake` and `unstake`, leaving time to slash
+    pub unstake_cooldown_slots: u64,
+    pub vault_bump: u8,
+    pub bump: u8,
+}
+
+/// Tokens bonded by a staker on behalf of one guardian key
+#[account]
+#[derive(InitSpace)]
+pub struct GuardianStake {
+    pub staker: Pubkey,
+    pub guardian: GuardianKey,
+    pub amount: u64,
+    /// Slot `request_unstake` was called; 0 when no exit is pending
+    pub unstake_requested_slot: u64,
+    pub slashed: bool,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl GuardianStake {
+    pub fn is_bonded(&self, min_stake: u64) -> bool {
+        !self.slashed && self.unstake_requested_slot == 0 && self.amount >= min_stake
+    }
+}
+
+/// Tunable bridge parameters, changed only through `update_config`
+#[account]
+#[derive(InitSpace)]
+pub struct BridgeConfig {
+    /// Protocol fee charged on outbound transfers
+    pub fee_bps: u16,
+    /// Smallest accepted transfer; 0 disables the bound
+    pub min_amount: u64,
+    /// Largest accepted transfer; 0 disables the bound
+    pub max_amount: u64,
+    /// Slots a release waits before it can be executed
+    pub release_delay: u64,
+    pub max_target_addr_len: u8,
+    /// Releases strictly above this amount are queued; 0 disables queueing
+    pub large_release_threshold: u64,
+    /// Minimum slots a queued large release waits before execution
+    pub large_release_delay_slots: u64,
+    /// Timelock on `execute_emergency_withdraw`, never below the 7-day floor
+    pub emergency_withdraw_delay_slots: u64,
+    /// Minimum age of a `ProcessedRecord` before `close_processed_record`;
+    /// should cover the deepest reorg any source chain can still undergo
+    pub retention_slots: u64,
+    /// Receives the rent of closed `ProcessedRecord`s
+    pub rent_collector: Pubkey,
+    /// Share of reclaimed rent paid to whoever runs `prune_processed`
+    pub prune_incentive_bps: u16,
+    /// May call `withdraw_fees` alongside the admin
+    pub fee_collector: Pubkey,
+    /// Largest native drop a transfer may request, in lamports; 0 disables drops
+    pub max_native_drop: u64,
+    /// Share of the protocol fee credited to a named referrer
+    pub referral_share_bps: u16,
+    /// Share of each protocol fee kept in the fee vault; sums to 10_000 with the insurance share
+    pub treasury_share_bps: u16,
+    /// Share of each protocol fee routed to the insurance vault
+    pub insurance_share_bps: u16,
+    /// Locks one user may make per token and epoch; 0 disables the bound
+    pub max_locks_per_epoch: u32,
+    /// Gross volume one user may lock per token and epoch; 0 disables the bound
+    pub max_volume_per_epoch: u64,
+    /// Largest `lock_with_payload` payload in bytes; 0 disables payload transfers
+    pub max_payload_len: u16,
+    /// Slots after which an unattested lock can be refunded to its source;
+    /// 0 disables refunds and the `PendingLock` records behind them
+    pub refund_after_slots: u64,
+    /// Longest sender memo `lock` and `burn_wrapped` accept; 0 disables them
+    pub max_memo_len: u16,
+    /// Challenge window of `optimistic_release`, in slots; 0 disables the path
+    pub optimistic_window_slots: u64,
+    /// Bond an optimistic release locks, as a share of its amount; at least
+    /// 100% so an unchallenged theft never pays
+    pub optimistic_bond_bps: u16,
+    /// Surcharge on `DeliveryTier::Priority` locks and burns, on top of the
+    /// protocol fee and split with it
+    pub priority_fee_bps: u16,
+    /// Adds and removes `BlockedAddress` entries; appointed by the admin
+    pub compliance_authority: Pubkey,
+    pub bump: u8,
+}
+
+impl BridgeConfig {
+    /// Splits a protocol fee into its treasury and insurance legs. The
+    /// insurance leg rounds down and the treasury takes the remainder, so the
+    /// legs always sum to `fee`.
+    pub fn split_fee(&self, fee: u64) -> (u64, u64) {
+        let insurance =
+            (fee as u128 * self.insurance_share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        (fee - insurance, insurance)
+    }
+
+    pub fn is_large_release(&self, amount: u64) -> bool {
+        self.large_release_threshold != 0 && amount > self.large_release_threshold
+    }
+
+    /// Slots between attestation and execution for a release of `amount`.
+    pub fn execution_delay(&self, amount: u64) -> u64 {
+        if self.is_large_release(amount) {
+            self.release_delay.max(self.large_release_delay_slots)
+        } else {
+            self.release_delay
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.fee_bps <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        require!(self.prune_incentive_bps <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        require!(self.referral_share_bps <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        require!(
+            self.treasury_share_bps as u32 + self.insurance_share_bps as u32
+                == BPS_DENOMINATOR as u32,
+            BridgeError::InvalidConfig
+        );
+        require!(
+            self.max_amount == 0 || self.min_amount <= self.max_amount,
+            BridgeError::InvalidConfig
+        );
+        require!(
+            self.max_target_addr_len > 0 && self.max_target_addr_len <= MAX_TARGET_ADDR_LEN,
+            BridgeError::InvalidConfig
+        );
+        require!(
+            self.emergency_withdraw_delay_slots >= MIN_EMERGENCY_WITHDRAW_DELAY_SLOTS,
+            BridgeError::InvalidConfig
+        );
+        require!(self.max_payload_len <= MAX_PAYLOAD_LEN, BridgeError::InvalidConfig);
+        require!(
+            self.refund_after_slots == 0 || self.refund_after_slots >= MIN_REFUND_AFTER_SLOTS,
+            BridgeError::InvalidConfig
+        );
+        require!(self.max_memo_len <= MAX_LOCK_MEMO_LEN, BridgeError::InvalidConfig);
+        require!(
+            self.optimistic_window_slots == 0
+                || self.optimistic_window_slots >= MIN_OPTIMISTIC_WINDOW_SLOTS,
+            BridgeError::InvalidConfig
+        );
+        require!(self.optimistic_bond_bps >= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        require!(self.priority_fee_bps <= BPS_DENOMINATOR, BridgeError::InvalidConfig);
+        Ok(())
+    }
+
+    pub fn check_amount(&self, amount: u64) -> Result<()> {
+        require!(
+            self.min_amount == 0 || amount >= self.min_amount,
+            BridgeError::AmountBelowMinimum
+        );
+        require!(
+            self.max_amount == 0 || amount <= self.max_amount,
+            BridgeError::AmountAboveMaximum
+        );
+        Ok(())
+    }
+
+    /// A sender memo must be non-empty and within `max_memo_len`.
+    pub fn check_memo(&self, memo: &Option<String>) -> Result<()> {
+        if let Some(memo) = memo {
+            require!(
+                !memo.is_empty() && memo.len() <= self.max_memo_len as usize,
+                BridgeError::InvalidMemo
+            );
+        }
+        Ok(())
+    }
+
+    /// Bond `optimistic_release` takes for `amount`, rounded up.
+    pub fn optimistic_bond(&self, amount: u64) -> Result<u64> {
+        let bond = (amount as u128 * self.optimistic_bond_bps as u128)
+            .div_ceil(BPS_DENOMINATOR as u128);
+        u64::try_from(bond).map_err(|_| error!(BridgeError::InvalidAmount))
+    }
+
+    /// Extra fee a lock or burn of `amount` pays for `tier`, charged
+    /// regardless of any `FeeExemption`.
+    pub fn priority_fee(&self, tier: DeliveryTier, amount: u64) -> u64 {
+        match tier {
+            DeliveryTier::Standard => 0,
+            DeliveryTier::Priority => protocol_fee(amount, self.priority_fee_bps),
+        }
+    }
+
+    pub fn rate_limited(&self) -> bool {
+        self.max_locks_per_epoch != 0 || self.max_volume_per_epoch != 0
+    }
+}
+
+/// Partial update for `update_config`; `None` fields are left unchanged
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ConfigUpdate {
+    pub fee_bps: Option<u16>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    pub release_delay: Option<u64>,
+    pub max_target_addr_len: Option<u8>,
+    pub large_release_threshold: Option<u64>,
+    pub large_release_delay_slots: Option<u64>,
+    pub emergency_withdraw_delay_slots: Option<u64>,
+    pub retention_slots: Option<u64>,
+    pub rent_collector: Option<Pubkey>,
+    pub prune_incentive_bps: Option<u16>,
+    pub fee_collector: Option<Pubkey>,
+    pub max_native_drop: Option<u64>,
+    pub referral_share_bps: Option<u16>,
+    pub treasury_share_bps: Option<u16>,
+    pub insurance_share_bps: Option<u16>,
+    pub max_locks_per_epoch: Option<u32>,
+    pub max_volume_per_epoch: Option<u64>,
+    pub max_payload_len: Option<u16>,
+    pub refund_after_slots: Option<u64>,
+    pub max_memo_len: Option<u16>,
+    pub optimistic_window_slots: Option<u64>,
+    pub optimistic_bond_bps: Option<u16>,
+    pub priority_fee_bps: Option<u16>,
+    pub compliance_authority: Option<Pubkey>,
+}
+
+/// Per-mint bridge settings, shared by native tokens and bridge-wrapped mints
+#[account]
+#[derive(InitSpace)]
+pub struct TokenRegistry {
+    pub mint: Pubkey,
+    /// Halts this token only, independently of `BridgeState::paused`
+    pub paused: bool,
+    /// Most that may leave through releases and mints within the window; 0 disables
+    pub circuit_breaker_limit: u64,
+    /// Width of one outflow bucket; the window spans `CIRCUIT_BREAKER_BUCKETS` of them
+    pub circuit_breaker_bucket_slots: u64,
+    /// Set by `trip_circuit_breaker` once the limit was hit; only
+    /// `reset_circuit_breaker` clears it, and it is kept apart from `paused`
+    pub circuit_breaker_tripped: bool,
+    pub outflow_buckets: [OutflowBucket; CIRCUIT_BREAKER_BUCKETS],
+    pub bump: u8,
+    /// Replaces `BridgeConfig::fee_bps` for this token. Kept last so older
+    /// registries only need zero-extending by `migrate_token_registry`.
+    pub fee_bps_override: Option<u16>,
+    /// Fee on releases out of the vault while utilization is at or below the kink
+    pub outflow_fee_bps: u16,
+    /// Share of the vault a single release may take before the surcharge starts
+    pub utilization_kink_bps: u16,
+    /// Added on top of `outflow_fee_bps` at 100% utilization, linearly from the kink
+    pub utilization_surcharge_bps: u16,
+    /// Part of the fee vault owed to referrers and not withdrawable as fees
+    pub referral_liability: u64,
+    /// Smallest gross transfer of this token; 0 disables the bound
+    pub min_amount: u64,
+    /// Largest gross transfer of this token; 0 disables the bound
+    pub max_amount: u64,
+    /// Most that may be released or minted over any rolling day; 0 disables
+    pub daily_limit: u64,
+    pub daily_outflow: [OutflowBucket; DAILY_LIMIT_BUCKETS],
+    /// No new locks; unlike `paused`, releases and mints keep working
+    pub delisted: bool,
+    /// Registered despite the mint having a freeze authority, accepting that
+    /// the issuer can freeze the vault
+    pub allow_freezable: bool,
+    /// `ALLOW_*` flags for restrictive Token-2022 extensions accepted at registration
+    pub allowed_extensions: u8,
+    /// Locked into the vault and not yet released. Starts at 0 for registries
+    /// migrated from before it was tracked.
+    pub total_locked: u64,
+    /// Liquidity providers' claim on the vault: deposits plus retained fees,
+    /// less withdrawals. Tracked here rather than read off the vault balance,
+    /// so a donation to the vault cannot move the share price.
+    pub lp_assets: u64,
+    /// Share of each release's outflow fee kept in the vault for liquidity
+    /// providers instead of going to the fee vault
+    pub lp_fee_share_bps: u16,
+    /// Part of `lp_assets` paid out by releases beyond `total_locked` and not
+    /// yet made good by later locks; still owed to LPs, but not in the vault
+    pub lp_drawn: u64,
+}
+
+/// Return data of `quote_fee`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FeeQuote {
+    pub gross: u64,
+    pub fee: u64,
+    /// What reaches the vault and what the destination releases
+    pub net: u64,
+    pub fee_bps_effective: u16,
+}
+
+/// Return data of a release rejected with `DailyLimitExceeded`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DailyCapacity {
+    /// What could still be released right now
+    pub remaining: u64,
+    /// Slot at which the oldest recorded outflow leaves the window
+    pub frees_at_slot: u64,
+}
+
+/// Outflow recorded during one `circuit_breaker_bucket_slots`-wide epoch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct OutflowBucket {
+    pub epoch: u64,
+    pub volume: u64,
+}
+
+impl OutflowBucket {
+    /// Buckets of a ring still inside the window ending at `epoch`.
+    fn window(buckets: &[OutflowBucket], epoch: u64) -> impl Iterator<Item = &OutflowBucket> {
+        let oldest = epoch.saturating_sub(buckets.len() as u64 - 1);
+        buckets
+            .iter()
+            .filter(move |bucket| bucket.epoch >= oldest && bucket.epoch <= epoch)
+    }
+
+    /// Adds `amount` to the ring slot of `epoch`, recycling it if stale.
+    fn add(buckets: &mut [OutflowBucket], epoch: u64, amount: u64) {
+        let len = buckets.len() as u64;
+        let bucket = &mut buckets[(epoch % len) as usize];
+        if bucket.epoch != epoch {
+            *bucket = OutflowBucket { epoch, volume: 0 };
+        }
+        bucket.volume = bucket.volume.saturating_add(amount);
+    }
+}
+
+impl TokenRegistry {
+    /// Unpaused, listed, and with every fee, limit and breaker off.
+    pub fn initialize(&mut self, mint: Pubkey, bump: u8) {
+        self.mint = mint;
+        self.paused = false;
+        self.circuit_breaker_limit = 0;
+        self.circuit_breaker_bucket_slots = 0;
+        self.circuit_breaker_tripped = false;
+        self.outflow_buckets = [OutflowBucket::default(); CIRCUIT_BREAKER_BUCKETS];
+        self.bump = bump;
+        self.fee_bps_override = None;
+        self.outflow_fee_bps = 0;
+        self.utilization_kink_bps = 0;
+        self.utilization_surcharge_bps = 0;
+        self.referral_liability = 0;
+        self.min_amount = 0;
+        self.max_amount = 0;
+        self.daily_limit = 0;
+        self.daily_outflow = [OutflowBucket::default(); DAILY_LIMIT_BUCKETS];
+        self.delisted = false;
+        self.allow_freezable = false;
+        self.allowed_extensions = 0;
+        self.total_locked = 0;
+        self.lp_assets = 0;
+        self.lp_drawn = 0;
+        self.lp_fee_share_bps = 0;
+    }
+
+    /// Whether a seed-checked registry address holds an initialized registry.
+    pub fn is_registered(info: &AccountInfo) -> bool {
+        info.owner == &crate::ID && !info.data_is_empty()
+    }
+
+    /// Fee charged on outbound transfers of this token.
+    pub fn fee_bps(&self, config: &BridgeConfig) -> u16 {
+        self.fee_bps_override.unwrap_or(config.fee_bps)
+    }
+
+    /// The protocol fee `lock` and `burn_wrapped` charge on `amount`, and
+    /// exactly what `quote_fee` returns.
+    pub fn quote_fee(&self, config: &BridgeConfig, amount: u64, exempt: bool) -> Result<FeeQuote> {
+        let fee_bps_effective = if exempt { 0 } else { self.fee_bps(config) };
+        let fee = protocol_fee(amount, fee_bps_effective);
+        let net = amount - fee;
+        require!(net > 0, BridgeError::InvalidAmount);
+        Ok(FeeQuote {
+            gross: amount,
+            fee,
+            net,
+            fee_bps_effective,
+        })
+    }
+
+    /// Per-token bounds, checked on the gross amount alongside the global ones.
+    pub fn check_amount(&self, amount: u64) -> Result<()> {
+        require!(
+            self.min_amount == 0 || amount >= self.min_amount,
+            BridgeError::AmountBelowMinimum
+        );
+        require!(
+            self.max_amount == 0 || amount <= self.max_amount,
+            BridgeError::AmountAboveMaximum
+        );
+        Ok(())
+    }
+
+    /// Credits liquidity providers with their share of a release's outflow
+    /// `fee`, which stays in the vault, and returns what goes to the fee
+    /// vault. Nothing is retained while the pool is empty.
+    pub fn retain_lp_fee(&mut self, fee: u64) -> u64 {
+        if self.lp_assets == 0 {
+            return fee;
+        }
+        let retained =
+            (fee as u128 * self.lp_fee_share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        self.lp_assets = self.lp_assets.saturating_add(retained);
+        fee - retained
+    }
+
+    /// LP capital actually in the vault: `lp_assets` less what releases drew.
+    pub fn lp_held(&self) -> u64 {
+        self.lp_assets.saturating_sub(self.lp_drawn)
+    }
+
+    /// LP capital the vault can pay back right now, which is all
+    /// `remove_liquidity` may pay out.
+    pub fn free_liquidity(&self, vault_balance: u64) -> u64 {
+        vault_balance.saturating_sub(self.total_locked).min(self.lp_held())
+    }
+
+    /// What the vault holds beyond the locked deposits and LP capital, such
+    /// as tokens sent to it directly; `sync_surplus` sweeps it.
+    pub fn surplus(&self, vault_balance: u64) -> u64 {
+        vault_balance
+            .saturating_sub(self.total_locked)
+            .saturating_sub(self.lp_held())
+    }
+
+    /// Records `amount` newly locked in the vault. It first makes good LP
+    /// capital earlier releases drew, and only the rest counts as locked.
+    pub fn add_locked(&mut self, amount: u64) -> Result<()> {
+        let repaid = amount.min(self.lp_drawn);
+        self.lp_drawn -= repaid;
+        self.total_locked = self
+            .total_locked
+            .checked_add(amount - repaid)
+            .ok_or(BridgeError::CounterOverflow)?;
+        Ok(())
+    }
+
+    /// Records `amount` released out of the vault. Locked deposits cover it
+    /// first and LP capital the rest, which stays owed to LPs as `lp_drawn`.
+    /// A release beyond both means the books are already wrong, so it fails
+    /// instead of stopping at zero.
+    pub fn sub_locked(&mut self, amount: u64) -> Result<()> {
+        let from_locked = amount.min(self.total_locked);
+        let from_lp = amount - from_locked;
+        require!(from_lp <= self.lp_held(), BridgeError::LockedBalanceUnderflow);
+        self.total_locked -= from_locked;
+        self.lp_drawn += from_lp;
+        Ok(())
+    }
+
+    /// Takes `amount` off the books after an emergency withdrawal: locked
+    /// deposits first, then LP capital, which LPs lose. Stops at zero, since
+    /// after an exploit the books may already exceed the vault.
+    pub fn write_off(&mut self, amount: u64) {
+        let from_locked = amount.min(self.total_locked);
+        self.total_locked -= from_locked;
+        self.lp_assets -= (amount - from_locked).min(self.lp_held());
+    }
+
+    /// Re-reads `vault` after the transfers that moved its balance and checks
+    /// that it still covers every locked deposit and the LP capital it holds.
+    pub fn check_vault(&self, vault: &mut InterfaceAccount<TokenAccount>) -> Result<()> {
+        vault.reload()?;
+        self.check_vault_balance(vault.amount)
+    }
+
+    fn check_vault_balance(&self, vault_balance: u64) -> Result<()> {
+        let owed = self.total_locked as u128 + self.lp_held() as u128;
+        require!(vault_balance as u128 >= owed, BridgeError::VaultInvariantViolated);
+        Ok(())
+    }
+
+    /// Fee on releasing `amount` from a vault currently holding `vault_balance`.
+    pub fn outflow_fee_bps(&self, amount: u64, vault_balance: u64) -> u16 {
+        let denominator = BPS_DENOMINATOR as u128;
+        let utilization = if vault_balance == 0 {
+            denominator
+        } else {
+            (amount as u128 * denominator / vault_balance as u128).min(denominator)
+        };
+        let kink = self.utilization_kink_bps as u128;
+        if utilization <= kink {
+            return self.outflow_fee_bps;
+        }
+        let surcharge =
+            self.utilization_surcharge_bps as u128 * (utilization - kink) / (denominator - kink);
+        self.outflow_fee_bps + surcharge as u16
+    }
+
+    /// Gate for every path that moves tokens out of the bridge.
+    pub fn check_outflow_allowed(&self) -> Result<()> {
+        require!(!self.circuit_breaker_tripped, BridgeError::CircuitBreakerTripped);
+        require!(!self.paused, BridgeError::TokenPaused);
+        Ok(())
+    }
+
+    /// Adds `amount` to the current bucket. If that would take the window past
+    /// `circuit_breaker_limit`, nothing is recorded and the would-be window total
+    /// is returned instead.
+    pub fn record_outflow(&mut self, amount: u64, slot: u64) -> Option<u64> {
+        if self.circuit_breaker_limit == 0 {
+            return None;
+        }
+        let window_volume = self.outflow_window(amount, slot);
+        if window_volume > self.circuit_breaker_limit {
+            return Some(window_volume);
+        }
+
+        let epoch = slot / self.circuit_breaker_bucket_slots;
+        OutflowBucket::add(&mut self.outflow_buckets, epoch, amount);
+        None
+    }
+
+    /// The window total at `slot` if `amount` were added to it.
+    fn outflow_window(&self, amount: u64, slot: u64) -> u64 {
+        let epoch = slot / self.circuit_breaker_bucket_slots;
+        OutflowBucket::window(&self.outflow_buckets, epoch)
+            .fold(amount, |total, bucket| total.saturating_add(bucket.volume))
+    }
+
+    /// Trips the breaker if `amount` would take the window past the limit,
+    /// returning the would-be window total. Nothing is recorded otherwise.
+    pub fn trip_circuit_breaker(&mut self, amount: u64, slot: u64) -> Result<u64> {
+        require!(!self.circuit_breaker_tripped, BridgeError::CircuitBreakerTripped);
+        require!(self.circuit_breaker_limit != 0, BridgeError::CircuitBreakerNotExceeded);
+        let window_volume = self.outflow_window(amount, slot);
+        require!(
+            window_volume > self.circuit_breaker_limit,
+            BridgeError::CircuitBreakerNotExceeded
+        );
+        self.circuit_breaker_tripped = true;
+        Ok(window_volume)
+    }
+
+    /// Clears a trip and the outflow window.
+    pub fn reset_circuit_breaker(&mut self) {
+        self.circuit_breaker_tripped = false;
+        self.outflow_buckets = [OutflowBucket::default(); CIRCUIT_BREAKER_BUCKETS];
+    }
+
+    /// Counts `amount` against the rolling daily limit, or fails with
+    /// `DailyLimitExceeded` and a `DailyCapacity` in return data. Only new
+    /// outflow is checked, so lowering the limit never undoes past releases.
+    pub fn record_daily_outflow(&mut self, amount: u64, slot: u64) -> Result<()> {
+        if self.daily_limit == 0 {
+            return Ok(());
+        }
+        let epoch = slot / DAILY_LIMIT_BUCKET_SLOTS;
+        let used = OutflowBucket::window(&self.daily_outflow, epoch)
+            .fold(0u64, |total, bucket| total.saturating_add(bucket.volume));
+        let remaining = self.daily_limit.saturating_sub(used);
+        if amount > remaining {
+            let oldest = OutflowBucket::window(&self.daily_outflow, epoch)
+                .filter(|bucket| bucket.volume > 0)
+                .map(|bucket| bucket.epoch)
+                .min()
+                .unwrap_or(epoch);
+            let frees_at_slot = (oldest + DAILY_LIMIT_BUCKETS as u64) * DAILY_LIMIT_BUCKET_SLOTS;
+            set_return_data(&DailyCapacity { remaining, frees_at_slot }.try_to_vec()?);
+            return err!(BridgeError::DailyLimitExceeded);
+        }
+
+        OutflowBucket::add(&mut self.daily_outflow, epoch, amount);
+        Ok(())
+    }
+}
+
+/// Wormhole emitter trusted for one of our source chains, keyed by its
+/// Wormhole chain id
+#[account]
+#[derive(InitSpace)]
+pub struct WormholeEmitter {
+    pub wormhole_chain: u16,
+    pub emitter_address: [u8; 32],
+    /// Our id of the chain the emitter's VAAs are transfers from
+    pub source_chain: [u8; 32],
+    pub bump: u8,
+}
+
+/// Marks a VAA as redeemed, keyed by its body hash
+#[account]
+#[derive(InitSpace)]
+pub struct ProcessedVaa {
+    pub vaa_hash: [u8; 32],
+    pub processed_slot: u64,
+    pub bump: u8,
+}
+
+/// The fields of a Wormhole core bridge posted-VAA account this program reads
+pub struct PostedVaa {
+    pub consistency_level: u8,
+    pub timestamp: u32,
+    pub nonce: u32,
+    pub sequence: u64,
+    pub emitter_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub payload: Vec<u8>,
+}
+
+impl PostedVaa {
+    /// Reads the core bridge's `PostedVAAData` layout: the `vaa` magic, then
+    /// little-endian fields, the emitter and a length-prefixed payload.
+    pub fn parse(data: &[u8]) -> Result<PostedVaa> {
+        let field = |at: usize, len: usize| data.get(at..at + len).ok_or(BridgeError::InvalidVaa);
+        let le_u32 = |at| -> Result<u32> { Ok(u32::from_le_bytes(field(at, 4)?.try_into().unwrap())) };
+        require!(field(0, 3)? == b"vaa", BridgeError::InvalidVaa);
+        let consistency_level = field(4, 1)?[0];
+        let timestamp = le_u32(5)?;
+        let nonce = le_u32(45)?;
+        let sequence = u64::from_le_bytes(field(49, 8)?.try_into().unwrap());
+        let emitter_chain = u16::from_le_bytes(field(57, 2)?.try_into().unwrap());
+        let emitter_address: [u8; 32] = field(59, 32)?.try_into().unwrap();
+        let payload_len = le_u32(91)? as usize;
+        let payload = field(95, payload_len)?.to_vec();
+        Ok(PostedVaa {
+            consistency_level,
+            timestamp,
+            nonce,
+            sequence,
+            emitter_chain,
+            emitter_address,
+            payload,
+        })
+    }
+
+    /// keccak256 of the VAA body as Wormhole serializes it, big-endian; the
+    /// core bridge derives the posted-VAA address from this.
+    pub fn hash(&self) -> [u8; 32] {
+        keccak::hashv(&[
+            &self.timestamp.to_be_bytes(),
+            &self.nonce.to_be_bytes(),
+            &self.emitter_chain.to_be_bytes(),
+            &self.emitter_address,
+            &self.sequence.to_be_bytes(),
+            &[self.consistency_level],
+            &self.payload,
+        ])
+        .to_bytes()
+    }
+}
+
+/// A destination chain the relayer delivers to, keyed by its 32-byte chain id
+#[account]
+#[derive(InitSpace)]
+pub struct ChainRoute {
+    pub chain_id: [u8; 32],
+    pub enabled: bool,
+    /// Exact length of a recipient address on the target chain
+    pub address_len: u8,
+    /// Source-chain confirmations the relayer waits for before attesting
+    pub confirmations: u32,
+    /// How inbound transfers from this chain are deduplicated; fixed at
+    /// registration, since switching would let delivered transfers through again
+    pub replay_mode: ReplayMode,
+    /// Floor on how long `ProcessedRecord`s from this chain are kept, on top
+    /// of `BridgeConfig::retention_slots`; sized to the chain's finality
+    pub min_retention_slots: u64,
+    /// Only relayers in the `RelayerRegistry` may deliver transfers from this
+    /// chain
+    pub restricted_delivery: bool,
+    /// Deliveries must arrive in source-nonce order
+    pub strict_ordering: bool,
+    /// Nonce the next delivery must carry while `strict_ordering` is set
+    pub next_expected_nonce: u64,
+    /// Encoding target addresses toward this chain must follow
+    pub address_format: AddressFormat,
+    /// Prefix every target address carries under `AddressFormat::Bech32`
+    #[max_len(MAX_BECH32_HRP_LEN)]
+    pub bech32_hrp: String,
+    pub bump: u8,
+}
+
+/// Native token standing for a bridged mint on one target chain, at
+/// `[b"destination_token", mint, target_chain]`
+#[account]
+#[derive(InitSpace)]
+pub struct DestinationToken {
+    pub mint: Pubkey,
+    pub target_chain: [u8; 32],
+    /// Token address on the target chain, `ChainRoute::address_len` bytes long
+    #[max_len(MAX_TARGET_ADDR_LEN)]
+    pub token: Vec<u8>,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl DestinationToken {
+    /// Mapped token behind an already seed-checked account; `None` when the
+    /// mint has no mapping for that chain.
+    pub fn load(info: &AccountInfo) -> Result<Option<Vec<u8>>> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(None);
+        }
+        let data = info.try_borrow_data()?;
+        Ok(Some(DestinationToken::try_deserialize(&mut &data[..])?.token))
+    }
+}
+
+/// Replay protection applied to transfers arriving from a chain
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum ReplayMode {
+    /// One `ProcessedRecord` per `(source_chain, source_tx)`
+    TxHash,
+    /// One bit per source nonce in `ReplayBitmap` pages, via `release_by_nonce`
+    Nonce,
+}
+
+/// How a route's target addresses are encoded, checked on every lock and burn
+/// toward it so an address meant for another chain is refused up front.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, InitSpace)]
+pub enum AddressFormat {
+    /// Any `address_len` bytes but all zeroes
+    #[default]
+    Raw,
+    /// 20 raw bytes, not the zero address
+    Evm,
+    /// 32 raw bytes, not the zero address
+    Solana,
+    /// ASCII `hrp1...` with a valid bech32 checksum, as Cosmos chains use
+    Bech32,
+}
+
+impl AddressFormat {
+    /// Checks that a route of `address_len` bytes can hold addresses of this
+    /// format, and that `bech32_hrp` is a usable prefix when one is needed.
+    pub fn check_route(self, address_len: u8, bech32_hrp: &str) -> Result<()> {
+        let valid = match self {
+            AddressFormat::Raw => bech32_hrp.is_empty(),
+            AddressFormat::Evm => address_len == EVM_ADDRESS_LEN && bech32_hrp.is_empty(),
+            AddressFormat::Solana => address_len == SOLANA_ADDRESS_LEN && bech32_hrp.is_empty(),
+            AddressFormat::Bech32 => {
+                is_bech32_hrp(bech32_hrp)
+                    && address_len as usize > bech32_hrp.len() + 1 + BECH32_CHECKSUM_LEN
+            }
+        };
+        require!(valid, self.error());
+        Ok(())
+    }
+
+    /// Error a target address that does not fit this format fails with
+    pub fn error(self) -> BridgeError {
+        match self {
+            AddressFormat::Raw => BridgeError::InvalidTargetAddress,
+            AddressFormat::Evm => BridgeError::InvalidEvmAddress,
+            AddressFormat::Solana => BridgeError::InvalidSolanaAddress,
+            AddressFormat::Bech32 => BridgeError::InvalidBech32Address,
+        }
+    }
+}
+
+/// An admin-queued withdrawal out of a vault, executable only while paused
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyWithdraw {
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Token account that receives the funds
+    pub destination: Pubkey,
+    pub rent_payer: Pubkey,
+    pub queued_slot: u64,
+    pub executable_after_slot: u64,
+    pub bump: u8,
+}
+
+/// One user's outbound transfers of one token in the current epoch
+#[account]
+#[derive(InitSpace)]
+pub struct UserActivity {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub epoch: u64,
+    pub lock_count: u32,
+    pub volume: u64,
+    pub bump: u8,
+}
+
+/// The wrapped mint standing for `source_token` of `source_chain`. New mints
+/// derive from `[b"wrapped_mint", source_chain, source_token]`; ones created
+/// under the old per-chain seeds are recorded with `legacy_seeds`.
+#[account]
+#[derive(InitSpace)]
+pub struct WrappedAssetRegistry {
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub wrapped_mint: Pubkey,
+    /// Bump of the wrapped mint PDA, which is its own mint authority
+    pub mint_bump: u8,
+    /// Mint derives from `[b"wrapped_mint", source_chain]` only
+    pub legacy_seeds: bool,
+    /// Most wrapped supply that may exist at once; 0 leaves it uncapped
+    pub supply_cap: u64,
+    /// Decimals of `source_token`, the unit of attested and burned amounts
+    pub source_decimals: u8,
+    /// Decimals of `wrapped_mint`
+    pub local_decimals: u8,
+    pub bump: u8,
+    /// May be burned toward chains other than `source_chain`
+    pub multi_hop: bool,
+}
+
+impl WrappedAssetRegistry {
+    /// Local units per source unit, or per local unit when the source has
+    /// more decimals.
+    fn scale(&self) -> Result<u64> {
+        10u64
+            .checked_pow(self.local_decimals.abs_diff(self.source_decimals) as u32)
+            .ok_or_else(|| error!(BridgeError::AmountNotRepresentable))
+    }
+
+    /// Smallest local amount that maps to a whole source unit.
+    pub fn source_unit(&self) -> Result<u64> {
+        if self.local_decimals > self.source_decimals {
+            self.scale()
+        } else {
+            Ok(1)
+        }
+    }
+
+    /// Converts an attested source `amount` to local units, truncating; the
+    /// second value is the truncated dust, in source units.
+    pub fn to_local(&self, amount: u64) -> Result<(u64, u64)> {
+        let scale = self.scale()?;
+        if self.source_decimals >= self.local_decimals {
+            Ok((amount / scale, amount % scale))
+        } else {
+            let local = amount.checked_mul(scale).ok_or(BridgeError::AmountNotRepresentable)?;
+            Ok((local, 0))
+        }
+    }
+
+    /// Converts a local `amount` to source units, failing unless it is exact.
+    pub fn to_source(&self, amount: u64) -> Result<u64> {
+        let scale = self.scale()?;
+        if self.local_decimals >= self.source_decimals {
+            let source = amount / scale;
+            require!(source * scale == amount, BridgeError::AmountNotRepresentable);
+            Ok(source)
+        } else {
+            Ok(amount.checked_mul(scale).ok_or(BridgeError::AmountNotRepresentable)?)
+        }
+    }
+
+    /// Checked against the mint's live supply, so burns free capacity and
+    /// there is no shadow counter to drift.
+    pub fn check_supply_cap(&self, supply: u64, amount: u64) -> Result<()> {
+        if self.supply_cap == 0 {
+            return Ok(());
+        }
+        let new_supply = supply.checked_add(amount).ok_or(BridgeError::WrappedSupplyCapExceeded)?;
+        require!(new_supply <= self.supply_cap, BridgeError::WrappedSupplyCapExceeded);
+        Ok(())
+    }
+
+    /// Runs `f` with the seeds the wrapped mint signs with as its authority.
+    pub fn with_mint_seeds<T>(&self, f: impl FnOnce(&[&[u8]]) -> Result<T>) -> Result<T> {
+        let bump = [self.mint_bump];
+        if self.legacy_seeds {
+            f(&[b"wrapped_mint", &self.source_chain, &bump])
+        } else {
+            f(&[b"wrapped_mint", &self.source_chain, &self.source_token, &bump])
+        }
+    }
+}
+
+/// Reverse index at `[b"origin", wrapped_mint]`, so the foreign token behind
+/// a wrapped mint can be resolved from the mint address alone.
+#[account]
+#[derive(InitSpace)]
+pub struct WrappedOrigin {
+    pub wrapped_mint: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub source_decimals: u8,
+    pub bump: u8,
+}
+
+/// A referrer's unclaimed share of protocol fees in one mint's fee vault
+#[account]
+#[derive(InitSpace)]
+pub struct ReferrerAccount {
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub claimable: u64,
+    pub bump: u8,
+}
+
+/// Bars `address` from the bridge, at `[b"blocked", address]`
+#[account]
+#[derive(InitSpace)]
+pub struct BlockedAddress {
+    pub address: Pubkey,
+    pub rent_payer: Pubkey,
+    pub blocked_slot: u64,
+    pub bump: u8,
+}
+
+impl BlockedAddress {
+    /// Whether an entry exists behind an already seed-checked account.
+    pub fn is_blocked(info: &AccountInfo) -> bool {
+        info.owner == &crate::ID && !info.data_is_empty()
+    }
+
+    pub fn check(info: &AccountInfo) -> Result<()> {
+        require!(!BlockedAddress::is_blocked(info), BridgeError::AddressBlocked);
+        Ok(())
+    }
+}
+
+/// Marks `user` as exempt from the protocol fee on `lock` and `burn_wrapped`
+#[account]
+#[derive(InitSpace)]
+pub struct FeeExemption {
+    pub user: Pubkey,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl ChainRoute {
+    /// Reads the route behind an already seed-checked account, treating an
+    /// uninitialized address as an unsupported chain.
+    pub fn load(info: &AccountInfo) -> Result<ChainRoute> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return err!(BridgeError::UnsupportedChain);
+        }
+        let data = info.try_borrow_data()?;
+        ChainRoute::try_deserialize(&mut &data[..])
+    }
+
+    /// Replay mode for transfers arriving from the chain behind a seed-checked
+    /// account; chains without a route fall back to tx-hash records.
+    pub fn replay_mode_of(info: &AccountInfo) -> Result<ReplayMode> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(ReplayMode::TxHash);
+        }
+        Ok(ChainRoute::load(info)?.replay_mode)
+    }
+
+    /// Retention floor for records from the chain behind a seed-checked
+    /// account; chains without a route add none beyond the global one.
+    pub fn min_retention_of(info: &AccountInfo) -> Result<u64> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(0);
+        }
+        Ok(ChainRoute::load(info)?.min_retention_slots)
+    }
+
+    /// Enforces `strict_ordering` on a delivery of `nonce` from the chain
+    /// behind a seed-checked, writable account, advancing the expected nonce.
+    /// Chains without a route, or without the flag, accept any order.
+    pub fn take_nonce(info: &AccountInfo, nonce: u64) -> Result<()> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(());
+        }
+        let mut route = ChainRoute::load(info)?;
+        if !route.strict_ordering {
+            return Ok(());
+        }
+        require!(nonce == route.next_expected_nonce, BridgeError::OutOfOrderDelivery);
+        route.next_expected_nonce = nonce.checked_add(1).ok_or(BridgeError::CounterOverflow)?;
+        let mut data = info.try_borrow_mut_data()?;
+        route.try_serialize(&mut &mut data[..])
+    }
+
+    /// Whether deliveries from the chain behind a seed-checked account are
+    /// limited to registered relayers; chains without a route are open.
+    pub fn restricted_delivery_of(info: &AccountInfo) -> Result<bool> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(false);
+        }
+        Ok(ChainRoute::load(info)?.restricted_delivery)
+    }
+
+    pub fn validate_target(&self, target_addr: &[u8]) -> Result<()> {
+        require!(self.enabled, BridgeError::RouteDisabled);
+        let valid = target_addr.len() == self.address_len as usize
+            && target_addr.iter().any(|b| *b != 0)
+            && match self.address_format {
+                AddressFormat::Raw | AddressFormat::Evm | AddressFormat::Solana => true,
+                AddressFormat::Bech32 => is_bech32_address(&self.bech32_hrp, target_addr),
+            };
+        require!(valid, self.address_format.error());
+        Ok(())
+    }
+}
+
+impl<'info> Lock<'info> {
+    /// The user signs its own transfers, so there are no signer seeds
+    fn lock_accounts<'a>(
+        &'a mut self,
+        bumps: &LockBumps,
+        hook_accounts: &'a [AccountInfo<'info>],
+    ) -> LockAccounts<'a, 'info> {
+        LockAccounts {
+            payer: &self.payer,
+            source: self.user.key(),
+            operator: None,
+            authority: self.user.to_account_info(),
+            signer_seeds: &[],
+            blocked_address: &self.blocked_address,
+            user_token_account: &self.user_token_account,
+            token_mint: &self.token_mint,
+            token_registry: &mut self.token_registry,
+            vault: &mut self.vault,
+            fee_vault: &self.fee_vault,
+            insurance_vault: &self.insurance_vault,
+            exempt: self.fee_exemption.is_some(),
+            referrer_account: self.referrer_account.as_mut(),
+            user_activity: self.user_activity.as_mut(),
+            user_activity_bump: bumps.user_activity,
+            chain_route: &self.chain_route,
+            destination_token: &self.destination_token,
+            pending_lock: &self.pending_lock,
+            lock_sequence: &mut self.lock_sequence,
+            lock_sequence_bump: bumps.lock_sequence,
+            config: &self.config,
+            bridge_state: &self.bridge_state,
+            token_program: &self.token_program,
+            system_program: &self.system_program,
+            memo_program: self.memo_program.as_ref(),
+            hook_accounts,
+        }
+    }
+}
+
+impl<'info> LockDelegated<'info> {
+    /// The lock delegate PDA signs for the owner under `signer_seeds`
+    fn lock_accounts<'a>(
+        &'a mut self,
+        bumps: &LockDelegatedBumps,
+        signer_seeds: &'a [&'a [&'a [u8]]],
+        hook_accounts: &'a [AccountInfo<'info>],
+    ) -> LockAccounts<'a, 'info> {
+        LockAccounts {
+            payer: &self.operator,
+            source: self.owner.key(),
+            operator: Some(self.operator.key()),
+            authority: self.lock_delegate.to_account_info(),
+            signer_seeds,
+            blocked_address: &self.blocked_address,
+            user_token_account: &self.user_token_account,
+            token_mint: &self.token_mint,
+            token_registry: &mut self.token_registry,
+            vault: &mut self.vault,
+            fee_vault: &self.fee_vault,
+            insurance_vault: &self.insurance_vault,
+            exempt: self.fee_exemption.is_some(),
+            referrer_account: self.referrer_account.as_mut(),
+            user_activity: self.user_activity.as_mut(),
+            user_activity_bump: bumps.user_activity,
+            chain_route: &self.chain_route,
+            destination_token: &self.destination_token,
+            pending_lock: &self.pending_lock,
+            lock_sequence: &mut self.lock_sequence,
+            lock_sequence_bump: bumps.lock_sequence,
+            config: &self.config,
+            bridge_state: &self.bridge_state,
+            token_program: &self.token_program,
+            system_program: &self.system_program,
+            memo_program: self.memo_program.as_ref(),
+            hook_accounts,
+        }
+    }
+}
+
+impl<'info> LockWithAuthorization<'info> {
+    /// The order delegate PDA signs for the owner under `signer_seeds`; the
+    /// relayer pays and is recorded as the operator
+    fn lock_accounts<'a>(
+        &'a mut self,
+        bumps: &LockWithAuthorizationBumps,
+        signer_seeds: &'a [&'a [&'a [u8]]],
+        hook_accounts: &'a [AccountInfo<'info>],
+    ) -> LockAccounts<'a, 'info> {
+        LockAccounts {
+            payer: &self.relayer,
+            source: self.owner.key(),
+            operator: Some(self.relayer.key()),
+            authority: self.order_delegate.to_account_info(),
+            signer_seeds,
+            blocked_address: &self.blocked_address,
+            user_token_account: &self.user_token_account,
+            token_mint: &self.token_mint,
+            token_registry: &mut self.token_registry,
+            vault: &mut self.vault,
+            fee_vault: &self.fee_vault,
+            insurance_vault: &self.insurance_vault,
+            exempt: self.fee_exemption.is_some(),
+            referrer_account: None,
+            user_activity: self.user_activity.as_mut(),
+            user_activity_bump: bumps.user_activity,
+            chain_route: &self.chain_route,
+            destination_token: &self.destination_token,
+            pending_lock: &self.pending_lock,
+            lock_sequence: &mut self.lock_sequence,
+            lock_sequence_bump: bumps.lock_sequence,
+            config: &self.config,
+            bridge_state: &self.bridge_state,
+            token_program: &self.token_program,
+            system_program: &self.system_program,
+            memo_program: None,
+            hook_accounts,
+        }
+    }
+}
+
+impl<'info> Release<'info> {
+    fn transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.vault.to_account_info(),
+                mint: self.token_mint.to_account_info(),
+                to: self.user_token_account.to_account_info(),
+                authority: self.vault.to_account_info(),
+            }
+        )
+    }
+
+    fn fee_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.vault.to_account_info(),
+                mint: self.token_mint.to_account_info(),
+                to: self.fee_vault.to_account_info(),
+                authority: self.vault.to_account_info(),
+            }
+        )
+    }
+}
+
+#[event]
+pub struct BridgeInitialized {
+    pub bridge_state: Pubkey,
+    pub admin: Pubkey,
+    pub payer: Pubkey,
+}
+
+#[event]
+pub struct StateMigrated {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub authority: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ProcessedRecordMigrated {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// Hashes still left in the legacy list
+    pub remaining: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct VaultInitialized {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeVaultInitialized {
+    pub mint: Pubkey,
+    pub fee_vault: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct InsuranceVaultInitialized {
+    pub mint: Pubkey,
+    pub insurance_vault: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub current_admin: Pubkey,
+    pub old_pending_admin: Option<Pubkey>,
+    pub proposed_admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AdminProposalCancelled {
+    pub admin: Pubkey,
+    pub cancelled_admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Paused {
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Unpaused {
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawQueued {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub executable_after_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawExecuted {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawCancelled {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct InsuranceWithdrawQueued {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub executable_after_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct InsuranceWithdrawExecuted {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct InsuranceWithdrawCancelled {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub old: BridgeConfig,
+    pub new: BridgeConfig,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub admin: Pubkey,
+    pub old_fee_bps: u16,
+    pub new_fee_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeCollectorUpdated {
+    pub admin: Pubkey,
+    pub old_fee_collector: Pubkey,
+    pub new_fee_collector: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MaxNativeDropUpdated {
+    pub admin: Pubkey,
+    pub old_max_native_drop: u64,
+    pub new_max_native_drop: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferralShareUpdated {
+    pub admin: Pubkey,
+    pub old_referral_share_bps: u16,
+    pub new_referral_share_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MaxPayloadLenUpdated {
+    pub admin: Pubkey,
+    pub old_max_payload_len: u16,
+    pub new_max_payload_len: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RefundAfterSlotsUpdated {
+    pub admin: Pubkey,
+    pub old_refund_after_slots: u64,
+    pub new_refund_after_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MaxMemoLenUpdated {
+    pub admin: Pubkey,
+    pub old_max_memo_len: u16,
+    pub new_max_memo_len: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OptimisticReleaseConfigUpdated {
+    pub admin: Pubkey,
+    pub old_window_slots: u64,
+    pub new_window_slots: u64,
+    pub old_bond_bps: u16,
+    pub new_bond_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PriorityFeeUpdated {
+    pub admin: Pubkey,
+    pub old_priority_fee_bps: u16,
+    pub new_priority_fee_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeSplitUpdated {
+    pub admin: Pubkey,
+    pub old_treasury_share_bps: u16,
+    pub old_insurance_share_bps: u16,
+    pub new_treasury_share_bps: u16,
+    pub new_insurance_share_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferrerRegistered {
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferralFeesClaimed {
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RateLimitUpdated {
+    pub admin: Pubkey,
+    pub old_max_locks_per_epoch: u32,
+    pub old_max_volume_per_epoch: u64,
+    pub new_max_locks_per_epoch: u32,
+    pub new_max_volume_per_epoch: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub authority: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct SurplusSynced {
+    pub mint: Pubkey,
+    /// Moved from the vault to the fee vault
+    pub amount: u64,
+    pub total_locked: u64,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ComplianceAuthorityUpdated {
+    pub admin: Pubkey,
+    pub old_compliance_authority: Pubkey,
+    pub new_compliance_authority: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AddressBlocklisted {
+    pub compliance_authority: Pubkey,
+    pub address: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AddressUnblocked {
+    pub compliance_authority: Pubkey,
+    pub address: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct BlockedReleaseRedirected {
+    pub admin: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub old_recipient: Pubkey,
+    pub new_recipient: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedAuthorityRepaired {
+    pub wrapped_mint: Pubkey,
+    pub admin: Pubkey,
+    /// Signed the authorities back
+    pub current_authority: Pubkey,
+    pub old_mint_authority: Option<Pubkey>,
+    pub old_freeze_authority: Option<Pubkey>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeExemptionAdded {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeExemptionRemoved {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LimitUpdated {
+    pub admin: Pubkey,
+    pub old_min_amount: u64,
+    pub old_max_amount: u64,
+    pub new_min_amount: u64,
+    pub new_max_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReleaseDelayUpdated {
+    pub admin: Pubkey,
+    pub old_release_delay: u64,
+    pub new_release_delay: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MaxTargetAddrLenUpdated {
+    pub admin: Pubkey,
+    pub old_max_target_addr_len: u8,
+    pub new_max_target_addr_len: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianAdded {
+    pub admin: Pubkey,
+    pub guardian: GuardianKey,
+    pub guardian_set_index: u32,
+    pub guardian_count: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianRemoved {
+    pub admin: Pubkey,
+    pub guardian: GuardianKey,
+    pub guardian_set_index: u32,
+    pub guardian_count: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianThresholdUpdated {
+    pub admin: Pubkey,
+    pub guardian_set_index: u32,
+    pub old_threshold: u8,
+    pub new_threshold: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianSetRotated {
+    pub admin: Pubkey,
+    pub old_index: u32,
+    pub old_expires_at_slot: u64,
+    pub new_index: u32,
+    pub guardian_count: u8,
+    pub threshold: u8,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianSetClosed {
+    pub admin: Pubkey,
+    pub index: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct StakingInitialized {
+    pub admin: Pubkey,
+    pub stake_mint: Pubkey,
+    pub treasury: Pubkey,
+    pub min_stake: u64,
+    pub unstake_cooldown_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianStaked {
+    pub staker: Pubkey,
+    pub guardian: GuardianKey,
+    pub amount: u64,
+    pub total_stake: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianUnstakeRequested {
+    pub staker: Pubkey,
+    pub guardian: GuardianKey,
+    pub amount: u64,
+    pub unlock_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianUnstaked {
+    pub staker: Pubkey,
+    pub guardian: GuardianKey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct GuardianSlashed {
+    pub admin: Pubkey,
+    pub guardian: GuardianKey,
+    pub amount: u64,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub guardian_set_index: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LargeReleaseUpdated {
+    pub admin: Pubkey,
+    pub old_threshold: u64,
+    pub old_delay_slots: u64,
+    pub new_threshold: u64,
+    pub new_delay_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct EmergencyWithdrawDelayUpdated {
+    pub admin: Pubkey,
+    pub old_delay_slots: u64,
+    pub new_delay_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RetentionUpdated {
+    pub admin: Pubkey,
+    pub old_retention_slots: u64,
+    pub old_rent_collector: Pubkey,
+    pub old_prune_incentive_bps: u16,
+    pub new_retention_slots: u64,
+    pub new_rent_collector: Pubkey,
+    pub new_prune_incentive_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenRegistered {
+    pub mint: Pubkey,
+    pub registry: Pubkey,
+    pub decimals: u8,
+    pub supply: u64,
+    pub mint_authority: Option<Pubkey>,
+    pub min_amount: u64,
+    pub max_amount: u64,
+    pub daily_limit: u64,
+    pub freeze_authority: Option<Pubkey>,
+    /// `ALLOW_*` flags the admin accepted for this mint
+    pub allowed_extensions: u8,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenPaused {
+    pub mint: Pubkey,
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenUnpaused {
+    pub mint: Pubkey,
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+/// Logged by a release or mint that was refused because a token account it
+/// pays into or out of is frozen; the transaction itself fails.
+#[event]
+pub struct PayoutFrozen {
+    pub mint: Pubkey,
+    pub frozen_account: Pubkey,
+    /// The frozen account is a bridge vault rather than a recipient
+    pub is_vault: bool,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenDelisted {
+    pub mint: Pubkey,
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenDecommissioned {
+    pub mint: Pubkey,
+    pub vault_swept: u64,
+    pub fee_vault_swept: u64,
+    pub treasury: Pubkey,
+    pub rent_collector: Pubkey,
+    /// Rent reclaimed from the vault, the fee vault and the registry
+    pub lamports: u64,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenRelisted {
+    pub mint: Pubkey,
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedAssetCreated {
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub wrapped_mint: Pubkey,
+    pub registry: Pubkey,
+    pub decimals: u8,
+    pub source_decimals: u8,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedMetadataCreated {
+    pub wrapped_mint: Pubkey,
+    pub metadata: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedMetadataUpdated {
+    pub wrapped_mint: Pubkey,
+    pub metadata: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedSupplyCapUpdated {
+    pub wrapped_mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_supply_cap: u64,
+    pub new_supply_cap: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedMultiHopUpdated {
+    pub wrapped_mint: Pubkey,
+    pub admin: Pubkey,
+    pub multi_hop: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedAssetRegistered {
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub wrapped_mint: Pubkey,
+    pub legacy_seeds: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenRegistryMigrated {
+    pub mint: Pubkey,
+    pub old_len: u32,
+    pub new_len: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenFeeUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_fee_bps: Option<u16>,
+    pub new_fee_bps: Option<u16>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OutflowFeeUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub base_bps: u16,
+    pub kink_bps: u16,
+    pub surcharge_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TokenLimitsUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_min_amount: u64,
+    pub old_max_amount: u64,
+    pub new_min_amount: u64,
+    pub new_max_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct DailyLimitUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_daily_limit: u64,
+    pub new_daily_limit: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct CircuitBreakerUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_limit: u64,
+    pub old_bucket_slots: u64,
+    pub new_limit: u64,
+    pub new_bucket_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct CircuitBreakerTripped {
+    pub mint: Pubkey,
+    /// Watcher who recorded the trip
+    pub by: Pubkey,
+    pub attempted_amount: u64,
+    pub window_volume: u64,
+    pub limit: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct CircuitBreakerReset {
+    pub mint: Pubkey,
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteRegistered {
+    pub chain_id: [u8; 32],
+    pub address_len: u8,
+    pub confirmations: u32,
+    pub replay_mode: ReplayMode,
+    pub address_format: AddressFormat,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteUnpaused {
+    pub chain_id: [u8; 32],
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteDeliveryRestrictionUpdated {
+    pub chain_id: [u8; 32],
+    pub admin: Pubkey,
+    pub restricted_delivery: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteAddressFormatUpdated {
+    pub chain_id: [u8; 32],
+    pub admin: Pubkey,
+    pub address_format: AddressFormat,
+    pub bech32_hrp: String,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteOrderingUpdated {
+    pub chain_id: [u8; 32],
+    pub admin: Pubkey,
+    pub strict_ordering: bool,
+    pub next_expected_nonce: u64,
+    pub slot: u64,
+}
+
+/// Raised by the admin escape hatch; the skipped transfer will not be delivered in order
+#[event]
+pub struct NonceSkipped {
+    pub chain_id: [u8; 32],
+    pub admin: Pubkey,
+    pub skipped_nonce: u64,
+    pub next_expected_nonce: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RouteRetentionUpdated {
+    pub chain_id: [u8; 32],
+    pub admin: Pubkey,
+    pub old_min_retention_slots: u64,
+    pub new_min_retention_slots: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct DestinationTokenSet {
+    pub mint: Pubkey,
+    pub target_chain: [u8; 32],
+    pub old_token: Option<Vec<u8>>,
+    /// `None` when the mapping was cleared
+    pub new_token: Option<Vec<u8>>,
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RoutePaused {
+    pub chain_id: [u8; 32],
+    pub by: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Locked {
+    /// Canonical id shared with the other chain's event for the same transfer
+    pub transfer_id: [u8; 32],
+    /// The signer, who paid; any refund goes back here
+    pub source: Pubkey,
+    /// Beneficiary the destination credits when locked on someone's behalf;
+    /// `None` means `source` itself
+    pub on_behalf_of: Option<Pubkey>,
+    /// Who submitted the lock against `source`'s approval: the
+    /// `lock_delegated` operator or the `lock_with_authorization` relayer
+    pub operator: Option<Pubkey>,
+    pub token: Pubkey,
+    /// Decimals of `token`, for the destination to normalize amounts
+    pub decimals: u8,
+    /// Alias of `net_amount`, kept for decoders predating the fee fields
+    pub amount: u64,
+    /// What the user sent, `net_amount + fee + transfer_fee`
+    pub gross_amount: u64,
+    pub fee: u64,
+    /// What the vault received, net of both fees; what guardians attest and
+    /// the destination releases
+    pub net_amount: u64,
+    /// Withheld by a Token-2022 transfer-fee mint on the way into the vault
+    pub transfer_fee: u64,
+    /// Part of `fee` routed to the insurance vault; the rest went to the fee vault
+    pub insurance_fee: u64,
+    /// Part of `fee` paid for `DeliveryTier::Priority`
+    pub priority_fee: u64,
+    /// The fee was waived by the user's `FeeExemption`
+    pub exempt: bool,
+    /// Part of `amount` a relayer earns by delivering it
+    pub relayer_fee: u64,
+    /// Lamports requested for the recipient wallet on delivery
+    pub native_drop: u64,
+    pub referrer: Option<Pubkey>,
+    /// Part of `fee` credited to `referrer`
+    pub referral_fee: u64,
+    /// The user named itself as referrer
+    pub self_referral: bool,
+    pub target_chain: [u8; 32],
+    pub target_addr: Vec<u8>,
+    /// Native token the destination releases; `None` means it mints the
+    /// wrapped asset instead
+    pub destination_token: Option<Vec<u8>>,
+    pub nonce: u64,
+    /// Position in the `(token, target_chain)` lock stream, see `TransferSequence`
+    pub sequence: u64,
+    /// Unix time after which the destination refuses delivery; 0 for none
+    pub deadline_unix: i64,
+    /// For the destination to write through its memo program on delivery
+    pub delivery_memo: Option<String>,
+    /// The sender's reference, written through the SPL Memo program at lock time
+    pub memo: Option<String>,
+    /// Relayers deliver `Priority` transfers first
+    pub tier: DeliveryTier,
+    /// Nonce of the first transfer of the `batch_lock` this one was part of
+    pub batch_id: Option<u64>,
+    pub slot: u64,
+    /// Cluster time of `slot`, for latency measurement across chains
+    pub unix_timestamp: i64,
+    /// The vault's balance once this lock landed in it
+    pub vault_balance: u64,
+}
+
+#[event]
+pub struct CallDelivered {
+    pub recipient_program: Pubkey,
+    pub escrow: Pubkey,
+    /// What reached the escrow before the call
+    pub amount: u64,
+    pub payload_hash: [u8; 32],
+    pub source_addr: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub source_chain: [u8; 32],
+}
+
+#[event]
+pub struct LockRefunded {
+    pub nonce: u64,
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LockCancelled {
+    pub nonce: u64,
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LockSettled {
+    pub nonce: u64,
+    pub mint: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LockedWithPayload {
+    pub sender: Pubkey,
+    /// Nonce of the `Locked` event emitted just before
+    pub nonce: u64,
+    pub payload_hash: [u8; 32],
+    pub payload: Vec<u8>,
+    pub consistency_level: ConsistencyLevel,
+    /// `PayloadMessage::digest`, what guardians sign
+    pub digest: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct Released {
+    /// Canonical id shared with the other chain's event for the same transfer
+    pub transfer_id: [u8; 32],
+    pub recipient: Pubkey,
+    /// Token paid out, whose `[b"vault", mint]` account it left
+    pub mint: Pubkey,
+    /// The attested amount; the recipient received `amount - fee`
+    pub amount: u64,
+    /// Outflow fee, utilization surcharge included; kept in the fee vault
+    /// apart from the liquidity providers' share, which stays in the vault
+    pub fee: u64,
+    /// Withheld from the recipient by a Token-2022 transfer-fee mint
+    pub transfer_fee: u64,
+    /// Paid to the executing relayer
+    pub relayer_fee: u64,
+    /// Lamports the relayer sent to the recipient wallet
+    pub native_drop: u64,
+    pub source_tx: [u8; 32],
+    /// With `source_tx`, the key the transfer was deduplicated on
+    pub source_chain: [u8; 32],
+    /// Written through the SPL Memo program in the same transaction
+    pub memo: Option<String>,
+    /// Tier the sender paid for, to measure delivery against
+    pub tier: DeliveryTier,
+    /// The delivery created the recipient's associated token account, with
+    /// the relayer paying its rent
+    pub recipient_account_created: bool,
+    pub slot: u64,
+    /// Cluster time of `slot`, for latency measurement across chains
+    pub unix_timestamp: i64,
+    /// The vault's balance once this payout left it
+    pub vault_balance: u64,
+}
+
+#[event]
+pub struct WrappedMinted {
+    /// Canonical id shared with the other chain's event for the same transfer
+    pub transfer_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub wrapped_mint: Pubkey,
+    /// Address of the token on `source_chain` that `wrapped_mint` stands for
+    pub source_token: [u8; 32],
+    /// The attested amount, in source-chain units
+    pub amount: u64,
+    /// `amount` in local units; the recipient received `minted - relayer_fee`
+    pub minted: u64,
+    /// Source units truncated off `amount` because the mint has fewer decimals
+    pub dust: u64,
+    /// Minted to the executing relayer, in local units
+    pub relayer_fee: u64,
+    /// Lamports the relayer sent to the recipient wallet
+    pub native_drop: u64,
+    pub source_tx: [u8; 32],
+    pub source_chain: [u8; 32],
+    /// Written through the SPL Memo program in the same transaction
+    pub memo: Option<String>,
+    /// Tier the sender paid for, to measure delivery against
+    pub tier: DeliveryTier,
+    /// The delivery created the recipient's associated token account, with
+    /// the relayer paying its rent
+    pub recipient_account_created: bool,
+    pub slot: u64,
+    /// Cluster time of `slot`, for latency measurement across chains
+    pub unix_timestamp: i64,
+    /// Supply of `wrapped_mint` after this mint
+    pub wrapped_supply: u64,
+}
+
+#[event]
+pub struct OptimisticReleasePosted {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub token: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub relayer: Pubkey,
+    pub bond: u64,
+    pub finalizable_after_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OptimisticReleaseChallenged {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub relayer: Pubkey,
+    pub challenger: Pubkey,
+    /// Bond moved to the insurance vault
+    pub slashed: u64,
+    pub guardian_set_index: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OptimisticReleaseFinalized {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub relayer: Pubkey,
+    pub bond_returned: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AttestationRootPosted {
+    pub root: [u8; 32],
+    pub leaf_count: u32,
+    pub guardian_set_index: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WormholeEmitterRegistered {
+    pub wormhole_chain: u16,
+    pub emitter_address: [u8; 32],
+    pub source_chain: [u8; 32],
+    pub admin: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct VaaRedeemed {
+    pub vaa_hash: [u8; 32],
+    pub emitter_chain: u16,
+    pub sequence: u64,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct NftLocked {
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    pub target_chain: [u8; 32],
+    pub target_addr: Vec<u8>,
+    pub nonce: u64,
+    pub metadata: NftMetadata,
+    /// `NftMetadata::hash` of `metadata`
+    pub metadata_hash: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedNftMinted {
+    pub recipient: Pubkey,
+    pub wrapped_mint: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub metadata_hash: [u8; 32],
+    /// The delivery created the recipient's associated token account, with
+    /// the relayer paying its rent
+    pub recipient_account_created: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedNftBurned {
+    pub source: Pubkey,
+    pub wrapped_mint: Pubkey,
+    /// Home chain of the NFT, where it is released
+    pub source_chain: [u8; 32],
+    pub source_token: [u8; 32],
+    pub target_addr: Vec<u8>,
+    pub nonce: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct NftReleased {
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    /// Nonce of the `NftLocked` event that escrowed it
+    pub lock_nonce: u64,
+    /// The delivery created the recipient's associated token account, with
+    /// the relayer paying its rent
+    pub recipient_account_created: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LpFeeShareUpdated {
+    pub mint: Pubkey,
+    pub admin: Pubkey,
+    pub old_lp_fee_share_bps: u16,
+    pub new_lp_fee_share_bps: u16,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+    /// What the vault received and `lp_assets` was credited with
+    pub amount: u64,
+    pub shares: u64,
+    /// `TokenRegistry::lp_assets` afterwards
+    pub lp_assets: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub lp_assets: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct AttestationPosted {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub token: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub nonce: u64,
+    pub guardian_set_index: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReleaseQueued {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub token: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub executable_after_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ProcessedRecordClosed {
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub rent_collector: Pubkey,
+    pub lamports: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ProcessedRecordsPruned {
+    pub cranker: Pubkey,
+    pub pruned: u32,
+    /// Records passed in but still inside retention or held by an active set
+    pub skipped: u32,
+    pub incentive_lamports: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RelayerAdded {
+    pub admin: Pubkey,
+    pub relayer: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RelayerRemoved {
+    pub admin: Pubkey,
+    pub relayer: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WatcherAdded {
+    pub admin: Pubkey,
+    pub watcher: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WatcherRemoved {
+    pub admin: Pubkey,
+    pub watcher: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReleaseVetoed {
+    pub watcher: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub slot: u64,
+}
+
+#[event]
+pub struct VetoResolved {
+    pub admin: Pubkey,
+    pub source_chain: [u8; 32],
+    pub source_tx: [u8; 32],
+    pub approved: bool,
+    pub vetoed_by: Option<Pubkey>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WrappedBurned {
+    /// Canonical id shared with the other chain's event for the same transfer
+    pub transfer_id: [u8; 32],
+    /// The signer, who paid; any refund goes back here
+    pub source: Pubkey,
+    /// Beneficiary the destination credits, as in `Locked::on_behalf_of`
+    pub on_behalf_of: Option<Pubkey>,
+    pub wrapped_mint: Pubkey,
+    /// Alias of `net_amount`, kept for decoders predating the fee fields
+    pub amount: u64,
+    /// What the user sent, `net_amount + fee`
+    pub gross_amount: u64,
+    pub fee: u64,
+    /// Net of the protocol fee, in wrapped-mint units
+    pub net_amount: u64,
+    /// `net_amount` in source-token units; what guardians attest and the
+    /// origin chain releases
+    pub source_amount: u64,
+    pub source_decimals: u8,
+    /// Part of `fee` routed to the insurance vault; the rest went to the fee vault
+    pub insurance_fee: u64,
+    /// Part of `fee` paid for `DeliveryTier::Priority`
+    pub priority_fee: u64,
+    /// The fee was waived by the user's `FeeExemption`
+    pub exempt: bool,
+    /// Part of `source_amount` a relayer earns by delivering it, in source units
+    pub relayer_fee: u64,
+    /// Lamports requested for the recipient wallet on delivery
+    pub native_drop: u64,
+    pub referrer: Option<Pubkey>,
+    /// Part of `fee` credited to `referrer`
+    pub referral_fee: u64,
+    /// The user named itself as referrer
+    pub self_referral: bool,
+    pub target_chain: [u8; 32],
+    pub target_addr: Vec<u8>,
+    pub nonce: u64,
+    /// Position in the `(wrapped_mint, target_chain)` burn stream, see `TransferSequence`
+    pub sequence: u64,
+    /// Unix time after which the origin chain refuses delivery; 0 for none
+    pub deadline_unix: i64,
+    /// For the origin chain to write through its memo program on delivery
+    pub delivery_memo: Option<String>,
+    /// The sender's reference, written through the SPL Memo program at burn time
+    pub memo: Option<String>,
+    /// Relayers deliver `Priority` transfers first
+    pub tier: DeliveryTier,
+    pub slot: u64,
+    /// Cluster time of `slot`, for latency measurement across chains
+    pub unix_timestamp: i64,
+    /// Supply of `wrapped_mint` after this burn
+    pub wrapped_supply: u64,
+}
+
+/// A lock or burn stream's `TransferSequence` was created; its first
+/// transfer carries `seed + 1`
+#[event]
+pub struct TransferSequenceStarted {
+    /// Locked token, or wrapped mint for a burn stream
+    pub mint: Pubkey,
+    pub target_chain: [u8; 32],
+    pub seed: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Codes are assigned in declaration order from Anchor's 6000 offset and
+/// relayers match on them, so variants are only ever appended.
+#[error_code]
+pub enum BridgeError {
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Invalid target address")]
+    InvalidTargetAddress,
+    #[msg("Transaction already processed")]
+    AlreadyProcessed,
+    #[msg("Account already initialized")]
+    AlreadyInitialized,
+    #[msg("Signer is not the bridge admin")]
+    Unauthorized,
+    #[msg("No admin transfer is pending")]
+    NoPendingAdmin,
+    #[msg("Bridge is paused")]
+    BridgePaused,
+    #[msg("Token is paused")]
+    TokenPaused,
+    #[msg("Target chain is not supported")]
+    UnsupportedChain,
+    #[msg("Route to target chain is disabled")]
+    RouteDisabled,
+    #[msg("Config value out of range")]
+    InvalidConfig,
+    #[msg("Amount is below the minimum transfer")]
+    AmountBelowMinimum,
+    #[msg("Amount is above the maximum transfer")]
+    AmountAboveMaximum,
+    #[msg("Guardian is already in the set")]
+    DuplicateGuardian,
+    #[msg("Guardian is not in the set")]
+    GuardianNotFound,
+    #[msg("Guardian set is full")]
+    GuardianSetFull,
+    #[msg("Threshold must be between 1 and the number of guardians")]
+    InvalidThreshold,
+    #[msg("Not enough distinct guardian signatures")]
+    InsufficientSignatures,
+    #[msg("Guardian signature does not cover this transfer")]
+    InvalidSignaturePayload,
+    #[msg("Malformed signature verification instruction")]
+    InvalidSignatureInstruction,
+    #[msg("Guardian set has expired")]
+    GuardianSetExpired,
+    #[msg("Guardian set is still active")]
+    GuardianSetActive,
+    #[msg("Token account does not belong to the attested recipient")]
+    RecipientMismatch,
+    #[msg("Guardian has no sufficient active stake")]
+    GuardianNotStaked,
+    #[msg("Stake account is bonded to a different guardian")]
+    GuardianMismatch,
+    #[msg("Guardian stake has been slashed")]
+    GuardianSlashed,
+    #[msg("Unstake cooldown has not elapsed")]
+    UnstakeCooldown,
+    #[msg("Guardian is still in the active set")]
+    GuardianStillActive,
+    #[msg("Slashing evidence is not two conflicting signed attestations")]
+    InvalidSlashEvidence,
+    #[msg("Watcher is already in the set")]
+    DuplicateWatcher,
+    #[msg("Watcher is not in the set")]
+    WatcherNotFound,
+    #[msg("Watcher set is full")]
+    WatcherSetFull,
+    #[msg("Release is on hold after a watcher veto")]
+    ReleaseVetoed,
+    #[msg("Release was cancelled after a watcher veto")]
+    ReleaseCancelled,
+    #[msg("Release is not on hold")]
+    ReleaseNotHeld,
+    #[msg("Release delay has not elapsed")]
+    ReleaseDelayActive,
+    #[msg("Release is already queued")]
+    AlreadyQueued,
+    #[msg("Bridge must be paused")]
+    BridgeNotPaused,
+    #[msg("Emergency withdraw timelock has not elapsed")]
+    EmergencyWithdrawTimelocked,
+    #[msg("Token outflow circuit breaker has tripped")]
+    CircuitBreakerTripped,
+    #[msg("Bridge state layout is not the current version")]
+    StateVersionMismatch,
+    #[msg("Legacy processed list still has entries to migrate")]
+    LegacyProcessedPending,
+    #[msg("Hash is not in the legacy processed list")]
+    LegacyRecordNotFound,
+    #[msg("Processed record is still inside its retention window")]
+    ProcessedRecordRetained,
+    #[msg("Source chain uses a different replay mode")]
+    WrongReplayMode,
+    #[msg("Invalid prune batch")]
+    InvalidPruneBatch,
+    #[msg("Token account is for a different mint")]
+    MintMismatch,
+    #[msg("Relayer fee must be below the transferred amount")]
+    InvalidRelayerFee,
+    #[msg("Native drop exceeds the configured maximum")]
+    NativeDropTooLarge,
+    #[msg("Referrer account is missing or belongs to another referrer")]
+    ReferrerMismatch,
+    #[msg("Token daily outflow limit exceeded")]
+    DailyLimitExceeded,
+    #[msg("Per-user lock rate limit exceeded")]
+    RateLimited,
+    #[msg("User activity account is required while rate limiting is on")]
+    UserActivityRequired,
+    #[msg("Token is not registered with the bridge")]
+    TokenNotRegistered,
+    #[msg("Decimals do not match the token mint")]
+    DecimalsMismatch,
+    #[msg("Token is delisted and accepts no new transfers")]
+    TokenDelisted,
+    #[msg("Wrapped mint does not match the registered asset")]
+    WrappedAssetMismatch,
+    #[msg("Token metadata name, symbol or URI is too long")]
+    MetadataTooLong,
+    #[msg("Mint would exceed the wrapped supply cap")]
+    WrappedSupplyCapExceeded,
+    #[msg("Amount is not representable in the other asset's decimals")]
+    AmountNotRepresentable,
+    #[msg("Token is a bridge-wrapped mint; use burn_wrapped")]
+    UseBurnWrappedInstead,
+    #[msg("Mint has a freeze authority")]
+    FreezableMint,
+    #[msg("Bridge vault token account is frozen")]
+    VaultFrozen,
+    #[msg("Recipient token account is frozen")]
+    RecipientAccountFrozen,
+    #[msg("Mint is non-transferable and cannot be bridged")]
+    NonTransferableMint,
+    #[msg("Mint has a transfer hook that was not allowed at registration")]
+    TransferHookNotAllowed,
+    #[msg("Mint creates accounts frozen by default, which was not allowed at registration")]
+    DefaultFrozenNotAllowed,
+    #[msg("Mint has a permanent delegate that was not allowed at registration")]
+    PermanentDelegateNotAllowed,
+    #[msg("Wrapped asset may only be burned toward its home chain")]
+    NotHomeChain,
+    #[msg("Token must be delisted first")]
+    TokenNotDelisted,
+    #[msg("Token still has locked funds awaiting release")]
+    TokenStillLocked,
+    #[msg("Referral fees are still unclaimed")]
+    ReferralFeesOutstanding,
+    #[msg("Only wrapped SOL can be bridged as native SOL")]
+    NotNativeMint,
+    #[msg("Payout would leave the recipient below the rent-exempt minimum")]
+    NativeAmountBelowRent,
+    #[msg("Recipient cannot receive lamports")]
+    InvalidRecipient,
+    #[msg("Payload is empty or longer than the configured maximum")]
+    PayloadTooLarge,
+    #[msg("Transfers with a call cannot be queued; amount is above the large-release threshold")]
+    CallNotQueueable,
+    #[msg("Release batch is empty, too large or does not match its accounts")]
+    InvalidReleaseBatch,
+    #[msg("Lock batch is empty, too large or does not match its accounts")]
+    InvalidLockBatch,
+    #[msg("Token account has not approved the bridge's lock delegate")]
+    DelegateNotApproved,
+    #[msg("Delegated amount does not cover the lock")]
+    DelegatedAmountTooLow,
+    #[msg("Lock order has expired")]
+    OrderExpired,
+    #[msg("Lock order is not signed by its owner")]
+    OrderNotAuthorized,
+    #[msg("Pending lock account does not match the lock nonce")]
+    PendingLockMismatch,
+    #[msg("Lock has already been refunded")]
+    LockAlreadyRefunded,
+    #[msg("Lock cannot be refunded yet")]
+    RefundNotYetAvailable,
+    #[msg("Transfer deadline has passed")]
+    TransferExpired,
+    #[msg("Memo is empty or longer than the maximum")]
+    InvalidMemo,
+    #[msg("A memo is being written; pass the memo program")]
+    MemoProgramRequired,
+    #[msg("Lock has been cancelled")]
+    LockIsCancelled,
+    #[msg("Lock has not been cancelled")]
+    LockNotCancelled,
+    #[msg("Guardians have already attested this lock")]
+    AlreadyAttested,
+    #[msg("Optimistic releases are disabled")]
+    OptimisticReleaseDisabled,
+    #[msg("Amount is too large for an optimistic release")]
+    OptimisticAmountTooLarge,
+    #[msg("Optimistic release was challenged")]
+    OptimisticReleaseChallenged,
+    #[msg("Challenge window is still open")]
+    ChallengeWindowOpen,
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("Merkle proof does not lead to the attestation root")]
+    InvalidMerkleProof,
+    #[msg("Pass the attestation root the proof leads to")]
+    AttestationRootRequired,
+    #[msg("Pass the guardian set that signed the attestation root")]
+    WrongGuardianSet,
+    #[msg("Not a valid posted VAA for this transfer")]
+    InvalidVaa,
+    #[msg("VAA emitter is not the one registered for its chain")]
+    UnknownEmitter,
+    #[msg("Transfers redeemed from a VAA cannot be queued; amount is above the large-release threshold")]
+    VaaNotQueueable,
+    #[msg("Relayer is already registered")]
+    DuplicateRelayer,
+    #[msg("Relayer is not registered")]
+    RelayerNotFound,
+    #[msg("Relayer registry is full")]
+    RelayerRegistryFull,
+    #[msg("Deliveries from this route are limited to registered relayers")]
+    RelayerNotAuthorized,
+    #[msg("Transfer nonce is not the next one this route expects")]
+    OutOfOrderDelivery,
+    #[msg("Route does not enforce ordering")]
+    RouteNotOrdered,
+    #[msg("Mint is not an NFT: supply must be 1 with 0 decimals")]
+    NotAnNft,
+    #[msg("Programmable NFTs cannot be bridged yet")]
+    ProgrammableNftUnsupported,
+    #[msg("Wrapped NFT is already in circulation")]
+    NftAlreadyMinted,
+    #[msg("Vault holds too little free liquidity for this withdrawal")]
+    InsufficientLiquidity,
+    #[msg("Token account holds a different mint than the one delivered")]
+    RecipientMintMismatch,
+    #[msg("Nonce, counter or fee arithmetic overflowed")]
+    CounterOverflow,
+    #[msg("Vault holds less than the release amount")]
+    InsufficientVaultBalance,
+    #[msg("Target is not a 20-byte, non-zero EVM address")]
+    InvalidEvmAddress,
+    #[msg("Target is not a 32-byte, non-zero Solana address")]
+    InvalidSolanaAddress,
+    #[msg("Target is not a bech32 address with the route's prefix")]
+    InvalidBech32Address,
+    #[msg("Target chain is the chain this program runs on")]
+    CannotBridgeToSelf,
+    #[msg("Target chain id is all zeroes")]
+    InvalidTargetChain,
+    #[msg("Token account is not owned by the expected wallet")]
+    TokenAccountOwnerMismatch,
+    #[msg("Account is not the configured staking treasury")]
+    TreasuryMismatch,
+    #[msg("Account is not the one owed this rent")]
+    RentRecipientMismatch,
+    #[msg("Account is not the queued withdrawal's destination")]
+    WithdrawDestinationMismatch,
+    #[msg("Account is not the relayer that claimed this release")]
+    RelayerMismatch,
+    #[msg("Account is not the instructions sysvar")]
+    InvalidInstructionsSysvar,
+    #[msg("Account is not the SPL Memo program")]
+    InvalidMemoProgram,
+    #[msg("Account is not the SPL Token program")]
+    InvalidTokenProgram,
+    #[msg("Vault holds less than the deposits locked in it")]
+    VaultInvariantViolated,
+    #[msg("Address is on the bridge blocklist")]
+    AddressBlocked,
+    #[msg("Address is not on the bridge blocklist")]
+    AddressNotBlocked,
+    #[msg("Signer is not the compliance authority")]
+    NotComplianceAuthority,
+    #[msg("Wrapped mint is not its own mint authority or has a freeze authority")]
+    InvalidWrappedMintAuthority,
+    #[msg("Wrapped mint authorities are already as created")]
+    WrappedAuthorityIntact,
+    #[msg("Outflow would not exceed the circuit-breaker limit")]
+    CircuitBreakerNotExceeded,
+    #[msg("Release is past its veto window")]
+    VetoWindowClosed,
+    #[msg("Release exceeds the amount recorded as locked")]
+    LockedBalanceUnderflow,
+}
+
+/// Protocol fee on `amount`, rounded up so truncation never favours the sender.
+fn protocol_fee(amount: u64, fee_bps: u16) -> u64 {
+    (amount as u128 * fee_bps as u128).div_ceil(BPS_DENOMINATOR as u128) as u64
+}
+
+/// LP shares a deposit of `assets` buys from a pool of `lp_assets` backing
+/// `supply` shares, rounded down.
+fn lp_shares_for(assets: u64, lp_assets: u64, supply: u64) -> Result<u64> {
+    let shares = assets as u128 * (supply as u128 + LP_VIRTUAL_SHARES as u128)
+        / (lp_assets as u128 + LP_VIRTUAL_ASSETS as u128);
+    u64::try_from(shares).map_err(|_| error!(BridgeError::InvalidAmount))
+}
+
+/// Assets `shares` redeem for from the same pool, rounded down.
+fn lp_assets_for(shares: u64, lp_assets: u64, supply: u64) -> Result<u64> {
+    let assets = shares as u128 * (lp_assets as u128 + LP_VIRTUAL_ASSETS as u128)
+        / (supply as u128 + LP_VIRTUAL_SHARES as u128);
+    Ok((assets as u64).min(lp_assets))
+}
+
+/// Counts a lock against the user's per-epoch allowance. With rate limiting
+/// off the activity account is not needed and is left untouched.
+fn record_user_activity(
+    activity: Option<&mut Account<UserActivity>>,
+    bump: Option<u8>,
+    config: &BridgeConfig,
+    user: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    epoch: u64,
+) -> Result<()> {
+    if !config.rate_limited() {
+        return Ok(());
+    }
+    let activity = activity.ok_or(BridgeError::UserActivityRequired)?;
+    if activity.epoch != epoch || activity.user != user {
+        activity.user = user;
+        activity.mint = mint;
+        activity.epoch = epoch;
+        activity.lock_count = 0;
+        activity.volume = 0;
+        activity.bump = bump.ok_or(BridgeError::UserActivityRequired)?;
+    }
+
+    activity.lock_count = activity.lock_count.saturating_add(1);
+    activity.volume = activity.volume.saturating_add(amount);
+    require!(
+        config.max_locks_per_epoch == 0 || activity.lock_count <= config.max_locks_per_epoch,
+        BridgeError::RateLimited
+    );
+    require!(
+        config.max_volume_per_epoch == 0 || activity.volume <= config.max_volume_per_epoch,
+        BridgeError::RateLimited
+    );
+    Ok(())
+}
+
+/// Credits `referrer` its share of `fee`, rounded down so it never exceeds the
+/// fee. The tokens stay in the fee vault until `claim_referral_fees`.
+fn accrue_referral(
+    registry: &mut TokenRegistry,
+    referrer_account: Option<&mut Account<ReferrerAccount>>,
+    referrer: Option<Pubkey>,
+    fee: u64,
+    share_bps: u16,
+) -> Result<u64> {
+    let Some(referrer) = referrer else {
+        return Ok(0);
+    };
+    let share = (fee as u128 * share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+    if share == 0 {
+        return Ok(0);
+    }
+    let account = referrer_account.ok_or(BridgeError::ReferrerMismatch)?;
+    require_keys_eq!(account.referrer, referrer, BridgeError::ReferrerMismatch);
+
+    account.claimable = account.claimable.saturating_add(share);
+    registry.referral_liability = registry.referral_liability.saturating_add(share);
+    Ok(share)
+}
+
+/// Sends `lamports` from the relayer to the recipient wallet, funded or not.
+fn drop_native<'info>(
+    relayer: &Signer<'info>,
+    recipient: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    lamports: u64,
+) -> Result<()> {
+    if lamports == 0 {
+        return Ok(());
+    }
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: relayer.to_account_info(),
+                to: recipient.to_account_info(),
+            },
+        ),
+        lamports,
+    )
+}
+
+/// The accounts `lock_tokens` works on, borrowed from `Lock`,
+/// `LockDelegated` or `LockWithAuthorization`. `source` is whose tokens move and who any refund goes
+/// back to; `authority` signs the transfers, under `signer_seeds` when it is
+/// a delegate PDA rather than the source itself.
+struct LockAccounts<'a, 'info> {
+    payer: &'a Signer<'info>,
+    source: Pubkey,
+    operator: Option<Pubkey>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &'a [&'a [&'a [u8]]],
+    blocked_address: &'a UncheckedAccount<'info>,
+    user_token_account: &'a InterfaceAccount<'info, TokenAccount>,
+    token_mint: &'a InterfaceAccount<'info, Mint>,
+    token_registry: &'a mut Account<'info, TokenRegistry>,
+    vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    fee_vault: &'a InterfaceAccount<'info, TokenAccount>,
+    insurance_vault: &'a InterfaceAccount<'info, TokenAccount>,
+    exempt: bool,
+    referrer_account: Option<&'a mut Account<'info, ReferrerAccount>>,
+    user_activity: Option<&'a mut Account<'info, UserActivity>>,
+    user_activity_bump: Option<u8>,
+    chain_route: &'a UncheckedAccount<'info>,
+    destination_token: &'a UncheckedAccount<'info>,
+    pending_lock: &'a UncheckedAccount<'info>,
+    lock_sequence: &'a mut Account<'info, TransferSequence>,
+    lock_sequence_bump: u8,
+    config: &'a Account<'info, BridgeConfig>,
+    bridge_state: &'a AccountLoader<'info, BridgeState>,
+    token_program: &'a Interface<'info, TokenInterface>,
+    system_program: &'a Program<'info, System>,
+    memo_program: Option<&'a UncheckedAccount<'info>>,
+    hook_accounts: &'a [AccountInfo<'info>],
+}
+
+/// Body of `lock`, `lock_with_payload`, `batch_lock`, `lock_delegated` and
+/// `lock_with_authorization`: checks, charges fees and moves the tokens into
+/// the vault, returning the `Locked` event for the caller to emit.
+fn lock_tokens(
+    accounts: LockAccounts,
+    amount: u64,
+    target_chain: [u8; 32],
+    target_addr: Vec<u8>,
+    options: LockOptions,
+) -> Result<Locked> {
+    let LockOptions {
+        relayer_fee,
+        native_drop,
+        referrer,
+        on_behalf_of,
+        deadline_unix,
+        delivery_memo,
+        memo,
+        tier,
+    } = options;
+    require!(!accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+    BlockedAddress::check(accounts.blocked_address)?;
+    require!(!accounts.token_registry.paused, BridgeError::TokenPaused);
+    require!(!accounts.token_registry.delisted, BridgeError::TokenDelisted);
+    require!(amount > 0, BridgeError::InvalidAmount);
+    require!(
+        target_addr.len() <= accounts.config.max_target_addr_len as usize,
+        BridgeError::InvalidTargetAddress
+    );
+    accounts.config.check_amount(amount)?;
+    accounts.token_registry.check_amount(amount)?;
+    check_deadline(deadline_unix)?;
+    check_memo(&delivery_memo)?;
+    accounts.config.check_memo(&memo)?;
+
+    check_target_chain(&target_chain)?;
+    let route = ChainRoute::load(accounts.chain_route)?;
+    route.validate_target(&target_addr)?;
+    let destination_token = DestinationToken::load(accounts.destination_token)?;
+
+    let clock = Clock::get()?;
+    record_user_activity(
+        accounts.user_activity,
+        accounts.user_activity_bump,
+        accounts.config,
+        accounts.source,
+        accounts.token_registry.mint,
+        amount,
+        clock.epoch,
+    )?;
+
+    let exempt = accounts.exempt;
+    let quote = accounts.token_registry.quote_fee(accounts.config, amount, exempt)?;
+    let priority_fee = accounts.config.priority_fee(tier, amount);
+    let fee = quote.fee.checked_add(priority_fee).ok_or(BridgeError::CounterOverflow)?;
+    let vault_amount = quote.net
+        .checked_sub(priority_fee)
+        .filter(|net| *net > 0)
+        .ok_or(BridgeError::InvalidAmount)?;
+    // A Token-2022 transfer fee is withheld from what the vault receives,
+    // and only what it receives may be released on the other side
+    let transfer_fee = token_transfer_fee(accounts.token_mint, vault_amount)?;
+    let net_amount = vault_amount - transfer_fee;
+    require!(net_amount > 0, BridgeError::InvalidAmount);
+    require!(relayer_fee < net_amount, BridgeError::InvalidRelayerFee);
+    require!(
+        native_drop <= accounts.config.max_native_drop,
+        BridgeError::NativeDropTooLarge
+    );
+    let (treasury_fee, insurance_fee) = accounts.config.split_fee(fee);
+    let referral_fee = accrue_referral(
+        accounts.token_registry,
+        accounts.referrer_account,
+        referrer,
+        treasury_fee,
+        accounts.config.referral_share_bps,
+    )?;
+
+    write_memo(accounts.memo_program, &memo)?;
+
+    // Transfer tokens to bridge vault; `hook_accounts` are whatever the
+    // mint's transfer hook needs
+    for (to, value) in [
+        (accounts.vault.to_account_info(), vault_amount),
+        (accounts.fee_vault.to_account_info(), treasury_fee),
+        (accounts.insurance_vault.to_account_info(), insurance_fee),
+    ] {
+        if value == 0 {
+            continue;
+        }
+        transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: accounts.user_token_account.to_account_info(),
+                    mint: accounts.token_mint.to_account_info(),
+                    to,
+                    authority: accounts.authority.clone(),
+                },
+                accounts.signer_seeds,
+            )
+            .with_remaining_accounts(accounts.hook_accounts.to_vec()),
+            value,
+            accounts.token_mint.decimals,
+        )?;
+    }
+
+    accounts.token_registry.add_locked(net_amount)?;
+    accounts.token_registry.check_vault(accounts.vault)?;
+
+    let mut bridge_state = accounts.bridge_state.load_mut()?;
+    let sequence = accounts.lock_sequence.advance(
+        accounts.token_mint.key(),
+        target_chain,
+        accounts.lock_sequence_bump,
+        bridge_state.nonce,
+        &clock,
+    )?;
+    bridge_state.next_nonce()?;
+    record_pending_lock(
+        accounts.payer,
+        accounts.pending_lock,
+        accounts.system_program,
+        accounts.config,
+        bridge_state.nonce,
+        accounts.source,
+        accounts.token_mint.key(),
+        net_amount,
+        deadline_unix,
+        clock.slot,
+    )?;
+
+    Ok(Locked {
+        transfer_id: transfer_id(
+            &LOCAL_CHAIN_ID,
+            &target_chain,
+            accounts.source.as_ref(),
+            &target_addr,
+            accounts.token_mint.key().as_ref(),
+            net_amount,
+            bridge_state.nonce,
+        ),
+        source: accounts.source,
+        on_behalf_of,
+        operator: accounts.operator,
+        token: accounts.token_mint.key(),
+        decimals: accounts.token_mint.decimals,
+        amount: net_amount,
+        gross_amount: amount,
+        fee,
+        net_amount,
+        transfer_fee,
+        insurance_fee,
+        priority_fee,
+        exempt,
+        relayer_fee,
+        native_drop,
+        referrer,
+        referral_fee,
+        self_referral: referrer == Some(accounts.source),
+        target_chain,
+        target_addr,
+        destination_token,
+        nonce: bridge_state.nonce,
+        sequence,
+        deadline_unix,
+        delivery_memo,
+        memo,
+        tier,
+        batch_id: None,
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp,
+        vault_balance: accounts.vault.amount,
+    })
+}
+
+/// Invokes `on_bridge_receive` on `program` with the escrow, its authority
+/// and the mint first, followed by `extra` accounts for the callee. Nothing
+/// is signed by the bridge.
+fn call_bridge_receiver<'info>(
+    program: &UncheckedAccount<'info>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    escrow_authority: &UncheckedAccount<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    extra: &[AccountInfo<'info>],
+    args: BridgeReceiveArgs,
+) -> Result<()> {
+    let sighash = anchor_lang::solana_program::hash::hash(b"global:on_bridge_receive");
+    let mut data = sighash.to_bytes()[..8].to_vec();
+    args.serialize(&mut data)?;
+
+    let mut accounts = vec![
+        AccountMeta::new(escrow.key(), false),
+        AccountMeta::new_readonly(escrow_authority.key(), false),
+        AccountMeta::new_readonly(mint.key(), false),
+    ];
+    let mut infos = vec![
+        escrow.to_account_info(),
+        escrow_authority.to_account_info(),
+        mint.to_account_info(),
+    ];
+    for info in extra {
+        accounts.push(if info.is_writable {
+            AccountMeta::new(*info.key, info.is_signer)
+        } else {
+            AccountMeta::new_readonly(*info.key, info.is_signer)
+        });
+        infos.push(info.clone());
+    }
+    infos.push(program.to_account_info());
+
+    invoke(
+        &Instruction {
+            program_id: program.key(),
+            accounts,
+            data,
+        },
+        &infos,
+    )?;
+    Ok(())
+}
+
+/// Admits transfer `nonce` from the chain behind `source_route`, delivered
+/// by `relayer`, enforcing the recipient's blocklist entry, the route's
+/// relayer allowlist and its strict ordering. Only `post_attestation` passes
+/// no entry, so that a blocked recipient's transfer can still be posted and
+/// then redirected by the admin.
+///
+/// Every delivery path calls it once, where the transfer first takes effect:
+/// paid or minted, queued, posted with `post_attestation`, or finalized
+/// optimistically. `execute_release`, `execute_mint` and `batch_release` run
+/// attestations that were admitted when they were posted or queued, and
+/// check only the allowlist.
+fn accept_delivery(
+    source_route: &AccountInfo,
+    relayer_registry: Option<&Account<RelayerRegistry>>,
+    relayer: &Pubkey,
+    blocked_recipient: Option<&AccountInfo>,
+    nonce: u64,
+) -> Result<()> {
+    if let Some(blocked_recipient) = blocked_recipient {
+        BlockedAddress::check(blocked_recipient)?;
+    }
+    RelayerRegistry::check_delivery(relayer_registry, source_route, relayer)?;
+    ChainRoute::take_nonce(source_route, nonce)
+}
+
+/// Everything `release` and `release_with_proof` do once the message is
+/// known to be attested: queueing, replay marker, limits and the payout.
+fn deliver_release<'info>(
+    ctx: Context<'_, '_, '_, 'info, Release<'info>>,
+    message: TransferMessage,
+    guardian_set_index: u32,
+) -> Result<()> {
+    let (amount, source_chain, source_tx) = (message.amount, message.source_chain, message.source_tx);
+    message.check_deadline()?;
+    check_memo(&message.memo)?;
+
+    let clock = Clock::get()?;
+    let slot = clock.slot;
+    if ctx.accounts.config.is_large_release(amount) {
+        accept_delivery(
+            &ctx.accounts.source_route,
+            ctx.accounts.relayer_registry.as_ref(),
+            ctx.accounts.payer.key,
+            Some(&ctx.accounts.blocked_address),
+            message.nonce,
+        )?;
+        return queue_release(
+            &ctx.accounts.payer,
+            &ctx.accounts.queued_release,
+            &ctx.accounts.system_program,
+            ctx.bumps.queued_release,
+            &message,
+            guardian_set_index,
+            slot,
+            ctx.accounts.config.execution_delay(amount),
+        );
+    }
+    // Checked ahead of any state change so an underfunded vault fails with
+    // its own error and the same transfer can be delivered once refilled
+    require!(ctx.accounts.vault.amount >= amount, BridgeError::InsufficientVaultBalance);
+    write_memo(ctx.accounts.memo_program.as_ref(), &message.memo)?;
+    let recipient_account_created = create_or_check_recipient_ata(
+        &ctx.accounts.payer,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.user,
+        &ctx.accounts.token_mint,
+        &ctx.accounts.token_program,
+        &ctx.accounts.associated_token_program,
+        &ctx.accounts.system_program,
+    )?;
+    check_not_frozen(
+        &[&ctx.accounts.vault, &ctx.accounts.fee_vault],
+        &[],
+        source_chain,
+        source_tx,
+        slot,
+    )?;
+    check_recipient_not_frozen(&ctx.accounts.user_token_account, source_chain, source_tx, slot)?;
+    if ctx.accounts.token_registry.record_outflow(amount, slot).is_some() {
+        return err!(BridgeError::CircuitBreakerTripped);
+    }
+    accept_delivery(
+        &ctx.accounts.source_route,
+        ctx.accounts.relayer_registry.as_ref(),
+        ctx.accounts.payer.key,
+        Some(&ctx.accounts.blocked_address),
+        message.nonce,
+    )?;
+    ctx.accounts.token_registry.record_daily_outflow(amount, slot)?;
+    ctx.accounts.token_registry.sub_locked(amount)?;
+    
+    let fee_bps = ctx.accounts.token_registry.outflow_fee_bps(amount, ctx.accounts.vault.amount);
+    let fee = protocol_fee(amount, fee_bps);
+    let treasury_fee = ctx.accounts.token_registry.retain_lp_fee(fee);
+    let relayer_fee = message.relayer_fee_for(ctx.accounts.payer.key).min(amount - fee);
+    let payout = amount - fee - relayer_fee;
+
+    // Transfer from vault to user
+    let mint = ctx.accounts.token_mint.key();
+    let seeds: &[&[u8]] = &[b"vault", mint.as_ref(), &[ctx.bumps.vault]];
+    let signer = &[seeds];
+    
+    let decimals = ctx.accounts.token_mint.decimals;
+    let hook_accounts = ctx.remaining_accounts.to_vec();
+    transfer_checked_with_hook(
+        ctx.accounts
+            .transfer_context()
+            .with_signer(signer)
+            .with_remaining_accounts(hook_accounts.clone()),
+        payout,
+        decimals
+    )?;
+    pay_out_release(
+        &ctx.accounts.token_program,
+        &ctx.accounts.token_mint,
+        &ctx.accounts.vault,
+        seeds,
+        &hook_accounts,
+        &[(&ctx.accounts.relayer_token_account, relayer_fee)],
+    )?;
+    if treasury_fee > 0 {
+        transfer_checked_with_hook(
+            ctx.accounts
+                .fee_transfer_context()
+                .with_signer(signer)
+                .with_remaining_accounts(hook_accounts),
+            treasury_fee,
+            decimals,
+        )?;
+    }
+    let native_drop = message
+        .native_drop_for(ctx.accounts.payer.key)
+        .min(ctx.accounts.config.max_native_drop);
+    drop_native(&ctx.accounts.payer, &ctx.accounts.user, &ctx.accounts.system_program, native_drop)?;
+    // Marked processed only once the payout went through
+    record_processed(
+        &ctx.accounts.payer,
+        &ctx.accounts.processed_record,
+        &ctx.accounts.system_program,
+        ctx.bumps.processed_record,
+        source_chain,
+        source_tx,
+        guardian_set_index,
+        slot,
+    )?;
+
+    ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+    emit!(Released {
+        transfer_id: message.transfer_id(&ctx.accounts.token_mint.key().to_bytes()),
+        recipient: *ctx.accounts.user.key,
+        mint: ctx.accounts.token_mint.key(),
+        amount,
+        fee,
+        transfer_fee: token_transfer_fee(&ctx.accounts.token_mint, payout)?,
+        relayer_fee,
+        native_drop,
+        source_tx,
+        source_chain,
+        memo: message.memo,
+        tier: message.tier,
+        recipient_account_created,
+        slot,
+        unix_timestamp: clock.unix_timestamp,
+        vault_balance: ctx.accounts.vault.amount,
+    });
+    
+    Ok(())
+}
+
+/// Pays each `(account, amount)` leg out of `vault`, skipping empty ones.
+/// `hook_accounts` are passed on to the mint's transfer hook, if it has one.
+fn pay_out_release<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    vault_seeds: &[&[u8]],
+    hook_accounts: &[AccountInfo<'info>],
+    legs: &[(&InterfaceAccount<'info, TokenAccount>, u64)],
+) -> Result<()> {
+    for &(to, value) in legs {
+        if value == 0 {
+            continue;
+        }
+        transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: vault.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: vault.to_account_info(),
+                },
+                &[vault_seeds],
+            )
+            .with_remaining_accounts(hook_accounts.to_vec()),
+            value,
+            mint.decimals,
+        )?;
+    }
+    Ok(())
+}
+
+/// `token_interface::transfer_checked`, except that the context's remaining
+/// accounts are searched for the extra accounts a transfer-hook mint declares
+/// and appended to the CPI. Behaves the same for mints without a hook.
+fn transfer_checked_with_hook<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Moves everything left in a self-owned token account to `to` and closes it,
+/// returning the amount swept.
+fn sweep_and_close_token_account<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    account: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    rent_collector: &UncheckedAccount<'info>,
+    seeds: &[&[u8]],
+) -> Result<u64> {
+    let amount = account.amount;
+    if amount > 0 {
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: account.to_account_info(),
+                },
+                &[seeds],
+            ),
+            amount,
+            mint.decimals,
+        )?;
+    }
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: account.to_account_info(),
+            destination: rent_collector.to_account_info(),
+            authority: account.to_account_info(),
+        },
+        &[seeds],
+    ))?;
+    Ok(amount)
+}
+
+/// Creates a token account at a program PDA that is its own authority, so
+/// the program can later move funds out by signing with `seeds`.
+fn create_self_owned_token_account<'info>(
+    payer: &Signer<'info>,
+    account: &UncheckedAccount<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    // Token-2022 mints may require account extensions, so ask for the size
+    let space = token_interface::get_account_data_size(
+        CpiContext::new(
+            token_program.to_account_info(),
+            GetAccountDataSize {
+                mint: mint.to_account_info(),
+            },
+        ),
+        &[],
+    )?;
+    create_pda_account(
+        payer,
+        account,
+        system_program,
+        space as usize,
+        token_program.key,
+        seeds,
+    )?;
+
+    token_interface::initialize_account3(CpiContext::new(
+        token_program.to_account_info(),
+        InitializeAccount3 {
+            account: account.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: account.to_account_info(),
+        },
+    ))
+}
+
+/// Metadata of a wrapped mint, with the origin chain and token appended to
+/// `uri` as hex query parameters.
+fn wrapped_metadata(
+    source_chain: &[u8; 32],
+    source_token: &[u8; 32],
+    name: String,
+    symbol: String,
+    uri: &str,
+) -> Result<DataV2> {
+    let uri = format!("{}?chain={}&token={}", uri, to_hex(source_chain), to_hex(source_token));
+    require!(
+        name.len() <= mpl_token_metadata::MAX_NAME_LENGTH
+            && symbol.len() <= mpl_token_metadata::MAX_SYMBOL_LENGTH
+            && uri.len() <= mpl_token_metadata::MAX_URI_LENGTH,
+        BridgeError::MetadataTooLong
+    );
+
+    Ok(DataV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    })
+}
+
+/// Fails with `VaultFrozen` or `RecipientAccountFrozen` before any CPI when
+/// a payout account is frozen, logging `PayoutFrozen` so monitoring can see
+/// the failed transaction.
+fn check_not_frozen<'a, 'info>(
+    vaults: &[&'a InterfaceAccount<'info, TokenAccount>],
+    recipients: &[&'a InterfaceAccount<'info, TokenAccount>],
+    source_chain: [u8; 32],
+    source_tx: [u8; 32],
+    slot: u64,
+) -> Result<()> {
+    let frozen = vaults
+        .iter()
+        .map(|account| (account, true))
+        .chain(recipients.iter().map(|account| (account, false)))
+        .find(|(account, _)| account.is_frozen());
+    let Some((account, is_vault)) = frozen else {
+        return Ok(());
+    };
+
+    emit!(PayoutFrozen {
+        mint: account.mint,
+        frozen_account: account.key(),
+        is_vault,
+        source_chain,
+        source_tx,
+        slot,
+    });
+    if is_vault {
+        err!(BridgeError::VaultFrozen)
+    } else {
+        err!(BridgeError::RecipientAccountFrozen)
+    }
+}
+
+/// Creates `recipient`'s associated token account for `mint` when it does not
+/// exist yet, at the payer's expense, and returns whether it did. The
+/// caller's address constraint pins `token_account` to the derived address;
+/// an existing account there must still belong to the recipient, since a
+/// classic-token ATA can have its owner reassigned.
+fn create_or_check_recipient_ata<'info>(
+    payer: &Signer<'info>,
+    token_account: &UncheckedAccount<'info>,
+    recipient: &UncheckedAccount<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    associated_token_program: &Program<'info, AssociatedToken>,
+    system_program: &Program<'info, System>,
+) -> Result<bool> {
+    if !token_account.data_is_empty() {
+        let existing = TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..])?;
+        require_keys_eq!(existing.owner, recipient.key(), BridgeError::RecipientMismatch);
+        require_keys_eq!(existing.mint, mint.key(), BridgeError::RecipientMintMismatch);
+        return Ok(false);
+    }
+
+    associated_token::create(CpiContext::new(
+        associated_token_program.to_account_info(),
+        Create {
+            payer: payer.to_account_info(),
+            associated_token: token_account.to_account_info(),
+            authority: recipient.to_account_info(),
+            mint: mint.to_account_info(),
+            system_program: system_program.to_account_info(),
+            token_program: token_program.to_account_info(),
+        },
+    ))?;
+    Ok(true)
+}
+
+/// `check_not_frozen` for a recipient account the handler created or
+/// checked itself rather than having Anchor deserialize it.
+fn check_recipient_not_frozen(
+    token_account: &UncheckedAccount,
+    source_chain: [u8; 32],
+    source_tx: [u8; 32],
+    slot: u64,
+) -> Result<()> {
+    let state = TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..])?;
+    if !state.is_frozen() {
+        return Ok(());
+    }
+
+    emit!(PayoutFrozen {
+        mint: state.mint,
+        frozen_account: token_account.key(),
+        is_vault: false,
+        source_chain,
+        source_tx,
+        slot,
+    });
+    err!(BridgeError::RecipientAccountFrozen)
+}
+
+/// Part of `amount` a Token-2022 transfer-fee mint withholds from the
+/// receiving account this epoch; 0 for mints without the extension.
+fn token_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    if info.owner != &spl_token_2022::ID {
+        return Ok(0);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+    config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or_else(|| error!(BridgeError::InvalidAmount))
+}
+
+/// Rejects Token-2022 extensions that can make a vault unable to pay out,
+/// unless `allowed` carries the matching `ALLOW_*` flag. Non-transferable
+/// mints can never be bridged.
+fn check_mint_extensions(mint: &InterfaceAccount<Mint>, allowed: u8) -> Result<()> {
+    let info = mint.to_account_info();
+    if info.owner != &spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        match extension {
+            ExtensionType::NonTransferable => return err!(BridgeError::NonTransferableMint),
+            ExtensionType::TransferHook => {
+                require!(
+                    transfer_hook::get_program_id(&state).is_none()
+                        || allowed & ALLOW_TRANSFER_HOOK != 0,
+                    BridgeError::TransferHookNotAllowed
+                );
+            }
+            ExtensionType::DefaultAccountState => {
+                let frozen = state.get_extension::<DefaultAccountState>()?.state
+                    == spl_token_2022::state::AccountState::Frozen as u8;
+                require!(
+                    !frozen || allowed & ALLOW_DEFAULT_FROZEN != 0,
+                    BridgeError::DefaultFrozenNotAllowed
+                );
+            }
+            ExtensionType::PermanentDelegate => {
+                let delegate: Option<Pubkey> =
+                    state.get_extension::<PermanentDelegate>()?.delegate.into();
+                require!(
+                    delegate.is_none() || allowed & ALLOW_PERMANENT_DELEGATE != 0,
+                    BridgeError::PermanentDelegateNotAllowed
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether `mint` is its own mint authority, which is how every wrapped mint
+/// this program creates is set up.
+fn is_bridge_wrapped(mint: &InterfaceAccount<Mint>) -> bool {
+    mint.mint_authority == COption::Some(mint.key())
+}
+
+/// Whether a wrapped mint is still set up the way `create_wrapped` leaves
+/// it: its own mint authority and no freeze authority.
+fn has_wrapped_authorities(mint: &InterfaceAccount<Mint>) -> bool {
+    is_bridge_wrapped(mint) && mint.freeze_authority.is_none()
+}
+
+/// Lowercase hex of `bytes`, without a prefix.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Allocates a program-derived account, funding it rent-exempt from `payer`.
+fn create_pda_account<'info>(
+    payer: &Signer<'info>,
+    target: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    owner: &Pubkey,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            CreateAccount {
+                from: payer.to_account_info(),
+                to: target.to_account_info(),
+            },
+            &[seeds],
+        ),
+        lamports,
+        space as u64,
+        owner,
+    )
+}
+
+/// Memos arrive as borsh strings, so they are valid UTF-8 by construction;
+/// only the length is left to check.
+fn check_memo(memo: &Option<String>) -> Result<()> {
+    if let Some(memo) = memo {
+        require!(
+            !memo.is_empty() && memo.len() <= MAX_MEMO_LEN,
+            BridgeError::InvalidMemo
+        );
+    }
+    Ok(())
+}
+
+/// Refuses chain ids no relayer could ever deliver to, ahead of the route
+/// lookup so they fail with their own error rather than `UnsupportedChain`.
+fn check_target_chain(target_chain: &[u8; 32]) -> Result<()> {
+    require!(*target_chain != [0u8; 32], BridgeError::InvalidTargetChain);
+    require!(*target_chain != LOCAL_CHAIN_ID, BridgeError::CannotBridgeToSelf);
+    Ok(())
+}
+
+/// Lowercase printable ASCII, as bech32 requires of a human-readable part.
+fn is_bech32_hrp(hrp: &str) -> bool {
+    !hrp.is_empty()
+        && hrp.len() <= MAX_BECH32_HRP_LEN
+        && hrp.bytes().all(|c| (33..=126).contains(&c) && !c.is_ascii_uppercase())
+}
+
+/// Whether `addr` is `hrp`, the `1` separator and a lowercase data part
+/// whose bech32 checksum holds, in at most `MAX_BECH32_LEN` characters.
+/// Bech32m addresses do not pass.
+fn is_bech32_address(hrp: &str, addr: &[u8]) -> bool {
+    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    if addr.len() > MAX_BECH32_LEN {
+        return false;
+    }
+    let Some(data) = addr
+        .strip_prefix(hrp.as_bytes())
+        .and_then(|rest| rest.strip_prefix(b"1"))
+    else {
+        return false;
+    };
+    if data.len() < BECH32_CHECKSUM_LEN {
+        return false;
+    }
+    let Some(values) = data
+        .iter()
+        .map(|c| CHARSET.iter().position(|x| x == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+    else {
+        return false;
+    };
+
+    let hrp = hrp.as_bytes();
+    let expanded = hrp
+        .iter()
+        .map(|c| c >> 5)
+        .chain([0])
+        .chain(hrp.iter().map(|c| c & 31))
+        .chain(values);
+    let polymod = expanded.fold(1u32, |chk, value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x1ff_ffff) << 5) ^ value as u32;
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(chk, |chk, (_, g)| chk ^ g)
+    });
+    polymod == 1
+}
+
+/// Writes `memo`, if any, through the SPL Memo program.
+fn write_memo(memo_program: Option<&UncheckedAccount>, memo: &Option<String>) -> Result<()> {
+    let Some(memo) = memo else {
+        return Ok(());
+    };
+    let program = memo_program.ok_or(BridgeError::MemoProgramRequired)?;
+    invoke(
+        &Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: Vec::new(),
+            data: memo.as_bytes().to_vec(),
+        },
+        &[program.to_account_info()],
+    )
+    .map_err(Into::into)
+}
+
+/// Fails with `TransferExpired` once `deadline_unix` has passed; 0 never expires.
+fn check_deadline(deadline_unix: i64) -> Result<()> {
+    require!(
+        deadline_unix == 0 || Clock::get()?.unix_timestamp <= deadline_unix,
+        BridgeError::TransferExpired
+    );
+    Ok(())
 }
 
-#[event]
-pub struct WrappedBurned {
-    pub source: Pubkey,
-    pub wrapped_mint: Pubkey,
-    pub amount: u64,
-    pub target_chain: [u8; 32],
-    pub target_addr: Vec<u8>,
-    pub nonce: u64,
+/// Pays a pending lock back to its source and marks it refunded; the caller
+/// has checked that the lock is due, and `RefundLock` that it is unattested.
+fn refund_pending_lock<'info>(
+    ctx: &mut Context<'_, '_, '_, 'info, RefundLock<'info>>,
+    nonce: u64,
+) -> Result<()> {
+    require!(!ctx.accounts.bridge_state.load()?.is_paused(), BridgeError::BridgePaused);
+
+    let pending = &mut ctx.accounts.pending_lock;
+    require!(!pending.refunded, BridgeError::LockAlreadyRefunded);
+    pending.refunded = true;
+    let amount = pending.amount;
+    let mint = pending.mint;
+
+    ctx.accounts.token_registry.sub_locked(amount)?;
+    let seeds: &[&[u8]] = &[b"vault", mint.as_ref(), &[ctx.bumps.vault]];
+    pay_out_release(
+        &ctx.accounts.token_program,
+        &ctx.accounts.token_mint,
+        &ctx.accounts.vault,
+        seeds,
+        ctx.remaining_accounts,
+        &[(&ctx.accounts.source_token_account, amount)],
+    )?;
+
+    ctx.accounts.token_registry.check_vault(&mut ctx.accounts.vault)?;
+    emit!(LockRefunded {
+        nonce,
+        source: ctx.accounts.source.key(),
+        mint,
+        amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
 }
 
-#[error_code]
-pub enum BridgeError {
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Invalid target address")]
-    InvalidTargetAddress,
-    #[msg("Transaction already processed")]
-    AlreadyProcessed,
-}
\ No newline at end of file
+/// Creates the `PendingLock` that lets `source` reclaim lock `nonce` if it is
+/// never attested; does nothing while refunds are disabled.
+#[allow(clippy::too_many_arguments)]
+fn record_pending_lock<'info>(
+    payer: &Signer<'info>,
+    record: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    config: &BridgeConfig,
+    nonce: u64,
+    source: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    deadline_unix: i64,
+    slot: u64,
+) -> Result<()> {
+    if config.refund_after_slots == 0 {
+        return Ok(());
+    }
+    let nonce_bytes = nonce.to_le_bytes();
+    let (key, bump) = Pubkey::find_program_address(&[b"pending_lock", nonce_bytes.as_ref()], &crate::ID);
+    require_keys_eq!(record.key(), key, BridgeError::PendingLockMismatch);
+
+    let seeds: &[&[u8]] = &[b"pending_lock", nonce_bytes.as_ref(), &[bump]];
+    create_pda_account(
+        payer,
+        record,
+        system_program,
+        8 + PendingLock::INIT_SPACE,
+        &crate::ID,
+        seeds,
+    )?;
+
+    let pending = PendingLock {
+        nonce,
+        source,
+        mint,
+        amount,
+        rent_payer: payer.key(),
+        refundable_after_slot: slot.saturating_add(config.refund_after_slots),
+        deadline_unix,
+        refunded: false,
+        cancelled: false,
+        cancelled_slot: 0,
+        bump,
+    };
+    let mut data = record.try_borrow_mut_data()?;
+    pending.try_serialize(&mut &mut data[..])
+}
+
+/// Creates the `ProcessedRecord` that marks a transfer as delivered. The
+/// caller's account constraint has already checked that it does not exist.
+#[allow(clippy::too_many_arguments)]
+fn record_processed<'info>(
+    payer: &Signer<'info>,
+    record: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    bump: u8,
+    source_chain: [u8; 32],
+    source_tx: [u8; 32],
+    guardian_set_index: u32,
+    slot: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[b"processed", source_chain.as_ref(), source_tx.as_ref(), &[bump]];
+    create_pda_account(
+        payer,
+        record,
+        system_program,
+        8 + ProcessedRecord::INIT_SPACE,
+        &crate::ID,
+        seeds,
+    )?;
+
+    let processed = ProcessedRecord {
+        source_chain,
+        source_tx,
+        guardian_set_index,
+        processed_slot: slot,
+        bump,
+    };
+    let mut data = record.try_borrow_mut_data()?;
+    processed.try_serialize(&mut &mut data[..])
+}
+
+/// Whether the guardian set behind a seed-checked account can no longer
+/// attest: it has been rotated out and its grace period is over, or it has
+/// been closed altogether.
+fn guardian_set_retired(info: &AccountInfo, current_index: u32, slot: u64) -> Result<bool> {
+    if info.owner != &crate::ID || info.data_is_empty() {
+        return Ok(true);
+    }
+    let data = info.try_borrow_data()?;
+    let guardian_set = GuardianSet::try_deserialize(&mut &data[..])?;
+    Ok(!guardian_set.can_attest(current_index, slot))
+}
+
+/// Grows or shrinks a program-owned account, topping up rent from `payer`.
+/// Any bytes added are zeroed.
+fn resize_account<'info>(
+    payer: &Signer<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(target.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: target.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    target.realloc(space, true)?;
+    Ok(())
+}
+
+/// Records a verified large release as an `Attestation`, to be executed through
+/// `execute_release` / `execute_mint` once its delay has passed.
+#[allow(clippy::too_many_arguments)]
+fn queue_release<'info>(
+    payer: &Signer<'info>,
+    record: &UncheckedAccount<'info>,
+    system_program: &Program<'info, System>,
+    bump: u8,
+    message: &TransferMessage,
+    guardian_set_index: u32,
+    slot: u64,
+    delay: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[
+        b"attestation",
+        message.source_chain.as_ref(),
+        message.source_tx.as_ref(),
+        &[bump],
+    ];
+    create_pda_account(
+        payer,
+        record,
+        system_program,
+        8 + Attestation::INIT_SPACE,
+        &crate::ID,
+        seeds,
+    )?;
+
+    let attestation = Attestation {
+        source_chain: message.source_chain,
+        source_tx: message.source_tx,
+        token: message.token,
+        amount: message.amount,
+        recipient: message.recipient,
+        nonce: message.nonce,
+        relayer_fee: message.relayer_fee,
+        native_drop: message.native_drop,
+        deadline_unix: message.deadline_unix,
+        memo: message.memo.clone(),
+        tier: message.tier,
+        source_addr: message.source_addr,
+        guardian_set_index,
+        posted_slot: slot,
+        executable_after_slot: slot.saturating_add(delay),
+        executed: false,
+        held: false,
+        cancelled: false,
+        vetoed_by: None,
+        bump,
+    };
+    let mut data = record.try_borrow_mut_data()?;
+    attestation.try_serialize(&mut &mut data[..])?;
+
+    emit!(ReleaseQueued {
+        source_chain: message.source_chain,
+        source_tx: message.source_tx,
+        token: message.token,
+        amount: message.amount,
+        recipient: message.recipient,
+        executable_after_slot: attestation.executable_after_slot,
+        slot,
+    });
+
+    Ok(())
+}
+
+/// Size of the `Ed25519SignatureOffsets` header entries in an ed25519 program instruction.
+const ED25519_OFFSETS_LEN: usize = 14;
+
+/// Size of the `SecpSignatureOffsets` header entries in a secp256k1 program instruction.
+const SECP256K1_OFFSETS_LEN: usize = 11;
+
+/// Folds `proof` into `leaf`, hashing each pair in sorted order so no
+/// left/right flags are needed. Leaves are full `TransferMessage` digests,
+/// whose preimages are longer than the 64 bytes of an inner node.
+fn merkle_root_of(leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    proof.iter().fold(leaf, |node, sibling| {
+        let (first, second) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        keccak::hashv(&[&first, &second]).to_bytes()
+    })
+}
+
+/// Checks that earlier instructions in this transaction include native
+/// signature-program verifications of `digest` by at least `threshold` distinct
+/// guardians, counting ed25519 and secp256k1 guardians together.
+///
+/// Both programs abort the transaction on a bad signature, so reaching this
+/// point means every signature they list is valid; all that remains is to
+/// confirm who signed and what they signed.
+fn verify_guardian_signatures(
+    instructions: &AccountInfo,
+    guardian_set: &GuardianSet,
+    message: &impl GuardianMessage,
+) -> Result<()> {
+    require!(guardian_set.threshold > 0, BridgeError::InsufficientSignatures);
+    let digest = message.digest(guardian_set.index)?;
+
+    let mut signers: Vec<GuardianKey> = Vec::new();
+    let mut foreign_payload = false;
+
+    for_each_signature(instructions, |key, signed| {
+        if !guardian_set.guardians.contains(&key) {
+            return;
+        }
+        if signed != digest.as_slice() {
+            foreign_payload = true;
+            return;
+        }
+        if !signers.contains(&key) {
+            signers.push(key);
+        }
+    })?;
+
+    if signers.len() < guardian_set.threshold as usize {
+        if foreign_payload {
+            return err!(BridgeError::InvalidSignaturePayload);
+        }
+        return err!(BridgeError::InsufficientSignatures);
+    }
+
+    Ok(())
+}
+
+/// Calls `f` with every `(signer, message)` pair verified by the ed25519 and
+/// secp256k1 programs in instructions preceding the current one.
+fn for_each_signature(
+    instructions: &AccountInfo,
+    mut f: impl FnMut(GuardianKey, &[u8]),
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+
+    for index in 0..current_index {
+        let ix = load_instruction_at_checked(index as usize, instructions)?;
+        let entries = if ix.program_id == ed25519_program::ID {
+            parse_ed25519_instruction(&ix.data)?
+        } else if ix.program_id == secp256k1_program::ID {
+            parse_secp256k1_instruction(&ix.data, index as u8)?
+        } else {
+            continue;
+        };
+
+        for (key, signed) in entries {
+            f(key, signed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Confirms `info` is a live, bonded stake PDA for `guardian`.
+fn check_guardian_stake(
+    info: &AccountInfo,
+    guardian: &GuardianKey,
+    staking: &StakingConfig,
+) -> Result<()> {
+    require!(info.owner == &crate::ID, BridgeError::GuardianNotStaked);
+    let stake = GuardianStake::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+
+    let expected = Pubkey::create_program_address(
+        &[b"guardian_stake", stake.staker.as_ref(), &[stake.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| BridgeError::GuardianNotStaked)?;
+    require_keys_eq!(info.key(), expected, BridgeError::GuardianNotStaked);
+
+    require!(stake.guardian == *guardian, BridgeError::GuardianMismatch);
+    require!(stake.is_bonded(staking.min_stake), BridgeError::GuardianNotStaked);
+    Ok(())
+}
+
+fn read_u16(data: &[u8], at: usize) -> Result<usize> {
+    let bytes = data
+        .get(at..at + 2)
+        .ok_or(BridgeError::InvalidSignatureInstruction)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn read_u8(data: &[u8], at: usize) -> Result<usize> {
+    let byte = data
+        .get(at)
+        .ok_or(BridgeError::InvalidSignatureInstruction)?;
+    Ok(*byte as usize)
+}
+
+fn read_slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    Ok(data
+        .get(offset..offset + len)
+        .ok_or(BridgeError::InvalidSignatureInstruction)?)
+}
+
+/// Extracts `(guardian, message)` pairs from ed25519 program instruction data.
+///
+/// Only self-contained entries are accepted: offsets pointing into other
+/// instructions would let a caller pair a guardian key with unrelated data.
+fn parse_ed25519_instruction(data: &[u8]) -> Result<Vec<(GuardianKey, &[u8])>> {
+    let count = read_u8(data, 0)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let base = 2 + i * ED25519_OFFSETS_LEN;
+        let signature_ix = read_u16(data, base + 2)?;
+        let pubkey_offset = read_u16(data, base + 4)?;
+        let pubkey_ix = read_u16(data, base + 6)?;
+        let message_offset = read_u16(data, base + 8)?;
+        let message_size = read_u16(data, base + 10)?;
+        let message_ix = read_u16(data, base + 12)?;
+
+        let this_ix = u16::MAX as usize;
+        require!(
+            signature_ix == this_ix && pubkey_ix == this_ix && message_ix == this_ix,
+            BridgeError::InvalidSignatureInstruction
+        );
+
+        let pubkey = Pubkey::try_from(read_slice(data, pubkey_offset, 32)?)
+            .map_err(|_| BridgeError::InvalidSignatureInstruction)?;
+        let signed = read_slice(data, message_offset, message_size)?;
+        entries.push((GuardianKey::Ed25519(pubkey), signed));
+    }
+
+    Ok(entries)
+}
+
+/// Extracts `(guardian, message)` pairs from secp256k1 program instruction data.
+///
+/// The secp256k1 program addresses data by absolute instruction index, so
+/// every entry must point back at `own_index`. Signatures must carry a raw
+/// recovery id of 0 or 1, not an Ethereum-style `v`.
+fn parse_secp256k1_instruction(data: &[u8], own_index: u8) -> Result<Vec<(GuardianKey, &[u8])>> {
+    let count = read_u8(data, 0)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let base = 1 + i * SECP256K1_OFFSETS_LEN;
+        let signature_offset = read_u16(data, base)?;
+        let signature_ix = read_u8(data, base + 2)?;
+        let address_offset = read_u16(data, base + 3)?;
+        let address_ix = read_u8(data, base + 5)?;
+        let message_offset = read_u16(data, base + 6)?;
+        let message_size = read_u16(data, base + 8)?;
+        let message_ix = read_u8(data, base + 10)?;
+
+        let this_ix = own_index as usize;
+        require!(
+            signature_ix == this_ix && address_ix == this_ix && message_ix == this_ix,
+            BridgeError::InvalidSignatureInstruction
+        );
+
+        let recovery_id = read_u8(data, signature_offset + 64)?;
+        require!(recovery_id <= 1, BridgeError::InvalidSignatureInstruction);
+
+        let mut address = [0u8; 20];
+        address.copy_from_slice(read_slice(data, address_offset, 20)?);
+        let signed = read_slice(data, message_offset, message_size)?;
+        entries.push((GuardianKey::Secp256k1(address), signed));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(byte: u8) -> [u8; 32] {
+        keccak::hash(&[byte]).to_bytes()
+    }
+
+    fn node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        merkle_root_of(a, &[b])
+    }
+
+    fn push_u16(data: &mut Vec<u8>, value: usize) {
+        data.extend_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    /// ed25519 program data with every entry's key, signature and message
+    /// inside the instruction itself
+    fn ed25519_data(entries: &[(Pubkey, &[u8])]) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8, 0];
+        let mut offset = 2 + entries.len() * ED25519_OFFSETS_LEN;
+        let mut payload = Vec::new();
+        for (key, message) in entries {
+            let (pubkey_offset, signature_offset) = (offset, offset + 32);
+            let message_offset = signature_offset + 64;
+            offset = message_offset + message.len();
+            for value in [
+                signature_offset,
+                u16::MAX as usize,
+                pubkey_offset,
+                u16::MAX as usize,
+                message_offset,
+                message.len(),
+                u16::MAX as usize,
+            ] {
+                push_u16(&mut data, value);
+            }
+            payload.extend_from_slice(key.as_ref());
+            payload.extend_from_slice(&[0; 64]);
+            payload.extend_from_slice(message);
+        }
+        data.extend(payload);
+        data
+    }
+
+    fn is_invalid_signature_instruction<T>(result: Result<T>) -> bool {
+        matches!(result, Err(e) if e == BridgeError::InvalidSignatureInstruction.into())
+    }
+
+    #[test]
+    fn ed25519_valid_layout() {
+        let key = Pubkey::new_unique();
+        let data = ed25519_data(&[(key, b"digest")]);
+        let entries = parse_ed25519_instruction(&data).unwrap();
+        assert!(entries == vec![(GuardianKey::Ed25519(key), &b"digest"[..])]);
+    }
+
+    #[test]
+    fn ed25519_truncated_buffer() {
+        let data = ed25519_data(&[(Pubkey::new_unique(), b"digest")]);
+        for len in [0, 1, 2 + ED25519_OFFSETS_LEN - 1, data.len() - 1] {
+            assert!(is_invalid_signature_instruction(parse_ed25519_instruction(&data[..len])));
+        }
+    }
+
+    #[test]
+    fn ed25519_offsets_into_another_instruction() {
+        // signature, public key and message instruction indices in turn
+        for at in [4, 8, 14] {
+            let mut data = ed25519_data(&[(Pubkey::new_unique(), b"digest")]);
+            data[at..at + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert!(is_invalid_signature_instruction(parse_ed25519_instruction(&data)));
+        }
+    }
+
+    #[test]
+    fn ed25519_signature_count() {
+        assert!(parse_ed25519_instruction(&ed25519_data(&[])).unwrap().is_empty());
+
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let data = ed25519_data(&[(a, b"first"), (b, b"second")]);
+        assert!(
+            parse_ed25519_instruction(&data).unwrap()
+                == vec![
+                    (GuardianKey::Ed25519(a), &b"first"[..]),
+                    (GuardianKey::Ed25519(b), &b"second"[..]),
+                ]
+        );
+
+        // A count beyond the offsets actually present
+        let mut data = ed25519_data(&[(a, b"first")]);
+        data[0] = 2;
+        assert!(is_invalid_signature_instruction(parse_ed25519_instruction(&data)));
+    }
+
+    /// secp256k1 program data for instruction `own_index`, each entry's
+    /// address, signature and message inside the instruction itself
+    fn secp256k1_data(own_index: u8, entries: &[([u8; 20], &[u8])]) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8];
+        let mut offset = 1 + entries.len() * SECP256K1_OFFSETS_LEN;
+        let mut payload = Vec::new();
+        for (address, message) in entries {
+            let (address_offset, signature_offset) = (offset, offset + 20);
+            let message_offset = signature_offset + 65;
+            offset = message_offset + message.len();
+            push_u16(&mut data, signature_offset);
+            data.push(own_index);
+            push_u16(&mut data, address_offset);
+            data.push(own_index);
+            push_u16(&mut data, message_offset);
+            push_u16(&mut data, message.len());
+            data.push(own_index);
+            payload.extend_from_slice(address);
+            payload.extend_from_slice(&[0; 64]);
+            payload.push(1);
+            payload.extend_from_slice(message);
+        }
+        data.extend(payload);
+        data
+    }
+
+    /// Where the first entry's recovery id sits in `secp256k1_data`
+    const SECP256K1_RECOVERY_ID_AT: usize = 1 + SECP256K1_OFFSETS_LEN + 20 + 64;
+
+    #[test]
+    fn secp256k1_valid_layout() {
+        let data = secp256k1_data(3, &[([7; 20], b"digest")]);
+        let entries = parse_secp256k1_instruction(&data, 3).unwrap();
+        assert!(entries == vec![(GuardianKey::Secp256k1([7; 20]), &b"digest"[..])]);
+    }
+
+    #[test]
+    fn secp256k1_truncated_buffer() {
+        let data = secp256k1_data(0, &[([7; 20], b"digest")]);
+        for len in [0, SECP256K1_OFFSETS_LEN, SECP256K1_RECOVERY_ID_AT, data.len() - 1] {
+            assert!(is_invalid_signature_instruction(parse_secp256k1_instruction(&data[..len], 0)));
+        }
+    }
+
+    #[test]
+    fn secp256k1_wrong_eth_address_offset() {
+        let data = secp256k1_data(0, &[([7; 20], b"digest")]);
+
+        let mut past_end = data.clone();
+        past_end[4..6].copy_from_slice(&(data.len() as u16 - 19).to_le_bytes());
+        assert!(is_invalid_signature_instruction(parse_secp256k1_instruction(&past_end, 0)));
+
+        let mut other_ix = data.clone();
+        other_ix[6] = 1;
+        assert!(is_invalid_signature_instruction(parse_secp256k1_instruction(&other_ix, 0)));
+
+        // Pointing at the signature reads a different signer
+        let mut shifted = data;
+        shifted[4..6].copy_from_slice(&(1 + SECP256K1_OFFSETS_LEN as u16 + 20).to_le_bytes());
+        let entries = parse_secp256k1_instruction(&shifted, 0).unwrap();
+        assert!(entries[0].0 != GuardianKey::Secp256k1([7; 20]));
+
+        // The whole instruction must be the one being parsed
+        let data = secp256k1_data(2, &[([7; 20], b"digest")]);
+        assert!(is_invalid_signature_instruction(parse_secp256k1_instruction(&data, 3)));
+    }
+
+    #[test]
+    fn secp256k1_wrong_recovery_id() {
+        for recovery_id in [0, 1] {
+            let mut data = secp256k1_data(0, &[([7; 20], b"digest")]);
+            data[SECP256K1_RECOVERY_ID_AT] = recovery_id;
+            assert!(parse_secp256k1_instruction(&data, 0).is_ok());
+        }
+        for recovery_id in [2, 27, 28] {
+            let mut data = secp256k1_data(0, &[([7; 20], b"digest")]);
+            data[SECP256K1_RECOVERY_ID_AT] = recovery_id;
+            assert!(is_invalid_signature_instruction(parse_secp256k1_instruction(&data, 0)));
+        }
+    }
+
+    #[test]
+    fn merkle_empty_proof_is_the_leaf() {
+        assert_eq!(merkle_root_of(leaf(1), &[]), leaf(1));
+    }
+
+    #[test]
+    fn merkle_single_sibling_is_order_independent() {
+        let (a, b) = (leaf(1), leaf(2));
+        let root = keccak::hashv(&[&a.min(b), &a.max(b)]).to_bytes();
+        assert_eq!(merkle_root_of(a, &[b]), root);
+        assert_eq!(merkle_root_of(b, &[a]), root);
+    }
+
+    #[test]
+    fn merkle_odd_leaf_is_carried_up() {
+        // [a, b, c]: c has no sibling at the bottom level and joins the root
+        let (a, b, c) = (leaf(1), leaf(2), leaf(3));
+        let root = node(node(a, b), c);
+        assert_eq!(merkle_root_of(a, &[b, c]), root);
+        assert_eq!(merkle_root_of(b, &[a, c]), root);
+        assert_eq!(merkle_root_of(c, &[node(a, b)]), root);
+    }
+
+    #[test]
+    fn merkle_proof_mismatch_changes_root() {
+        let (a, b, c, d) = (leaf(1), leaf(2), leaf(3), leaf(4));
+        let root = node(node(a, b), node(c, d));
+        assert_eq!(merkle_root_of(a, &[b, node(c, d)]), root);
+        assert_ne!(merkle_root_of(a, &[c, node(b, d)]), root);
+        assert_ne!(merkle_root_of(a, &[b]), root);
+        assert_ne!(merkle_root_of(leaf(5), &[b, node(c, d)]), root);
+    }
+
+    fn wrapped_asset(source_decimals: u8, local_decimals: u8) -> WrappedAssetRegistry {
+        WrappedAssetRegistry {
+            source_chain: [1; 32],
+            source_token: [2; 32],
+            wrapped_mint: Pubkey::new_unique(),
+            mint_bump: 255,
+            legacy_seeds: false,
+            supply_cap: 0,
+            source_decimals,
+            local_decimals,
+            bump: 255,
+            multi_hop: false,
+        }
+    }
+
+    fn is_not_representable<T>(result: Result<T>) -> bool {
+        matches!(result, Err(e) if e == BridgeError::AmountNotRepresentable.into())
+    }
+
+    #[test]
+    fn to_local_scales_up() {
+        let asset = wrapped_asset(6, 9);
+        assert_eq!(asset.to_local(1_234_567).unwrap(), (1_234_567_000, 0));
+        assert_eq!(wrapped_asset(8, 8).to_local(42).unwrap(), (42, 0));
+    }
+
+    #[test]
+    fn to_local_scales_down_and_truncates_dust() {
+        let asset = wrapped_asset(18, 8);
+        assert_eq!(asset.to_local(5_000_000_000_000_000_000).unwrap(), (500_000_000, 0));
+        assert_eq!(asset.to_local(1_234_567_890_123).unwrap(), (123, 4_567_890_123));
+        // Less than one local unit is all dust
+        assert_eq!(asset.to_local(9_999_999_999).unwrap(), (0, 9_999_999_999));
+    }
+
+    #[test]
+    fn to_local_overflow_is_an_error() {
+        assert!(is_not_representable(wrapped_asset(6, 9).to_local(u64::MAX / 999)));
+        assert!(wrapped_asset(6, 9).to_local(u64::MAX / 1000).is_ok());
+        // 10^20 does not fit a u64 either way round
+        assert!(is_not_representable(wrapped_asset(0, 20).to_local(1)));
+        assert!(is_not_representable(wrapped_asset(20, 0).to_local(1)));
+    }
+
+    #[test]
+    fn lp_first_deposit_is_one_to_one() {
+        assert_eq!(lp_shares_for(1_000, 0, 0).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn lp_proportional_deposit() {
+        // Doubling a pool of 1000 assets backing 1000 shares
+        assert_eq!(lp_shares_for(1_000, 1_000, 1_000).unwrap(), 1_000);
+        // A pool that has earned fees sells fewer shares per asset
+        assert_eq!(lp_shares_for(1_000, 2_000, 1_000).unwrap(), 500);
+    }
+
+    #[test]
+    fn lp_zero_supply_or_balance() {
+        // Assets donated before any shares exist price the first deposit
+        assert_eq!(lp_shares_for(1_000, 1_000, 0).unwrap(), 0);
+        assert_eq!(lp_shares_for(2_000, 1_000, 0).unwrap(), 1);
+        // Shares outstanding over an empty pool are priced at the virtual asset
+        assert_eq!(lp_shares_for(10, 0, 1_000).unwrap(), 10_010);
+        assert!(matches!(
+            lp_shares_for(u64::MAX, 0, 1_000),
+            Err(e) if e == BridgeError::InvalidAmount.into()
+        ));
+        assert_eq!(lp_shares_for(0, 1_000, 1_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn lp_rounding_favors_the_pool() {
+        // 100 * 501 / 1001 = 50.049..., rounded down
+        assert_eq!(lp_shares_for(100, 1_000, 500).unwrap(), 50);
+        for (assets, lp_assets, supply) in [(100, 1_000, 500), (7, 3, 11), (1, 999, 1_000)] {
+            let shares = lp_shares_for(assets, lp_assets, supply).unwrap();
+            let redeemable = lp_assets_for(shares, lp_assets + assets, supply + shares).unwrap();
+            assert!(redeemable <= assets);
+        }
+    }
+
+    /// A registry with `locked` deposited by users and `lp` by LPs
+    fn registry(locked: u64, lp: u64) -> TokenRegistry {
+        let mut registry = TokenRegistry::deserialize(&mut &vec![0; TokenRegistry::INIT_SPACE][..]).unwrap();
+        registry.initialize(Pubkey::new_unique(), 255);
+        registry.add_locked(locked).unwrap();
+        registry.lp_assets = lp;
+        registry
+    }
+
+    #[test]
+    fn release_draws_on_lp_capital() {
+        let mut registry = registry(100, 50);
+        registry.sub_locked(130).unwrap();
+        assert_eq!(registry.total_locked, 0);
+        assert_eq!(registry.lp_drawn, 30);
+        // Still owed to LPs, so share pricing is unchanged
+        assert_eq!(registry.lp_assets, 50);
+        assert_eq!(registry.lp_held(), 20);
+        assert_eq!(registry.free_liquidity(20), 20);
+        assert!(registry.check_vault_balance(20).is_ok());
+        assert!(matches!(
+            registry.sub_locked(21),
+            Err(e) if e == BridgeError::LockedBalanceUnderflow.into()
+        ));
+        // Later locks repay the draw before counting as locked
+        registry.add_locked(40).unwrap();
+        assert_eq!(registry.lp_drawn, 0);
+        assert_eq!(registry.total_locked, 10);
+        assert!(registry.check_vault_balance(60).is_ok());
+        assert!(matches!(
+            registry.check_vault_balance(59),
+            Err(e) if e == BridgeError::VaultInvariantViolated.into()
+        ));
+    }
+
+    #[test]
+    fn lp_withdrawal_limited_to_capital_in_vault() {
+        let mut registry = registry(100, 50);
+        assert_eq!(registry.free_liquidity(150), 50);
+        registry.sub_locked(120).unwrap();
+        // Only the 30 LP tokens left in the vault can leave it
+        assert_eq!(registry.free_liquidity(30), 30);
+        assert_eq!(registry.surplus(30), 0);
+        // A vault already short of the books pays out what it has
+        assert_eq!(registry.free_liquidity(25), 25);
+        assert!(registry.check_vault_balance(25).is_err());
+    }
+
+    #[test]
+    fn lp_rounding_after_draw_favors_the_pool() {
+        let mut registry = registry(0, 1_000);
+        registry.sub_locked(400).unwrap();
+        let supply = 1_000;
+        for assets in [1, 7, 333, 1_000] {
+            let shares = lp_shares_for(assets, registry.lp_assets, supply).unwrap();
+            let redeemable = lp_assets_for(shares, registry.lp_assets + assets, supply + shares).unwrap();
+            assert!(redeemable <= assets);
+        }
+        // Redeeming every share asks for the full book value, more than the
+        // vault holds until locks repay the draw
+        let owed = lp_assets_for(supply, registry.lp_assets, supply).unwrap();
+        assert_eq!(owed, 1_000);
+        assert_eq!(registry.free_liquidity(600), 600);
+    }
+
+    #[test]
+    fn lp_donation_does_not_inflate_share_price() {
+        // A first depositor mints a single share, then sends tokens straight
+        // to the vault hoping to price the next deposit at zero shares
+        let mut registry = registry(0, 1);
+        let donated_vault = 1 + 1_000_000;
+        assert_eq!(registry.surplus(donated_vault), 1_000_000);
+        assert_eq!(registry.free_liquidity(donated_vault), 1);
+        // The donation never reaches `lp_assets`, so the victim is priced
+        // against the recorded capital alone
+        assert_eq!(lp_shares_for(1_000, registry.lp_assets, 1).unwrap(), 1_000);
+        // The same holds once a release has drawn the attacker's capital
+        registry.sub_locked(1).unwrap();
+        assert_eq!(registry.surplus(donated_vault - 1), 1_000_000);
+        assert_eq!(lp_shares_for(1_000, registry.lp_assets, 1).unwrap(), 1_000);
+    }
+
+    const BECH32_ADDRESS: &[u8] = b"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    #[test]
+    fn bech32_valid_address() {
+        assert!(is_bech32_address("bc", BECH32_ADDRESS));
+        // Exactly the longest allowed
+        let longest = b"a1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq87k0gd";
+        assert_eq!(longest.len(), MAX_BECH32_LEN);
+        assert!(is_bech32_address("a", longest));
+    }
+
+    #[test]
+    fn bech32_mixed_case() {
+        assert!(!is_bech32_address("bc", b"bc1QW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"));
+        assert!(!is_bech32_address("bc", &BECH32_ADDRESS.to_ascii_uppercase()));
+    }
+
+    #[test]
+    fn bech32_bad_checksum() {
+        let mut addr = BECH32_ADDRESS.to_vec();
+        *addr.last_mut().unwrap() = b'5';
+        assert!(!is_bech32_address("bc", &addr));
+        // Too short to hold a checksum at all
+        assert!(!is_bech32_address("bc", b"bc1qqqqq"));
+    }
+
+    #[test]
+    fn bech32_bad_hrp() {
+        assert!(!is_bech32_address("tb", BECH32_ADDRESS));
+        assert!(!is_bech32_address("b", BECH32_ADDRESS));
+        // Missing separator
+        assert!(!is_bech32_address("bc", &BECH32_ADDRESS[..2]));
+    }
+
+    #[test]
+    fn bech32_over_length() {
+        // Checksum holds, but one character past the limit
+        let addr = b"a1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqql0ccdy";
+        assert_eq!(addr.len(), MAX_BECH32_LEN + 1);
+        assert!(!is_bech32_address("a", addr));
+    }
+
+    fn chain(id: u8) -> [u8; 32] {
+        let mut chain = [0; 32];
+        chain[31] = id;
+        chain
+    }
+
+    fn hex(id: &str) -> [u8; 32] {
+        let mut out = [0; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&id[2 * i..2 * i + 2], 16).unwrap();
+        }
+        out
+    }
+
+    /// The vectors of `test/TransferId.test.ts`, which every implementation
+    /// must reproduce
+    #[test]
+    fn transfer_id_matches_shared_vectors() {
+        // Solana lock to an EVM address
+        assert_eq!(
+            transfer_id(&chain(1), &chain(2), &[0x11; 32], &[0x22; 20], &[0x33; 32], 1_000_000, 7),
+            hex("a9d2e46bc24acda518d8079c23a93bd622989ceb60ab916e1700a2b466717098")
+        );
+        // EVM lock to Solana with the largest amount
+        assert_eq!(
+            transfer_id(&chain(2), &chain(1), &[0xaa; 20], &[0xbb; 32], &[0xcc; 20], u64::MAX, 0),
+            hex("4acd7c47e25eb3129c8a9c492e237cce1d2f28fd1442c63add3ed3ed22353788")
+        );
+        // Destination address longer than a word
+        assert_eq!(
+            transfer_id(
+                &chain(1),
+                &chain(3),
+                &[0x11; 32],
+                b"cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu",
+                &[0x33; 32],
+                42,
+                1,
+            ),
+            hex("afdab30939068ff5af33ac99d402d563aa3eac7cc04e6a4e3a2610848478fd7d")
+        );
+    }
+
+    fn rate_limits(max_locks_per_epoch: u32, max_volume_per_epoch: u64) -> BridgeConfig {
+        let mut config = BridgeConfig::deserialize(&mut &vec![0; BridgeConfig::INIT_SPACE][..]).unwrap();
+        config.max_locks_per_epoch = max_locks_per_epoch;
+        config.max_volume_per_epoch = max_volume_per_epoch;
+        config
+    }
+
+    /// Runs `f` on a `UserActivity` account that has just been created
+    fn with_activity(f: impl FnOnce(&mut Account<UserActivity>)) {
+        let (key, owner) = (Pubkey::new_unique(), crate::ID);
+        let mut lamports = 0;
+        let mut data = vec![0; 8 + UserActivity::INIT_SPACE];
+        data[..8].copy_from_slice(&UserActivity::DISCRIMINATOR);
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+        f(&mut Account::try_from(&info).unwrap());
+    }
+
+    #[test]
+    fn user_activity_rolls_over_each_epoch() {
+        let config = rate_limits(2, 0);
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        with_activity(|activity| {
+            for _ in 0..2 {
+                record_user_activity(Some(activity), Some(254), &config, user, mint, 10, 5).unwrap();
+            }
+            assert!(matches!(
+                record_user_activity(Some(activity), Some(254), &config, user, mint, 10, 5),
+                Err(e) if e == BridgeError::RateLimited.into()
+            ));
+            // A new epoch starts the count over
+            record_user_activity(Some(activity), Some(254), &config, user, mint, 10, 6).unwrap();
+            assert_eq!((activity.epoch, activity.lock_count, activity.volume), (6, 1, 10));
+            assert_eq!(activity.bump, 254);
+            // So does an account last used by another user
+            let other = Pubkey::new_unique();
+            record_user_activity(Some(activity), Some(254), &config, other, mint, 10, 6).unwrap();
+            assert!(activity.user == other);
+            assert_eq!(activity.lock_count, 1);
+        });
+    }
+
+    #[test]
+    fn user_activity_volume_limit() {
+        let config = rate_limits(0, 100);
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        with_activity(|activity| {
+            record_user_activity(Some(activity), Some(254), &config, user, mint, 60, 1).unwrap();
+            // Exactly at the limit is allowed
+            record_user_activity(Some(activity), Some(254), &config, user, mint, 40, 1).unwrap();
+            assert!(matches!(
+                record_user_activity(Some(activity), Some(254), &config, user, mint, 1, 1),
+                Err(e) if e == BridgeError::RateLimited.into()
+            ));
+            record_user_activity(Some(activity), Some(254), &config, user, mint, 100, 2).unwrap();
+        });
+    }
+
+    #[test]
+    fn user_activity_disabled() {
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // No limits set needs no account and leaves a given one untouched
+        record_user_activity(None, None, &rate_limits(0, 0), user, mint, u64::MAX, 1).unwrap();
+        with_activity(|activity| {
+            record_user_activity(Some(activity), Some(254), &rate_limits(0, 0), user, mint, 10, 1).unwrap();
+            assert_eq!((activity.epoch, activity.lock_count, activity.volume), (0, 0, 0));
+        });
+        assert!(matches!(
+            record_user_activity(None, None, &rate_limits(1, 0), user, mint, 10, 1),
+            Err(e) if e == BridgeError::UserActivityRequired.into()
+        ));
+    }
+
+    fn sequence() -> TransferSequence {
+        TransferSequence::deserialize(&mut &vec![0; TransferSequence::INIT_SPACE][..]).unwrap()
+    }
+
+    #[test]
+    fn interleaved_streams_stay_contiguous() {
+        let (token, wrapped) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let clock = Clock::default();
+        let (mut locks, mut burns) = (sequence(), sequence());
+        let (mut lock_seqs, mut burn_seqs) = (vec![], vec![]);
+        // Transfers of other streams already took the shared nonce to 10
+        let order = [false, true, true, false, true, false, false, true];
+        for (nonce, burn) in (10..).zip(order) {
+            if burn {
+                burn_seqs.push(burns.advance(wrapped, chain(2), 254, nonce, &clock).unwrap());
+            } else {
+                lock_seqs.push(locks.advance(token, chain(2), 253, nonce, &clock).unwrap());
+            }
+        }
+        // Each stream is seeded once, from the nonce when it was created, and
+        // then counts up without gaps whatever the other stream does
+        assert_eq!(lock_seqs, [11, 12, 13, 14]);
+        assert_eq!(burn_seqs, [12, 13, 14, 15]);
+        assert!(locks.mint == token && burns.mint == wrapped);
+        assert_eq!((locks.bump, burns.bump), (253, 254));
+    }
+
+    #[test]
+    fn sequence_overflow_is_an_error() {
+        let mut stream = sequence();
+        assert_eq!(
+            stream.advance(Pubkey::new_unique(), chain(2), 254, u64::MAX - 1, &Clock::default()).unwrap(),
+            u64::MAX
+        );
+        assert!(matches!(
+            stream.advance(stream.mint, chain(2), 254, 0, &Clock::default()),
+            Err(e) if e == BridgeError::CounterOverflow.into()
+        ));
+    }
+
+    /// `registry` with a breaker of `limit` over six 100-slot buckets
+    fn breaker(limit: u64) -> TokenRegistry {
+        let mut registry = registry(0, 0);
+        registry.circuit_breaker_limit = limit;
+        registry.circuit_breaker_bucket_slots = 100;
+        registry
+    }
+
+    #[test]
+    fn outflow_window_rolls_over() {
+        let mut registry = breaker(1_000);
+        assert_eq!(registry.record_outflow(600, 0), None);
+        assert_eq!(registry.record_outflow(400, 599), None);
+        // Full until the first bucket leaves the six-bucket window
+        assert_eq!(registry.record_outflow(1, 599), Some(1_001));
+        assert_eq!(registry.record_outflow(600, 600), None);
+        assert_eq!(registry.record_outflow(1, 600), Some(1_001));
+        // A release long after reuses the stale ring slots
+        assert_eq!(registry.record_outflow(1_000, 100_000), None);
+        assert_eq!(registry.record_outflow(1_001, 200_000), Some(1_001));
+    }
+
+    #[test]
+    fn outflow_without_limit_is_unrecorded() {
+        let mut registry = breaker(0);
+        assert_eq!(registry.record_outflow(u64::MAX, 0), None);
+        assert!(registry.outflow_buckets.iter().all(|bucket| bucket.volume == 0));
+    }
+
+    #[test]
+    fn circuit_breaker_trip_and_reset() {
+        let mut registry = breaker(1_000);
+        assert_eq!(registry.record_outflow(900, 0), None);
+        assert!(matches!(
+            registry.trip_circuit_breaker(100, 0),
+            Err(e) if e == BridgeError::CircuitBreakerNotExceeded.into()
+        ));
+        assert!(registry.check_outflow_allowed().is_ok());
+        // Neither attempt is counted, only reported
+        assert_eq!(registry.trip_circuit_breaker(101, 0).unwrap(), 1_001);
+        assert!(matches!(
+            registry.check_outflow_allowed(),
+            Err(e) if e == BridgeError::CircuitBreakerTripped.into()
+        ));
+        assert!(matches!(
+            registry.trip_circuit_breaker(101, 0),
+            Err(e) if e == BridgeError::CircuitBreakerTripped.into()
+        ));
+
+        registry.paused = true;
+        registry.reset_circuit_breaker();
+        assert!(!registry.circuit_breaker_tripped);
+        // An admin's pause outlives the reset
+        assert!(matches!(
+            registry.check_outflow_allowed(),
+            Err(e) if e == BridgeError::TokenPaused.into()
+        ));
+        // The window starts empty again
+        assert_eq!(registry.record_outflow(1_000, 0), None);
+    }
+
+    #[test]
+    fn daily_limit_boundary() {
+        let mut registry = registry(0, 0);
+        registry.daily_limit = 1_000;
+        registry.record_daily_outflow(400, 0).unwrap();
+        // Exactly up to the limit is allowed, one more is not
+        registry.record_daily_outflow(600, DAILY_LIMIT_BUCKET_SLOTS).unwrap();
+        assert!(matches!(
+            registry.record_daily_outflow(1, DAILY_LIMIT_BUCKET_SLOTS),
+            Err(e) if e == BridgeError::DailyLimitExceeded.into()
+        ));
+        // A failed release records nothing
+        let used: u64 = registry.daily_outflow.iter().map(|bucket| bucket.volume).sum();
+        assert_eq!(used, 1_000);
+    }
+
+    #[test]
+    fn daily_limit_rolls_over_by_bucket() {
+        let mut registry = registry(0, 0);
+        registry.daily_limit = 1_000;
+        registry.record_daily_outflow(400, 0).unwrap();
+        registry.record_daily_outflow(600, 5 * DAILY_LIMIT_BUCKET_SLOTS).unwrap();
+        // The first bucket frees a day after it opened, the rest later
+        let day = DAILY_LIMIT_BUCKETS as u64 * DAILY_LIMIT_BUCKET_SLOTS;
+        assert!(registry.record_daily_outflow(1, day - 1).is_err());
+        registry.record_daily_outflow(400, day).unwrap();
+        assert!(registry.record_daily_outflow(1, day).is_err());
+        registry.record_daily_outflow(600, day + 5 * DAILY_LIMIT_BUCKET_SLOTS).unwrap();
+        // Days later every bucket is stale
+        registry.record_daily_outflow(1_000, 10 * day).unwrap();
+    }
+
+    #[test]
+    fn daily_limit_off() {
+        let mut registry = registry(0, 0);
+        registry.record_daily_outflow(u64::MAX, 0).unwrap();
+        registry.record_daily_outflow(u64::MAX, 0).unwrap();
+        // Setting a limit later counts only outflow from then on
+        registry.daily_limit = 1;
+        registry.record_daily_outflow(1, 0).unwrap();
+    }
+
+    /// 0.3% base fee, rising by up to 2% above 80% utilization
+    fn fee_curve() -> TokenRegistry {
+        let mut registry = registry(0, 0);
+        registry.outflow_fee_bps = 30;
+        registry.utilization_kink_bps = 8_000;
+        registry.utilization_surcharge_bps = 200;
+        registry
+    }
+
+    #[test]
+    fn outflow_fee_up_to_kink() {
+        let registry = fee_curve();
+        assert_eq!(registry.outflow_fee_bps(0, 100), 30);
+        assert_eq!(registry.outflow_fee_bps(50, 100), 30);
+        assert_eq!(registry.outflow_fee_bps(80, 100), 30);
+    }
+
+    #[test]
+    fn outflow_fee_above_kink() {
+        let registry = fee_curve();
+        // Halfway from the kink to full utilization
+        assert_eq!(registry.outflow_fee_bps(90, 100), 130);
+        // 8001 bps is 1/2000 of the way, which rounds down to nothing
+        assert_eq!(registry.outflow_fee_bps(8_001, 10_000), 30);
+        assert_eq!(registry.outflow_fee_bps(100, 100), 230);
+        // Past the vault balance counts as full
+        assert_eq!(registry.outflow_fee_bps(u64::MAX, 100), 230);
+    }
+
+    #[test]
+    fn outflow_fee_empty_vault() {
+        let registry = fee_curve();
+        assert_eq!(registry.outflow_fee_bps(1, 0), 230);
+        assert_eq!(registry.outflow_fee_bps(0, 0), 230);
+    }
+
+    #[test]
+    fn outflow_fee_without_kink() {
+        let mut registry = fee_curve();
+        registry.utilization_kink_bps = 0;
+        assert_eq!(registry.outflow_fee_bps(50, 100), 130);
+        registry.utilization_surcharge_bps = 0;
+        assert_eq!(registry.outflow_fee_bps(100, 100), 30);
+    }
+
+    fn bitmap(page_index: u64) -> ReplayBitmap {
+        let mut bitmap = ReplayBitmap::deserialize(&mut &vec![0; ReplayBitmap::INIT_SPACE][..]).unwrap();
+        bitmap.page_index = page_index;
+        bitmap
+    }
+
+    fn is_already_processed(r: Result<()>) -> bool {
+        matches!(r, Err(e) if e == BridgeError::AlreadyProcessed.into())
+    }
+
+    #[test]
+    fn replay_bitmap_rejects_double_consumption() {
+        let mut page = bitmap(0);
+        page.consume(7).unwrap();
+        assert!(is_already_processed(page.consume(7)));
+        // Neighbours in the same byte are untouched
+        page.consume(6).unwrap();
+        page.consume(8).unwrap();
+        assert_eq!(page.bits[0], 0b1100_0000);
+        assert_eq!(page.bits[1], 0b0000_0001);
+    }
+
+    #[test]
+    fn replay_bitmap_page_boundaries() {
+        let last = NONCES_PER_BITMAP_PAGE - 1;
+        let mut first_page = bitmap(0);
+        first_page.consume(0).unwrap();
+        first_page.consume(last).unwrap();
+        assert_eq!(first_page.bits[REPLAY_BITMAP_BYTES - 1], 0b1000_0000);
+        assert!(is_already_processed(first_page.consume(last)));
+
+        // The next page starts over at its first bit
+        let mut second_page = bitmap(1);
+        second_page.consume(NONCES_PER_BITMAP_PAGE).unwrap();
+        assert_eq!(second_page.bits[0], 1);
+        assert!(is_already_processed(second_page.consume(NONCES_PER_BITMAP_PAGE)));
+        second_page.consume(2 * NONCES_PER_BITMAP_PAGE - 1).unwrap();
+
+        let mut top_page = bitmap(u64::MAX / NONCES_PER_BITMAP_PAGE);
+        top_page.consume(u64::MAX).unwrap();
+        assert!(is_already_processed(top_page.consume(u64::MAX)));
+    }
+
+    /// An enabled route that `format` accepts as registered
+    fn route(format: AddressFormat, address_len: u8, bech32_hrp: &str) -> ChainRoute {
+        format.check_route(address_len, bech32_hrp).unwrap();
+        let mut route = ChainRoute::deserialize(&mut &vec![0; ChainRoute::INIT_SPACE][..]).unwrap();
+        route.enabled = true;
+        route.address_len = address_len;
+        route.address_format = format;
+        route.bech32_hrp = bech32_hrp.to_string();
+        route
+    }
+
+    fn fails_with(r: Result<()>, error: BridgeError) -> bool {
+        matches!(r, Err(e) if e == error.into())
+    }
+
+    #[test]
+    fn route_length_must_fit_format() {
+        assert!(fails_with(AddressFormat::Evm.check_route(32, ""), BridgeError::InvalidEvmAddress));
+        assert!(fails_with(AddressFormat::Solana.check_route(20, ""), BridgeError::InvalidSolanaAddress));
+        // Only bech32 routes carry a prefix
+        assert!(fails_with(AddressFormat::Raw.check_route(20, "bc"), BridgeError::InvalidTargetAddress));
+        assert!(fails_with(AddressFormat::Evm.check_route(20, "bc"), BridgeError::InvalidEvmAddress));
+        // Too short for prefix, separator and checksum
+        assert!(fails_with(AddressFormat::Bech32.check_route(9, "bc"), BridgeError::InvalidBech32Address));
+        assert!(AddressFormat::Bech32.check_route(10, "bc").is_ok());
+        assert!(fails_with(AddressFormat::Bech32.check_route(42, ""), BridgeError::InvalidBech32Address));
+        assert!(fails_with(AddressFormat::Bech32.check_route(42, "BC"), BridgeError::InvalidBech32Address));
+        let long_hrp = "a".repeat(MAX_BECH32_HRP_LEN + 1);
+        assert!(fails_with(AddressFormat::Bech32.check_route(90, &long_hrp), BridgeError::InvalidBech32Address));
+    }
+
+    #[test]
+    fn solana_address_to_evm_route_is_refused() {
+        let evm = route(AddressFormat::Evm, EVM_ADDRESS_LEN, "");
+        assert!(evm.validate_target(&[0x11; 20]).is_ok());
+        assert!(fails_with(evm.validate_target(Pubkey::new_unique().as_ref()), BridgeError::InvalidEvmAddress));
+        assert!(fails_with(evm.validate_target(&[0; 20]), BridgeError::InvalidEvmAddress));
+
+        let solana = route(AddressFormat::Solana, SOLANA_ADDRESS_LEN, "");
+        assert!(solana.validate_target(Pubkey::new_unique().as_ref()).is_ok());
+        assert!(fails_with(solana.validate_target(&[0x11; 20]), BridgeError::InvalidSolanaAddress));
+        assert!(fails_with(solana.validate_target(&[0; 32]), BridgeError::InvalidSolanaAddress));
+    }
+
+    #[test]
+    fn raw_and_bech32_targets() {
+        let raw = route(AddressFormat::Raw, 25, "");
+        assert!(raw.validate_target(&[0x11; 25]).is_ok());
+        assert!(fails_with(raw.validate_target(&[0x11; 24]), BridgeError::InvalidTargetAddress));
+        assert!(fails_with(raw.validate_target(&[0; 25]), BridgeError::InvalidTargetAddress));
+
+        let bech32 = route(AddressFormat::Bech32, BECH32_ADDRESS.len() as u8, "bc");
+        assert!(bech32.validate_target(BECH32_ADDRESS).is_ok());
+        // Right length, but an EVM address and a broken checksum
+        let mut evm_hex = b"0x".to_vec();
+        evm_hex.extend_from_slice(&[b'a'; 40]);
+        assert!(fails_with(bech32.validate_target(&evm_hex), BridgeError::InvalidBech32Address));
+        let mut typo = BECH32_ADDRESS.to_vec();
+        typo[10] = b'p';
+        assert!(fails_with(bech32.validate_target(&typo), BridgeError::InvalidBech32Address));
+    }
+
+    #[test]
+    fn disabled_route_refuses_any_target() {
+        let mut evm = route(AddressFormat::Evm, EVM_ADDRESS_LEN, "");
+        evm.enabled = false;
+        assert!(fails_with(evm.validate_target(&[0x11; 20]), BridgeError::RouteDisabled));
+    }
+}
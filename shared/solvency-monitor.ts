@@ -0,0 +1,55 @@
+// Checks each transfer event's post-transfer balance against the previous
+// event for the same mint. Locked / Released carry the vault balance and
+// WrappedMinted / WrappedBurned the wrapped supply, read back on-chain after
+// the transfer, so drift shows up on the event that caused it.
+export type BalanceEvent =
+  | { kind: "locked"; mint: string; netAmount: bigint; vaultBalance: bigint }
+  | { kind: "released"; mint: string; amount: bigint; fee: bigint; vaultBalance: bigint }
+  | { kind: "wrappedMinted"; mint: string; minted: bigint; wrappedSupply: bigint }
+  | { kind: "wrappedBurned"; mint: string; netAmount: bigint; wrappedSupply: bigint };
+
+export interface BalanceMismatch {
+  kind: BalanceEvent["kind"];
+  mint: string;
+  previous: bigint;
+  actual: bigint;
+  // Inclusive range the balance should have landed in
+  expectedMin: bigint;
+  expectedMax: bigint;
+}
+
+export class SolvencyMonitor {
+  // Last reported balance per vault or wrapped mint
+  private balances: Map<string, bigint> = new Map();
+
+  // Feed events in emission order. The first event for a mint only sets its
+  // baseline. Instructions that move the vault without one of these events
+  // (liquidity, refunds, surplus sweeps) are reported too, as unexplained drift.
+  observe(event: BalanceEvent): BalanceMismatch | null {
+    const [key, actual, min, max] = this.expected(event);
+    const previous = this.balances.get(key);
+    this.balances.set(key, actual);
+    if (previous === undefined) return null;
+
+    const expectedMin = previous + min;
+    const expectedMax = previous + max;
+    if (actual >= expectedMin && actual <= expectedMax) return null;
+    return { kind: event.kind, mint: event.mint, previous, actual, expectedMin, expectedMax };
+  }
+
+  // Storage key, reported balance and the range of the change it implies
+  private expected(event: BalanceEvent): [string, bigint, bigint, bigint] {
+    switch (event.kind) {
+      case "locked":
+        return [`vault:${event.mint}`, event.vaultBalance, event.netAmount, event.netAmount];
+      case "released":
+        // The liquidity providers' part of the fee stays in the vault, and
+        // the event does not split it out of `fee`
+        return [`vault:${event.mint}`, event.vaultBalance, -event.amount, event.fee - event.amount];
+      case "wrappedMinted":
+        return [`supply:${event.mint}`, event.wrappedSupply, event.minted, event.minted];
+      case "wrappedBurned":
+        return [`supply:${event.mint}`, event.wrappedSupply, -event.netAmount, -event.netAmount];
+    }
+  }
+}
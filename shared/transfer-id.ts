@@ -0,0 +1,47 @@
+import { concat, getBytes, keccak256, toBeHex, zeroPadValue, type BytesLike } from "ethers";
+
+// Canonical id of a cross-chain transfer. The Solana program's `transfer_id`,
+// contracts/TransferId.sol and this module must agree byte for byte:
+//
+//   keccak256(sourceChain || destChain || sourceAddr || destAddr || token || amount || nonce)
+//
+// Chains are 32-byte ids. Addresses and the token, its address on its home
+// chain, are 32-byte words (see transferIdWord). amount, in the units the
+// source event carries, and the source nonce are big-endian uint64s.
+export interface TransferIdFields {
+  sourceChain: BytesLike;
+  destChain: BytesLike;
+  sourceAddr: BytesLike;
+  destAddr: BytesLike;
+  token: BytesLike;
+  amount: bigint;
+  nonce: bigint;
+}
+
+// Left-padded with zeroes when shorter than 32 bytes, hashed when longer
+export function transferIdWord(addr: BytesLike): Uint8Array {
+  const bytes = getBytes(addr);
+  return bytes.length > 32 ? getBytes(keccak256(bytes)) : getBytes(zeroPadValue(bytes, 32));
+}
+
+function chainId(id: BytesLike): Uint8Array {
+  const bytes = getBytes(id);
+  if (bytes.length !== 32) {
+    throw new Error(`Chain ids are 32 bytes, got ${bytes.length}`);
+  }
+  return bytes;
+}
+
+export function transferId(fields: TransferIdFields): string {
+  return keccak256(
+    concat([
+      chainId(fields.sourceChain),
+      chainId(fields.destChain),
+      transferIdWord(fields.sourceAddr),
+      transferIdWord(fields.destAddr),
+      transferIdWord(fields.token),
+      toBeHex(fields.amount, 8),
+      toBeHex(fields.nonce, 8),
+    ])
+  );
+}
@@ -0,0 +1,85 @@
+import { expect } from "chai";
+import { ethers } from "hardhat";
+import { transferId, type TransferIdFields } from "../shared/transfer-id";
+
+const chain = (n: number) => "0x" + n.toString(16).padStart(64, "0");
+const repeat = (byte: string, len: number) => "0x" + byte.repeat(len);
+
+// Computed by the Solana program's `transfer_id`; every implementation must
+// reproduce these exactly
+const vectors: { name: string; fields: TransferIdFields; id: string }[] = [
+  {
+    name: "Solana lock to an EVM address",
+    fields: {
+      sourceChain: chain(1),
+      destChain: chain(2),
+      sourceAddr: repeat("11", 32),
+      destAddr: repeat("22", 20),
+      token: repeat("33", 32),
+      amount: 1_000_000n,
+      nonce: 7n,
+    },
+    id: "0xa9d2e46bc24acda518d8079c23a93bd622989ceb60ab916e1700a2b466717098",
+  },
+  {
+    name: "EVM lock to Solana with the largest amount",
+    fields: {
+      sourceChain: chain(2),
+      destChain: chain(1),
+      sourceAddr: repeat("aa", 20),
+      destAddr: repeat("bb", 32),
+      token: repeat("cc", 20),
+      amount: 2n ** 64n - 1n,
+      nonce: 0n,
+    },
+    id: "0x4acd7c47e25eb3129c8a9c492e237cce1d2f28fd1442c63add3ed3ed22353788",
+  },
+  {
+    name: "destination address longer than a word",
+    fields: {
+      sourceChain: chain(1),
+      destChain: chain(3),
+      sourceAddr: repeat("11", 32),
+      destAddr: ethers.toUtf8Bytes("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu"),
+      token: repeat("33", 32),
+      amount: 42n,
+      nonce: 1n,
+    },
+    id: "0xafdab30939068ff5af33ac99d402d563aa3eac7cc04e6a4e3a2610848478fd7d",
+  },
+];
+
+describe("TransferId", function () {
+  describe("TypeScript", function () {
+    for (const { name, fields, id } of vectors) {
+      it(`matches the program for ${name}`, function () {
+        expect(transferId(fields)).to.equal(id);
+      });
+    }
+  });
+
+  describe("Solidity", function () {
+    let library: any;
+
+    before(async function () {
+      const TransferIdFactory = await ethers.getContractFactory("TransferId");
+      library = await TransferIdFactory.deploy();
+      await library.waitForDeployment();
+    });
+
+    for (const { name, fields, id } of vectors) {
+      it(`matches the program for ${name}`, async function () {
+        const result = await library.compute(
+          fields.sourceChain,
+          fields.destChain,
+          fields.sourceAddr,
+          fields.destAddr,
+          fields.token,
+          fields.amount,
+          fields.nonce
+        );
+        expect(result).to.equal(id);
+      });
+    }
+  });
+});